@@ -0,0 +1,20 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    let header = cbindgen::generate(&crate_dir).expect("Unable to generate C header");
+    header.write_to_file(out_dir.join("beacn.h"));
+
+    // The header is checked in, so C users don't need a Rust toolchain to read it. It's only
+    // replaced when asked for, and tests/header.rs fails if it's out of date.
+    if env::var_os("BEACN_UPDATE_HEADER").is_some() {
+        header.write_to_file(crate_dir.join("include").join("beacn.h"));
+    }
+
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=BEACN_UPDATE_HEADER");
+}
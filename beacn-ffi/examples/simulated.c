@@ -0,0 +1,66 @@
+/*
+ * Exercises the C API against a simulated Mic, so it can be run without any hardware.
+ *
+ *   cargo build -p beacn-ffi
+ *   cc beacn-ffi/examples/simulated.c -Ibeacn-ffi/include -Ltarget/debug -lbeacn -o simulated
+ *   LD_LIBRARY_PATH=target/debug ./simulated
+ */
+
+#include <stdio.h>
+#include <string.h>
+#include "beacn.h"
+
+static int check(BeacnStatus status, BeacnStatus expected, const char *what) {
+    if (status != expected) {
+        const char *detail = beacn_last_error();
+        fprintf(stderr, "%s: %s (%s)\n", what, beacn_status_message(status), detail ? detail : "no detail");
+        return 1;
+    }
+    return 0;
+}
+
+int main(void) {
+    BeacnHandle *handle = NULL;
+    char value[32];
+    uint32_t raw = 0;
+    int failures = 0;
+
+    printf("Connected Mics: %zu\n", beacn_device_count());
+
+    if (check(beacn_open_simulated(&handle), BEACN_STATUS_OK, "open")) {
+        return 1;
+    }
+
+    /* By name, using the same formatting as the CLI */
+    failures += check(beacn_set(handle, "led.colour1", "#ff8800"), BEACN_STATUS_OK, "set led.colour1");
+    failures += check(beacn_get(handle, "led.colour1", value, sizeof(value)), BEACN_STATUS_OK, "get led.colour1");
+    printf("led.colour1 = %s\n", value);
+
+    failures += check(beacn_set(handle, "headphones.level", "-12dB"), BEACN_STATUS_OK, "set headphones.level");
+    failures += check(beacn_get(handle, "headphones.level", value, sizeof(value)), BEACN_STATUS_OK, "get headphones.level");
+    printf("headphones.level = %s\n", value);
+
+    /* By protocol group (0x01 is LED) and id (0x05 is brightness), which is the same value by name */
+    failures += check(beacn_set_raw(handle, 0x01, 0x05, 50), BEACN_STATUS_OK, "set_raw brightness");
+    failures += check(beacn_get_raw(handle, 0x01, 0x05, &raw), BEACN_STATUS_OK, "get_raw brightness");
+    printf("led.brightness (raw) = %u\n", raw);
+    failures += check(beacn_get(handle, "led.brightness", value, sizeof(value)), BEACN_STATUS_OK, "get led.brightness");
+    printf("led.brightness = %s\n", value);
+    if (strcmp(value, "50%") != 0) {
+        fprintf(stderr, "led.brightness should be 50%% after setting it raw\n");
+        failures++;
+    }
+
+    /* Errors are reported as status codes, with details from beacn_last_error() */
+    failures += check(beacn_get(handle, "led.nonsense", value, sizeof(value)), BEACN_STATUS_UNKNOWN_PARAMETER, "unknown parameter");
+    failures += check(beacn_set(handle, "led.brightness", "150"), BEACN_STATUS_INVALID_VALUE, "out of range");
+    failures += check(beacn_set(handle, "meter.mic_rms", "0"), BEACN_STATUS_READ_ONLY, "read only");
+    failures += check(beacn_get(handle, "led.colour1", value, 4), BEACN_STATUS_BUFFER_TOO_SMALL, "small buffer");
+    failures += check(beacn_apply_profile(handle, "/nonexistent/profile"), BEACN_STATUS_PROFILE, "missing profile");
+    printf("Last error: %s\n", beacn_last_error());
+
+    beacn_close(handle);
+
+    printf("%s\n", failures ? "FAILED" : "OK");
+    return failures ? 1 : 0;
+}
@@ -0,0 +1,131 @@
+#ifndef BEACN_H
+#define BEACN_H
+
+/* Generated by cbindgen from beacn-ffi, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum BeacnStatus {
+  BEACN_STATUS_OK = 0,
+  BEACN_STATUS_INVALID_ARGUMENT = 1,
+  BEACN_STATUS_DEVICE_NOT_FOUND = 2,
+  BEACN_STATUS_UNKNOWN_PARAMETER = 3,
+  BEACN_STATUS_INVALID_VALUE = 4,
+  BEACN_STATUS_READ_ONLY = 5,
+  BEACN_STATUS_BUFFER_TOO_SMALL = 6,
+  BEACN_STATUS_DEVICE = 7,
+  BEACN_STATUS_PROFILE = 8,
+  BEACN_STATUS_PANIC = 9,
+} BeacnStatus;
+
+/**
+ * An open connection to a Mic, created by beacn_open() and released with beacn_close().
+ */
+typedef struct BeacnHandle BeacnHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns the number of Beacn Mics currently connected.
+ */
+size_t beacn_device_count(void);
+
+/**
+ * Opens the first connected Mic. timeout_ms is how long to wait for each response, or 0 for
+ * the default. On success, *handle must later be passed to beacn_close().
+ *
+ * # Safety
+ * handle must point to writable memory for a BeacnHandle pointer.
+ */
+enum BeacnStatus beacn_open(uint32_t timeout_ms, struct BeacnHandle **handle);
+
+/**
+ * Opens a simulated Mic, which behaves like a real one but needs no hardware.
+ *
+ * # Safety
+ * handle must point to writable memory for a BeacnHandle pointer.
+ */
+enum BeacnStatus beacn_open_simulated(struct BeacnHandle **handle);
+
+/**
+ * Closes a handle from beacn_open(), passing NULL is a no-op.
+ *
+ * # Safety
+ * handle must be NULL or a handle which hasn't already been closed.
+ */
+void beacn_close(struct BeacnHandle *handle);
+
+/**
+ * Reads a parameter by name (see 'beacn-mic-test list'), formatted the same way the CLI prints
+ * it (eg. "#ff0000" or "-20.00dB"). length is the size of buffer, including the terminating NUL.
+ *
+ * # Safety
+ * name must be a NUL terminated string, and buffer must be writable for length bytes.
+ */
+enum BeacnStatus beacn_get(const struct BeacnHandle *handle,
+                           const char *name,
+                           char *buffer,
+                           size_t length);
+
+/**
+ * Changes a parameter by name, the value is parsed the same way as 'beacn-mic-test set'.
+ *
+ * # Safety
+ * name and value must be NUL terminated strings.
+ */
+enum BeacnStatus beacn_set(const struct BeacnHandle *handle, const char *name, const char *value);
+
+/**
+ * Reads a parameter by its protocol group and id, value receives the raw little endian value.
+ *
+ * # Safety
+ * value must point to writable memory for a uint32_t.
+ */
+enum BeacnStatus beacn_get_raw(const struct BeacnHandle *handle,
+                               uint8_t group,
+                               uint16_t id,
+                               uint32_t *value);
+
+/**
+ * Changes a parameter by its protocol group and id, using the raw little endian value. The
+ * value is still checked against the parameter's valid range before being sent.
+ *
+ * # Safety
+ * handle must be a valid handle from beacn_open().
+ */
+enum BeacnStatus beacn_set_raw(const struct BeacnHandle *handle,
+                               uint8_t group,
+                               uint16_t id,
+                               uint32_t value);
+
+/**
+ * Applies a profile, either by name (as saved by 'beacn-mic-test profile save') or by path to a
+ * profile file if it contains a '/'.
+ *
+ * # Safety
+ * profile must be a NUL terminated string.
+ */
+enum BeacnStatus beacn_apply_profile(const struct BeacnHandle *handle, const char *profile);
+
+/**
+ * Returns a static description of a status code.
+ */
+const char *beacn_status_message(enum BeacnStatus status);
+
+/**
+ * Returns a description of the last error on this thread, or NULL if the last call succeeded.
+ * The string is valid until the next beacn_* call on this thread.
+ */
+const char *beacn_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BEACN_H */
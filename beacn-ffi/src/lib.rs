@@ -0,0 +1,279 @@
+// A C ABI over the blocking client, for tools which can't link against Rust directly.
+//
+// Every function returns a BeacnStatus, anything more detailed than that can be fetched with
+// beacn_last_error() on the same thread. The doc comments here end up in include/beacn.h.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+use std::time::Duration;
+use anyhow::anyhow;
+use beacn_lib::blocking::BlockingClient;
+use beacn_lib::device::device_count;
+use beacn_lib::messages::BeacnParameter;
+use beacn_lib::profile::Profile;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BeacnStatus {
+    Ok = 0,
+    InvalidArgument = 1,
+    DeviceNotFound = 2,
+    UnknownParameter = 3,
+    InvalidValue = 4,
+    ReadOnly = 5,
+    BufferTooSmall = 6,
+    Device = 7,
+    Profile = 8,
+    Panic = 9,
+}
+
+/// An open connection to a Mic, created by beacn_open() and released with beacn_close().
+pub struct BeacnHandle {
+    client: BlockingClient,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+struct Failure(BeacnStatus, anyhow::Error);
+
+fn fail<T>(status: BeacnStatus, error: anyhow::Error) -> Result<T, Failure> {
+    Err(Failure(status, error))
+}
+
+// Runs the body, converting any error (or panic) into a status code and recording the message
+fn guard(body: impl FnOnce() -> Result<(), Failure>) -> BeacnStatus {
+    let result = match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(result) => result,
+        Err(_) => Err(Failure(BeacnStatus::Panic, anyhow!("Internal error, please report this"))),
+    };
+
+    let (status, message) = match result {
+        Ok(()) => (BeacnStatus::Ok, None),
+        Err(Failure(status, error)) => {
+            // Messages come from our own errors, but strip any NULs rather than lose the message
+            let message = format!("{:#}", error).replace('\0', "");
+            (status, CString::new(message).ok())
+        }
+    };
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    status
+}
+
+unsafe fn read_str<'a>(value: *const c_char, what: &str) -> Result<&'a str, Failure> {
+    if value.is_null() {
+        return fail(BeacnStatus::InvalidArgument, anyhow!("{} must not be NULL", what));
+    }
+    CStr::from_ptr(value).to_str().or_else(|_| fail(BeacnStatus::InvalidArgument, anyhow!("{} is not valid UTF-8", what)))
+}
+
+unsafe fn read_handle<'a>(handle: *const BeacnHandle) -> Result<&'a BeacnHandle, Failure> {
+    handle.as_ref().ok_or_else(|| Failure(BeacnStatus::InvalidArgument, anyhow!("handle must not be NULL")))
+}
+
+fn find_parameter(name: &str) -> Result<BeacnParameter, Failure> {
+    BeacnParameter::from_name(name).ok_or_else(|| Failure(BeacnStatus::UnknownParameter, anyhow!("Unknown parameter '{}'", name)))
+}
+
+fn find_raw_parameter(group: u8, id: u16) -> Result<BeacnParameter, Failure> {
+    BeacnParameter::from_ids(group, id).ok_or_else(|| {
+        Failure(BeacnStatus::UnknownParameter, anyhow!("Unknown parameter {:#04x}:{:#06x}", group, id))
+    })
+}
+
+fn device_error(error: anyhow::Error) -> Failure {
+    Failure(BeacnStatus::Device, error)
+}
+
+fn open(handle: *mut *mut BeacnHandle, client: impl FnOnce() -> Result<BlockingClient, Failure>) -> BeacnStatus {
+    guard(|| {
+        if handle.is_null() {
+            return fail(BeacnStatus::InvalidArgument, anyhow!("handle must not be NULL"));
+        }
+        let client = client()?;
+        unsafe { *handle = Box::into_raw(Box::new(BeacnHandle { client })) };
+        Ok(())
+    })
+}
+
+/// Returns the number of Beacn Mics currently connected.
+#[no_mangle]
+pub extern "C" fn beacn_device_count() -> usize {
+    catch_unwind(device_count).unwrap_or(0)
+}
+
+/// Opens the first connected Mic. timeout_ms is how long to wait for each response, or 0 for
+/// the default. On success, *handle must later be passed to beacn_close().
+///
+/// # Safety
+/// handle must point to writable memory for a BeacnHandle pointer.
+#[no_mangle]
+pub unsafe extern "C" fn beacn_open(timeout_ms: u32, handle: *mut *mut BeacnHandle) -> BeacnStatus {
+    open(handle, || {
+        if device_count() == 0 {
+            return fail(BeacnStatus::DeviceNotFound, anyhow!("No Beacn Mic found"));
+        }
+
+        let timeout = match timeout_ms {
+            0 => beacn_lib::device::DEFAULT_TIMEOUT,
+            ms => Duration::from_millis(ms as u64),
+        };
+        BlockingClient::open(timeout).map_err(device_error)
+    })
+}
+
+/// Opens a simulated Mic, which behaves like a real one but needs no hardware.
+///
+/// # Safety
+/// handle must point to writable memory for a BeacnHandle pointer.
+#[no_mangle]
+pub unsafe extern "C" fn beacn_open_simulated(handle: *mut *mut BeacnHandle) -> BeacnStatus {
+    open(handle, || Ok(BlockingClient::open_simulated()))
+}
+
+/// Closes a handle from beacn_open(), passing NULL is a no-op.
+///
+/// # Safety
+/// handle must be NULL or a handle which hasn't already been closed.
+#[no_mangle]
+pub unsafe extern "C" fn beacn_close(handle: *mut BeacnHandle) {
+    if !handle.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(handle))));
+    }
+}
+
+/// Reads a parameter by name (see 'beacn-mic-test list'), formatted the same way the CLI prints
+/// it (eg. "#ff0000" or "-20.00dB"). length is the size of buffer, including the terminating NUL.
+///
+/// # Safety
+/// name must be a NUL terminated string, and buffer must be writable for length bytes.
+#[no_mangle]
+pub unsafe extern "C" fn beacn_get(handle: *const BeacnHandle, name: *const c_char, buffer: *mut c_char, length: usize) -> BeacnStatus {
+    guard(|| {
+        let handle = read_handle(handle)?;
+        let param = find_parameter(read_str(name, "name")?)?;
+        if buffer.is_null() {
+            return fail(BeacnStatus::InvalidArgument, anyhow!("buffer must not be NULL"));
+        }
+
+        let value = handle.client.get_value(param).map_err(device_error)?;
+        let value = param.schema().format(value);
+        if value.len() >= length {
+            return fail(BeacnStatus::BufferTooSmall, anyhow!("{} needs a buffer of {} bytes", value, value.len() + 1));
+        }
+
+        ptr::copy_nonoverlapping(value.as_ptr() as *const c_char, buffer, value.len());
+        *buffer.add(value.len()) = 0;
+        Ok(())
+    })
+}
+
+/// Changes a parameter by name, the value is parsed the same way as 'beacn-mic-test set'.
+///
+/// # Safety
+/// name and value must be NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn beacn_set(handle: *const BeacnHandle, name: *const c_char, value: *const c_char) -> BeacnStatus {
+    guard(|| {
+        let handle = read_handle(handle)?;
+        let param = find_parameter(read_str(name, "name")?)?;
+        let schema = param.schema();
+        if schema.read_only {
+            return fail(BeacnStatus::ReadOnly, anyhow!("{} is read only", schema.name));
+        }
+
+        let value = schema.parse(read_str(value, "value")?).map_err(|e| Failure(BeacnStatus::InvalidValue, e))?;
+        let value = schema.encode(value).map_err(|e| Failure(BeacnStatus::InvalidValue, e))?;
+        handle.client.set(param, value).map_err(device_error)?;
+        Ok(())
+    })
+}
+
+/// Reads a parameter by its protocol group and id, value receives the raw little endian value.
+///
+/// # Safety
+/// value must point to writable memory for a uint32_t.
+#[no_mangle]
+pub unsafe extern "C" fn beacn_get_raw(handle: *const BeacnHandle, group: u8, id: u16, value: *mut u32) -> BeacnStatus {
+    guard(|| {
+        let handle = read_handle(handle)?;
+        let param = find_raw_parameter(group, id)?;
+        if value.is_null() {
+            return fail(BeacnStatus::InvalidArgument, anyhow!("value must not be NULL"));
+        }
+
+        *value = u32::from_le_bytes(handle.client.fetch(param).map_err(device_error)?);
+        Ok(())
+    })
+}
+
+/// Changes a parameter by its protocol group and id, using the raw little endian value. The
+/// value is still checked against the parameter's valid range before being sent.
+///
+/// # Safety
+/// handle must be a valid handle from beacn_open().
+#[no_mangle]
+pub unsafe extern "C" fn beacn_set_raw(handle: *const BeacnHandle, group: u8, id: u16, value: u32) -> BeacnStatus {
+    guard(|| {
+        let handle = read_handle(handle)?;
+        let param = find_raw_parameter(group, id)?;
+        let schema = param.schema();
+        if schema.read_only {
+            return fail(BeacnStatus::ReadOnly, anyhow!("{} is read only", schema.name));
+        }
+
+        let value = value.to_le_bytes();
+        schema.validate(value).map_err(|e| Failure(BeacnStatus::InvalidValue, e))?;
+        handle.client.set(param, value).map_err(device_error)?;
+        Ok(())
+    })
+}
+
+/// Applies a profile, either by name (as saved by 'beacn-mic-test profile save') or by path to a
+/// profile file if it contains a '/'.
+///
+/// # Safety
+/// profile must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn beacn_apply_profile(handle: *const BeacnHandle, profile: *const c_char) -> BeacnStatus {
+    guard(|| {
+        let handle = read_handle(handle)?;
+        let profile = read_str(profile, "profile")?;
+
+        let profile = match profile.contains('/') {
+            true => Profile::load(Path::new(profile)),
+            false => Profile::load_named(profile),
+        };
+        let profile = profile.map_err(|e| Failure(BeacnStatus::Profile, e))?;
+        handle.client.apply_profile(&profile).map_err(device_error)
+    })
+}
+
+/// Returns a static description of a status code.
+#[no_mangle]
+pub extern "C" fn beacn_status_message(status: BeacnStatus) -> *const c_char {
+    let message: &CStr = match status {
+        BeacnStatus::Ok => c"Success",
+        BeacnStatus::InvalidArgument => c"Invalid argument",
+        BeacnStatus::DeviceNotFound => c"No Beacn Mic found",
+        BeacnStatus::UnknownParameter => c"Unknown parameter",
+        BeacnStatus::InvalidValue => c"Invalid value for parameter",
+        BeacnStatus::ReadOnly => c"Parameter is read only",
+        BeacnStatus::BufferTooSmall => c"Buffer too small",
+        BeacnStatus::Device => c"Error communicating with the Mic",
+        BeacnStatus::Profile => c"Unable to load profile",
+        BeacnStatus::Panic => c"Internal error",
+    };
+    message.as_ptr()
+}
+
+/// Returns a description of the last error on this thread, or NULL if the last call succeeded.
+/// The string is valid until the next beacn_* call on this thread.
+#[no_mangle]
+pub extern "C" fn beacn_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
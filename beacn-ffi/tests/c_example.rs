@@ -0,0 +1,39 @@
+// Builds examples/simulated.c against the library, and checks it runs cleanly
+use std::env;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn simulated_example_runs() {
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    // Tests run from target/<profile>/deps, next to where the library is built
+    let exe = env::current_exe().unwrap();
+    let library_dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+    let output = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("simulated");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| String::from("cc"));
+    let compiled = Command::new(&compiler)
+        .arg(crate_dir.join("examples").join("simulated.c"))
+        .arg("-I").arg(crate_dir.join("include"))
+        .arg("-L").arg(library_dir)
+        .arg("-lbeacn")
+        .arg("-o").arg(&output)
+        .status();
+    match compiled {
+        Ok(status) => assert!(status.success(), "Unable to compile examples/simulated.c"),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("No C compiler ({}), skipping", compiler);
+            return;
+        }
+        Err(e) => panic!("Unable to run {}: {}", compiler, e),
+    }
+
+    let run = Command::new(&output).env("LD_LIBRARY_PATH", library_dir).output().unwrap();
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    assert!(run.status.success(), "examples/simulated.c failed:\n{}{}", stdout, String::from_utf8_lossy(&run.stderr));
+    assert!(stdout.contains("led.colour1 = #ff8800"), "{}", stdout);
+    assert!(stdout.contains("led.brightness = 50%"), "{}", stdout);
+    assert!(stdout.ends_with("OK\n"), "{}", stdout);
+}
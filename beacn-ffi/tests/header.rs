@@ -0,0 +1,7 @@
+// The checked in header has to match what cbindgen makes of the current source
+#[test]
+fn header_is_up_to_date() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/beacn.h"));
+    let committed = include_str!("../include/beacn.h");
+    assert!(generated == committed, "include/beacn.h is out of date, rebuild with BEACN_UPDATE_HEADER=1 to update it");
+}
@@ -0,0 +1,219 @@
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail, Result};
+use strum::IntoEnumIterator;
+use tokio::sync::oneshot::error::TryRecvError;
+use crate::client::BeacnClient;
+use crate::device::{BeacnDevice, DeviceInfo, DEFAULT_TIMEOUT};
+use crate::events::DeviceEvent;
+use crate::messages::{BeacnParameter, BeacnValue, Message, MessageValue, RGB};
+use crate::messages::headphones::HeadphoneParameter;
+use crate::messages::led::{LEDColour, LEDParameter};
+use crate::messages::mic::MicParameter;
+use crate::messages::schema::ParameterValue;
+use crate::profile::Profile;
+use crate::state::DeviceState;
+
+// How often we check for a response when going through a device handler
+const RESPONSE_POLL: Duration = Duration::from_millis(5);
+
+enum Connection {
+    // We own the device, and talk to it directly on the calling thread
+    Direct(Mutex<BeacnDevice>),
+
+    // Something else owns the device (such as a BeacnClient in the same process)
+    Handler(BeacnClient),
+}
+
+// A client for code which can't (or doesn't want to) run inside a tokio runtime, every call
+// blocks until the device responds, or the timeout is hit.
+pub struct BlockingClient {
+    connection: Connection,
+    info: DeviceInfo,
+    timeout: Duration,
+}
+
+impl BlockingClient {
+    pub fn open(timeout: Duration) -> Result<Self> {
+        let device = BeacnDevice::open_with_timeout(timeout)?;
+        let info = device.info().clone();
+
+        Ok(Self {
+            connection: Connection::Direct(Mutex::new(device)),
+            info,
+            timeout,
+        })
+    }
+
+    pub fn open_simulated() -> Self {
+        let device = BeacnDevice::open_simulated();
+        Self {
+            info: device.info().clone(),
+            connection: Connection::Direct(Mutex::new(device)),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    // Wraps an existing client, the timeout is how long to wait for the handler to respond.
+    pub fn from_client(client: BeacnClient, timeout: Duration) -> Self {
+        Self {
+            info: client.info().clone(),
+            connection: Connection::Handler(client),
+            timeout,
+        }
+    }
+
+    pub fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    pub fn send(&self, message: Message) -> Result<BeacnValue> {
+        match &self.connection {
+            Connection::Direct(device) => {
+                let device = device.lock().map_err(|_| anyhow!("Device Lock Poisoned"))?;
+                match message {
+                    Message::FETCH(param) => device.fetch(param),
+                    Message::SET((param, value)) => device.set(param, value),
+
+                    // There's no handler to stop, the device is released when we're dropped.
+                    Message::QUIT => Ok([0, 0, 0, 0]),
+                }
+            }
+            Connection::Handler(client) => {
+                let mut response = client.queue(message)?;
+
+                let deadline = Instant::now() + self.timeout;
+                while Instant::now() < deadline {
+                    match response.try_recv() {
+                        Ok(value) => return Ok(value),
+                        Err(TryRecvError::Empty) => sleep(RESPONSE_POLL),
+                        Err(TryRecvError::Closed) => bail!("Device was unable to handle the message"),
+                    }
+                }
+                bail!("Did not receive a response in time");
+            }
+        }
+    }
+
+    pub fn fetch(&self, param: BeacnParameter) -> Result<BeacnValue> {
+        self.send(Message::FETCH(param))
+    }
+
+    pub fn set(&self, param: BeacnParameter, value: BeacnValue) -> Result<BeacnValue> {
+        param.schema().validate(value)?;
+        self.send(Message::SET((param, value)))
+    }
+
+    pub fn get_value(&self, param: BeacnParameter) -> Result<ParameterValue> {
+        Ok(param.schema().decode(self.fetch(param)?))
+    }
+
+    pub fn set_value(&self, param: BeacnParameter, value: ParameterValue) -> Result<ParameterValue> {
+        let schema = param.schema();
+        let value = self.set(param, schema.encode(value)?)?;
+        Ok(schema.decode(value))
+    }
+
+    pub fn fetch_state(&self) -> Result<DeviceState> {
+        let mut state = DeviceState::default();
+        for param in LEDParameter::iter() {
+            state.set_led_param(param, self.fetch(BeacnParameter::LED(param))?);
+        }
+        for param in HeadphoneParameter::iter() {
+            state.set_headphone_param(param, self.fetch(BeacnParameter::Headphones(param))?);
+        }
+        for param in MicParameter::iter() {
+            state.set_mic_param(param, self.fetch(BeacnParameter::Mic(param))?);
+        }
+        Ok(state)
+    }
+
+    // Reads everything that belongs in a profile from the device
+    pub fn capture_profile(&self) -> Result<Profile> {
+        let mut profile = Profile::default();
+        for param in Profile::parameters() {
+            profile.set(param, self.get_value(param)?);
+        }
+        Ok(profile)
+    }
+
+    pub fn apply_profile(&self, profile: &Profile) -> Result<()> {
+        for (param, value) in &profile.values {
+            self.set_value(*param, *value)?;
+        }
+        if let Connection::Handler(client) = &self.connection {
+            client.notify(DeviceEvent::ProfileApplied(profile.name.clone()));
+        }
+        Ok(())
+    }
+
+    pub fn get_led_mode(&self) -> Result<u32> {
+        self.get(BeacnParameter::LED(LEDParameter::Mode))
+    }
+
+    pub fn set_led_mode(&self, mode: u32) -> Result<u32> {
+        self.put(BeacnParameter::LED(LEDParameter::Mode), mode)
+    }
+
+    pub fn get_led_colour(&self, colour: LEDColour) -> Result<RGB> {
+        self.get(BeacnParameter::LED(colour.parameter()))
+    }
+
+    pub fn set_led_colour(&self, colour: LEDColour, value: RGB) -> Result<RGB> {
+        self.put(BeacnParameter::LED(colour.parameter()), value)
+    }
+
+    pub fn get_led_brightness(&self) -> Result<i32> {
+        self.get(BeacnParameter::LED(LEDParameter::Brightness))
+    }
+
+    pub fn set_led_brightness(&self, brightness: i32) -> Result<i32> {
+        self.put(BeacnParameter::LED(LEDParameter::Brightness), brightness)
+    }
+
+    pub fn get_led_speed(&self) -> Result<i32> {
+        self.get(BeacnParameter::LED(LEDParameter::Speed))
+    }
+
+    pub fn set_led_speed(&self, speed: i32) -> Result<i32> {
+        self.put(BeacnParameter::LED(LEDParameter::Speed), speed)
+    }
+
+    pub fn get_headphone_level(&self) -> Result<f32> {
+        self.get(BeacnParameter::Headphones(HeadphoneParameter::Level))
+    }
+
+    pub fn set_headphone_level(&self, level: f32) -> Result<f32> {
+        self.put(BeacnParameter::Headphones(HeadphoneParameter::Level), level)
+    }
+
+    pub fn get_muted(&self) -> Result<bool> {
+        self.get(BeacnParameter::Mic(MicParameter::Mute))
+    }
+
+    pub fn set_muted(&self, muted: bool) -> Result<bool> {
+        self.put(BeacnParameter::Mic(MicParameter::Mute), muted)
+    }
+
+    pub fn toggle_mute(&self) -> Result<bool> {
+        let muted = self.get_muted()?;
+        self.set_muted(!muted)
+    }
+
+    fn get<T>(&self, param: BeacnParameter) -> Result<T>
+    where
+        MessageValue<T>: From<BeacnValue>,
+    {
+        Ok(MessageValue::<T>::from(self.fetch(param)?).0)
+    }
+
+    fn put<T>(&self, param: BeacnParameter, value: T) -> Result<T>
+    where
+        MessageValue<T>: From<BeacnValue>,
+        BeacnValue: From<MessageValue<T>>,
+    {
+        let value = self.set(param, BeacnValue::from(MessageValue(value)))?;
+        Ok(MessageValue::<T>::from(value).0)
+    }
+}
@@ -0,0 +1,215 @@
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use strum::IntoEnumIterator;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task;
+use crate::blocking::BlockingClient;
+use crate::device::{spawn_device_handler, BeacnDevice, DeviceInfo};
+use crate::events::DeviceEvent;
+use crate::messages::{BeacnParameter, BeacnValue, Message, MessageValue, RGB};
+use crate::messages::headphones::HeadphoneParameter;
+use crate::messages::led::{LEDColour, LEDParameter};
+use crate::messages::mic::MicParameter;
+use crate::messages::schema::ParameterValue;
+use crate::meter::MeterLevels;
+use crate::profile::Profile;
+use crate::state::DeviceState;
+
+// A handle to a running device handler, this can be cloned and shared around as needed.
+#[derive(Clone)]
+pub struct BeacnClient {
+    info: DeviceInfo,
+    sender: mpsc::Sender<(Message, oneshot::Sender<BeacnValue>)>,
+    meters: broadcast::Sender<MeterLevels>,
+    events: broadcast::Sender<DeviceEvent>,
+}
+
+impl BeacnClient {
+    // Locates the Mic and spawns a handler for it on the current tokio runtime
+    pub async fn connect() -> Result<Self> {
+        Ok(Self::from_device(BeacnDevice::open()?))
+    }
+
+    pub fn connect_simulated() -> Self {
+        Self::from_device(BeacnDevice::open_simulated())
+    }
+
+    pub fn from_device(device: BeacnDevice) -> Self {
+        let (sender, receiver) = mpsc::channel(30);
+        let (meters, _) = broadcast::channel(5);
+        // Applying a profile sends a burst of changes, so leave some room for those
+        let (events, _) = broadcast::channel(64);
+
+        let info = device.info().clone();
+        task::spawn(spawn_device_handler(device, receiver, meters.clone(), events.clone()));
+
+        Self {
+            info,
+            sender,
+            meters,
+            events,
+        }
+    }
+
+    pub fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    // Gives a client which can be used from threads outside the runtime, see BlockingClient
+    pub fn blocking(&self, timeout: Duration) -> BlockingClient {
+        BlockingClient::from_client(self.clone(), timeout)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.events.subscribe()
+    }
+
+    // The device is only polled for levels while at least one of these is alive
+    pub fn subscribe_meters(&self) -> broadcast::Receiver<MeterLevels> {
+        self.meters.subscribe()
+    }
+
+    // For events which happen in the client rather than the handler, nobody listening is fine
+    pub(crate) fn notify(&self, event: DeviceEvent) {
+        let _ = self.events.send(event);
+    }
+
+    pub async fn send(&self, message: Message) -> Result<BeacnValue> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.sender.send((message, response_tx)).await?;
+        Ok(response_rx.await?)
+    }
+
+    // Queues a message without waiting, for callers who can't await (such as a UI thread).
+    pub fn queue(&self, message: Message) -> Result<oneshot::Receiver<BeacnValue>> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.sender.try_send((message, response_tx)).map_err(|e| anyhow!("Unable to queue message: {}", e))?;
+        Ok(response_rx)
+    }
+
+    pub async fn fetch(&self, param: BeacnParameter) -> Result<BeacnValue> {
+        self.send(Message::FETCH(param)).await
+    }
+
+    pub async fn set(&self, param: BeacnParameter, value: BeacnValue) -> Result<BeacnValue> {
+        param.schema().validate(value)?;
+        self.send(Message::SET((param, value))).await
+    }
+
+    // Fetches and sets using the schema types, so callers don't need to know the wire format
+    pub async fn get_value(&self, param: BeacnParameter) -> Result<ParameterValue> {
+        Ok(param.schema().decode(self.fetch(param).await?))
+    }
+
+    pub async fn set_value(&self, param: BeacnParameter, value: ParameterValue) -> Result<ParameterValue> {
+        let schema = param.schema();
+        let value = self.set(param, schema.encode(value)?).await?;
+        Ok(schema.decode(value))
+    }
+
+    pub async fn fetch_state(&self) -> Result<DeviceState> {
+        let mut state = DeviceState::default();
+        for param in LEDParameter::iter() {
+            state.set_led_param(param, self.fetch(BeacnParameter::LED(param)).await?);
+        }
+        for param in HeadphoneParameter::iter() {
+            state.set_headphone_param(param, self.fetch(BeacnParameter::Headphones(param)).await?);
+        }
+        for param in MicParameter::iter() {
+            state.set_mic_param(param, self.fetch(BeacnParameter::Mic(param)).await?);
+        }
+        Ok(state)
+    }
+
+    // Reads everything that belongs in a profile from the device
+    pub async fn capture_profile(&self) -> Result<Profile> {
+        let mut profile = Profile::default();
+        for param in Profile::parameters() {
+            profile.set(param, self.get_value(param).await?);
+        }
+        Ok(profile)
+    }
+
+    pub async fn apply_profile(&self, profile: &Profile) -> Result<()> {
+        for (param, value) in &profile.values {
+            self.set_value(*param, *value).await?;
+        }
+        self.notify(DeviceEvent::ProfileApplied(profile.name.clone()));
+        Ok(())
+    }
+
+    pub async fn get_led_mode(&self) -> Result<u32> {
+        self.get(BeacnParameter::LED(LEDParameter::Mode)).await
+    }
+
+    pub async fn set_led_mode(&self, mode: u32) -> Result<u32> {
+        self.put(BeacnParameter::LED(LEDParameter::Mode), mode).await
+    }
+
+    pub async fn get_led_colour(&self, colour: LEDColour) -> Result<RGB> {
+        self.get(BeacnParameter::LED(colour.parameter())).await
+    }
+
+    pub async fn set_led_colour(&self, colour: LEDColour, value: RGB) -> Result<RGB> {
+        self.put(BeacnParameter::LED(colour.parameter()), value).await
+    }
+
+    pub async fn get_led_brightness(&self) -> Result<i32> {
+        self.get(BeacnParameter::LED(LEDParameter::Brightness)).await
+    }
+
+    pub async fn set_led_brightness(&self, brightness: i32) -> Result<i32> {
+        self.put(BeacnParameter::LED(LEDParameter::Brightness), brightness).await
+    }
+
+    pub async fn get_led_speed(&self) -> Result<i32> {
+        self.get(BeacnParameter::LED(LEDParameter::Speed)).await
+    }
+
+    pub async fn set_led_speed(&self, speed: i32) -> Result<i32> {
+        self.put(BeacnParameter::LED(LEDParameter::Speed), speed).await
+    }
+
+    pub async fn get_headphone_level(&self) -> Result<f32> {
+        self.get(BeacnParameter::Headphones(HeadphoneParameter::Level)).await
+    }
+
+    pub async fn set_headphone_level(&self, level: f32) -> Result<f32> {
+        self.put(BeacnParameter::Headphones(HeadphoneParameter::Level), level).await
+    }
+
+    pub async fn get_muted(&self) -> Result<bool> {
+        self.get(BeacnParameter::Mic(MicParameter::Mute)).await
+    }
+
+    pub async fn set_muted(&self, muted: bool) -> Result<bool> {
+        self.put(BeacnParameter::Mic(MicParameter::Mute), muted).await
+    }
+
+    pub async fn toggle_mute(&self) -> Result<bool> {
+        let muted = self.get_muted().await?;
+        self.set_muted(!muted).await
+    }
+
+    // Stops the device handler, any other clones of this client will stop working.
+    pub async fn quit(&self) -> Result<()> {
+        self.send(Message::QUIT).await?;
+        Ok(())
+    }
+
+    async fn get<T>(&self, param: BeacnParameter) -> Result<T>
+    where
+        MessageValue<T>: From<BeacnValue>,
+    {
+        Ok(MessageValue::<T>::from(self.fetch(param).await?).0)
+    }
+
+    async fn put<T>(&self, param: BeacnParameter, value: T) -> Result<T>
+    where
+        MessageValue<T>: From<BeacnValue>,
+        BeacnValue: From<MessageValue<T>>,
+    {
+        let value = self.set(param, BeacnValue::from(MessageValue(value))).await?;
+        Ok(MessageValue::<T>::from(value).0)
+    }
+}
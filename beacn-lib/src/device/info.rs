@@ -0,0 +1,77 @@
+use std::fmt::{Display, Formatter};
+use rusb::{Device, DeviceDescriptor, DeviceHandle, GlobalContext};
+use crate::{PID_BEACN_MIC, VID_BEACN_MIC};
+
+// The strings are optional, older firmware has been known to not respond to some of them.
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial: Option<String>,
+
+    // This is the bcdDevice from the descriptor, which is where the firmware reports its version.
+    // It's the only source: none of the parameters we know of (see messages) hold the version, so
+    // there's nothing to fetch. Trying unknown ids to look for one isn't safe, as the device may act
+    // on them.
+    pub version: String,
+
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub bus_number: u8,
+    pub address: u8,
+}
+
+impl DeviceInfo {
+    // The strings can only be read with a handle, without one (eg. when the device is already in
+    // use elsewhere) they're left empty.
+    pub fn from_device(device: &Device<GlobalContext>, handle: Option<&DeviceHandle<GlobalContext>>, descriptor: &DeviceDescriptor) -> Self {
+        let version = descriptor.device_version();
+
+        Self {
+            manufacturer: handle.and_then(|handle| handle.read_manufacturer_string_ascii(descriptor).ok()),
+            product: handle.and_then(|handle| handle.read_product_string_ascii(descriptor).ok()),
+            serial: handle.and_then(|handle| handle.read_serial_number_string_ascii(descriptor).ok()),
+            version: format!("{}.{}.{}", version.major(), version.minor(), version.sub_minor()),
+            vendor_id: descriptor.vendor_id(),
+            product_id: descriptor.product_id(),
+            bus_number: device.bus_number(),
+            address: device.address(),
+        }
+    }
+
+    pub fn simulated() -> Self {
+        Self {
+            manufacturer: Some(String::from("Beacn")),
+            product: Some(String::from("Beacn Mic (Simulated)")),
+            serial: Some(String::from("SIMULATED")),
+            version: String::from("0.0.0"),
+            vendor_id: VID_BEACN_MIC,
+            product_id: PID_BEACN_MIC,
+            bus_number: 0,
+            address: 0,
+        }
+    }
+
+    // Returns a list of labels and values, in the order they should be displayed
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let unknown = || String::from("Unknown");
+
+        vec![
+            ("Manufacturer", self.manufacturer.clone().unwrap_or_else(unknown)),
+            ("Product", self.product.clone().unwrap_or_else(unknown)),
+            ("Serial", self.serial.clone().unwrap_or_else(unknown)),
+            ("Firmware Version", self.version.clone()),
+            ("USB ID", format!("{:04x}:{:04x}", self.vendor_id, self.product_id)),
+            ("USB Location", format!("{}.{}", self.bus_number, self.address)),
+        ]
+    }
+}
+
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (label, value) in self.fields() {
+            writeln!(f, "{:<18}{}", format!("{}:", label), value)?;
+        }
+        Ok(())
+    }
+}
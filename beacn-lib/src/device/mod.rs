@@ -0,0 +1,164 @@
+mod info;
+mod protocol;
+mod transport;
+
+pub use info::DeviceInfo;
+pub use protocol::{device_count, list_devices, BeacnDevice, DEFAULT_TIMEOUT};
+pub use transport::{SimulatedTransport, Transport, UsbTransport};
+
+use std::time::Duration;
+use log::{debug, info, warn};
+use tokio::select;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::time::interval;
+use crate::events::DeviceEvent;
+use crate::messages::{BeacnParameter, BeacnValue, Message, MessageValue};
+use crate::messages::mic::MicParameter;
+use crate::meter::MeterLevels;
+use crate::device::protocol::is_disconnected;
+
+// How often the meters are read from the device while something is listening to them
+const METER_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// The mute button doesn't tell us when it's pressed, so we need to keep checking it
+const MUTE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// How often we look for the Mic again after it's been unplugged
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+// This is simply something to run in a thread, and have a back and forth with the device..
+pub async fn spawn_device_handler(mut device: BeacnDevice, mut receiver: mpsc::Receiver<(Message, oneshot::Sender<BeacnValue>)>, meters: broadcast::Sender<MeterLevels>, events: broadcast::Sender<DeviceEvent>) {
+    debug!("Device Handler Started");
+
+    let mut meter_interval = interval(METER_POLL_INTERVAL);
+    let mut mute_interval = interval(MUTE_POLL_INTERVAL);
+    let mut reconnect_interval = interval(RECONNECT_INTERVAL);
+    let mut muted = None;
+    let mut connected = true;
+    loop {
+        select! {
+            Some((message, receiver)) = receiver.recv() => {
+                // If something goes wrong, the response is dropped and the caller gets an error
+                let response = match message {
+                    Message::FETCH(param) => device.fetch(param),
+                    Message::SET((param, value)) => device.set(param, value).inspect(|value| {
+                        if param != BeacnParameter::Mic(MicParameter::Mute) {
+                            let _ = events.send(DeviceEvent::ParameterChanged(param, *value));
+                            return;
+                        }
+
+                        // Mute is only reported as MuteChanged, and polling would see this as a
+                        // change too, so it's done here instead
+                        let value = MessageValue::<bool>::from(*value).0;
+                        if muted.replace(value) != Some(value) {
+                            debug!("Mute State Changed: {}", value);
+                            let _ = events.send(DeviceEvent::MuteChanged(value));
+                        }
+                    }),
+                    Message::QUIT => {
+                        receiver.send([00,00,00,00]).expect("Broken Response Oneshot");
+                        break;
+                    }
+                };
+
+                match response {
+                    Ok(value) => {
+                        // The caller may have given up waiting (see BlockingClient), that's fine.
+                        let _ = receiver.send(value);
+                    }
+                    Err(e) => {
+                        warn!("Unable to handle message: {}", e);
+                        check_connection(&e, &mut connected, &events);
+                    }
+                }
+            }
+            // There's no point hitting the device for levels if nobody is watching them
+            _ = meter_interval.tick(), if connected && meters.receiver_count() > 0 => {
+                match device.read_meters() {
+                    // This only fails if the last receiver went away since we checked, which is fine.
+                    Ok(levels) => { let _ = meters.send(levels); }
+                    Err(e) => {
+                        warn!("Unable to read meters: {}", e);
+                        check_connection(&e, &mut connected, &events);
+                    }
+                }
+            }
+            _ = mute_interval.tick(), if connected && events.receiver_count() > 0 => {
+                let value = match device.fetch(BeacnParameter::Mic(MicParameter::Mute)) {
+                    Ok(value) => MessageValue::<bool>::from(value).0,
+                    Err(e) => {
+                        warn!("Unable to read mute state: {}", e);
+                        check_connection(&e, &mut connected, &events);
+                        continue;
+                    }
+                };
+
+                // The first read is just to find out where we're starting from
+                if muted.is_some_and(|muted| muted != value) {
+                    debug!("Mute State Changed: {}", value);
+                    let _ = events.send(DeviceEvent::MuteChanged(value));
+                }
+                muted = Some(value);
+            }
+            _ = reconnect_interval.tick(), if !connected => {
+                match device.reconnect() {
+                    Ok(()) => {
+                        info!("Mic reconnected");
+                        connected = true;
+
+                        // The button may have been pressed while it was away, start afresh
+                        muted = None;
+                        let _ = events.send(DeviceEvent::Connected);
+                    }
+                    Err(e) => debug!("Mic not found yet: {}", e),
+                }
+            }
+            else => break,
+        }
+    }
+}
+
+// Unplugging the Mic shows up as an error from whatever next tries to use it
+fn check_connection(error: &anyhow::Error, connected: &mut bool, events: &broadcast::Sender<DeviceEvent>) {
+    if *connected && is_disconnected(error) {
+        warn!("Mic has been disconnected");
+        *connected = false;
+        let _ = events.send(DeviceEvent::Disconnected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::sleep;
+    use crate::client::BeacnClient;
+    use crate::messages::led::LEDParameter;
+    use crate::messages::schema::ParameterValue;
+    use super::*;
+
+    #[tokio::test]
+    async fn mute_changes_are_reported_once() {
+        let client = BeacnClient::connect_simulated();
+        let mut events = client.subscribe();
+
+        // Let polling find the starting state, then give it time to see (and not report) the change
+        sleep(MUTE_POLL_INTERVAL * 2).await;
+        client.set_muted(true).await.unwrap();
+        client.set_muted(true).await.unwrap();
+        sleep(MUTE_POLL_INTERVAL * 3).await;
+        client.set_muted(false).await.unwrap();
+        client.set_value(BeacnParameter::LED(LEDParameter::Brightness), ParameterValue::Int(40)).await.unwrap();
+
+        // Mute doesn't come through as a parameter change as well
+        let mut changes = vec![];
+        let mut parameters = vec![];
+        while let Ok(event) = events.try_recv() {
+            match event {
+                DeviceEvent::MuteChanged(muted) => changes.push(muted),
+                DeviceEvent::ParameterChanged(param, _) => parameters.push(param),
+                _ => {}
+            }
+        }
+        assert_eq!(changes, vec![true, false]);
+        assert_eq!(parameters, vec![BeacnParameter::LED(LEDParameter::Brightness)]);
+    }
+}
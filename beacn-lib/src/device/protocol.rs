@@ -0,0 +1,201 @@
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use log::debug;
+use rusb::{DeviceDescriptor, DeviceList, GlobalContext};
+use crate::{PID_BEACN_MIC, VID_BEACN_MIC};
+use crate::device::DeviceInfo;
+use crate::device::transport::{SimulatedTransport, Transport, UsbTransport};
+use crate::messages::{BeacnParameter, BeacnValue, GetId, MessageValue};
+use crate::messages::meter::MeterParameter;
+use crate::meter::{ChannelLevel, MeterLevels};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// A synchronous connection to the Mic, this is shared by the async handler and the blocking client.
+pub struct BeacnDevice {
+    transport: Box<dyn Transport>,
+    info: DeviceInfo,
+    timeout: Duration,
+}
+
+impl BeacnDevice {
+    pub fn open() -> Result<Self> {
+        Self::open_with_timeout(DEFAULT_TIMEOUT)
+    }
+
+    // The timeout is applied to each USB transfer, a FETCH is two transfers, a SET is four.
+    pub fn open_with_timeout(timeout: Duration) -> Result<Self> {
+        // Firstly, we're going to locate, and connect to the device...
+        debug!("Locating Beacn Mic");
+        let (device, descriptor) = find_devices().ok_or_else(|| anyhow!("Unable to Locate Device"))?;
+
+        debug!("Connecting to and configuring Device");
+        let handle = device.open().map_err(|e| anyhow!("Unable to Open Device: {}", e))?;
+        handle.set_auto_detach_kernel_driver(true)?;
+        handle.claim_interface(3)?;
+        handle.set_alternate_setting(3, 1)?;
+
+        let info = DeviceInfo::from_device(&device, Some(&handle), &descriptor);
+        Ok(Self {
+            transport: Box::new(UsbTransport::new(handle)),
+            info,
+            timeout,
+        })
+    }
+
+    // A device which behaves like a Mic, for testing without one attached
+    pub fn open_simulated() -> Self {
+        Self {
+            transport: Box::new(SimulatedTransport::default()),
+            info: DeviceInfo::simulated(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    // Opens the Mic again after it was unplugged (simulated ones never are)
+    pub fn reconnect(&mut self) -> Result<()> {
+        *self = Self::open_with_timeout(self.timeout)?;
+        Ok(())
+    }
+
+    pub fn fetch(&self, param: BeacnParameter) -> Result<BeacnValue> {
+        let mut request = [0; 4];
+        request[0] = param.get_id();
+        LittleEndian::write_u16(&mut request[1..3], param.get_child_id());
+        request[3] = 0xa3;
+
+        self.param_lookup(request)
+    }
+
+    pub fn set(&self, param: BeacnParameter, value: BeacnValue) -> Result<BeacnValue> {
+        // Don't let anything invalid reach the device
+        param.schema().validate(value)?;
+
+        let mut property = [0; 4];
+        property[0] = param.get_id();
+        LittleEndian::write_u16(&mut property[1..3], param.get_child_id());
+        property[3] = 0xa4;
+
+        // We're defining the values and their lengths, so this should be safe.
+        let concat = [property, value].concat();
+        let request = concat.try_into().unwrap();
+
+        self.param_set(request)
+    }
+
+    // The meter ids haven't been confirmed on real hardware, see MeterParameter
+    pub fn read_meters(&self) -> Result<MeterLevels> {
+        let read = |meter: MeterParameter| -> Result<f32> {
+            Ok(MessageValue::<f32>::from(self.fetch(BeacnParameter::Meter(meter))?).0)
+        };
+
+        Ok(MeterLevels {
+            mic: ChannelLevel {
+                rms: read(MeterParameter::MicRms)?,
+                peak: read(MeterParameter::MicPeak)?,
+            },
+            headphones: ChannelLevel {
+                rms: read(MeterParameter::HeadphoneRms)?,
+                peak: read(MeterParameter::HeadphonePeak)?,
+            },
+        })
+    }
+
+    fn param_lookup(&self, request: [u8; 4]) -> Result<BeacnValue> {
+        // Write out the command request
+        self.transport.write(&request, self.timeout)?;
+
+        // Grab the response into a buffer
+        let mut buf = [0; 8];
+        self.transport.read(&mut buf, self.timeout)?;
+
+        // Validate the header...
+        if buf[0..2] != request[0..2] || buf[3] != 0xa4 {
+            bail!("Invalid Response Received");
+        }
+
+        Ok(<BeacnValue>::try_from(&buf[4..8]).expect("Buffer has shrunk itself?!"))
+    }
+
+    fn param_set(&self, request: [u8; 8]) -> Result<BeacnValue> {
+        // Write out the command request
+        self.transport.write(&request, self.timeout)?;
+
+        // Setters don't have responses, so read the value back out, and make sure it was changed..
+        let mut lookup_request: [u8; 4] = request[0..4].try_into().unwrap();
+        lookup_request[3] = 0xa3;
+
+        let new_value = self.param_lookup(lookup_request)?;
+
+        // Compare the new response
+        if new_value != request[4..8] {
+            bail!("Value was not changed on the device!");
+        }
+
+        Ok(new_value)
+    }
+}
+
+// Whether an error came from the Mic having gone away, rather than a bad request
+pub fn is_disconnected(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<rusb::Error>(), Some(rusb::Error::NoDevice))
+}
+
+// rusb panics if the global context can't be created (eg. no USB support in a container), so
+// check libusb can actually start before going near it.
+fn usb_devices() -> Option<DeviceList<GlobalContext>> {
+    rusb::Context::new().ok()?;
+    rusb::devices().ok()
+}
+
+// Returns how many Mics are currently attached
+pub fn device_count() -> usize {
+    let Some(devices) = usb_devices() else {
+        return 0;
+    };
+
+    devices.iter().filter(|device| {
+        device.device_descriptor().is_ok_and(|descriptor| {
+            descriptor.vendor_id() == VID_BEACN_MIC && descriptor.product_id() == PID_BEACN_MIC
+        })
+    }).count()
+}
+
+// Returns the details of every attached Mic, without claiming any of them
+pub fn list_devices() -> Vec<DeviceInfo> {
+    let Some(devices) = usb_devices() else {
+        return vec![];
+    };
+
+    devices.iter().filter_map(|device| {
+        let descriptor = device.device_descriptor().ok()?;
+        if descriptor.vendor_id() != VID_BEACN_MIC || descriptor.product_id() != PID_BEACN_MIC {
+            return None;
+        }
+
+        let handle = device.open().ok();
+        Some(DeviceInfo::from_device(&device, handle.as_ref(), &descriptor))
+    }).collect()
+}
+
+fn find_devices() -> Option<(rusb::Device<GlobalContext>, DeviceDescriptor)> {
+    if let Some(devices) = usb_devices() {
+        for device in devices.iter() {
+            if let Ok(descriptor) = device.device_descriptor() {
+                let bus_number = device.bus_number();
+                let address = device.address();
+
+                if descriptor.vendor_id() == VID_BEACN_MIC && descriptor.product_id() == PID_BEACN_MIC {
+                    debug!("Found Beacn Mic at address {}.{}", bus_number, address);
+                    return Some((device, descriptor));
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use rusb::{DeviceHandle, GlobalContext};
+use crate::messages::{BeacnParameter, BeacnValue, GetId, MessageValue};
+use crate::messages::meter::MeterParameter;
+
+// The raw bulk transfers BeacnDevice speaks its protocol over
+pub trait Transport: Send {
+    fn write(&self, data: &[u8], timeout: Duration) -> Result<()>;
+    fn read(&self, buf: &mut [u8], timeout: Duration) -> Result<usize>;
+}
+
+pub struct UsbTransport {
+    handle: DeviceHandle<GlobalContext>,
+}
+
+impl UsbTransport {
+    pub fn new(handle: DeviceHandle<GlobalContext>) -> Self {
+        Self { handle }
+    }
+}
+
+impl Transport for UsbTransport {
+    fn write(&self, data: &[u8], timeout: Duration) -> Result<()> {
+        self.handle.write_bulk(0x03, data, timeout)?;
+        Ok(())
+    }
+
+    fn read(&self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        Ok(self.handle.read_bulk(0x83, buf, timeout)?)
+    }
+}
+
+// Behaves like a Mic, without needing one plugged in. Values start at their schema defaults,
+// and the meters drift up and down so there's something to look at.
+pub struct SimulatedTransport {
+    values: Mutex<HashMap<(u8, u16), BeacnValue>>,
+    response: Mutex<Option<[u8; 8]>>,
+    started: Instant,
+}
+
+impl Default for SimulatedTransport {
+    fn default() -> Self {
+        let values = BeacnParameter::all().into_iter().map(|param| {
+            let schema = param.schema();
+            let value = schema.encode(schema.default).expect("Schema default is invalid");
+            ((param.get_id(), param.get_child_id()), value)
+        }).collect();
+
+        Self {
+            values: Mutex::new(values),
+            response: Mutex::new(None),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl SimulatedTransport {
+    fn meter_value(&self, meter: MeterParameter) -> BeacnValue {
+        let time = self.started.elapsed().as_secs_f32();
+        let (offset, headroom) = match meter {
+            MeterParameter::MicRms => (0.0, 18.0),
+            MeterParameter::MicPeak => (0.0, 6.0),
+            MeterParameter::HeadphoneRms => (1.3, 24.0),
+            MeterParameter::HeadphonePeak => (1.3, 12.0),
+        };
+        let level = ((time * 2.0 + offset).sin() * 0.5 + 0.5) * 50.0 - 50.0 - headroom;
+        MessageValue::<f32>(level).into()
+    }
+}
+
+impl Transport for SimulatedTransport {
+    fn write(&self, data: &[u8], _timeout: Duration) -> Result<()> {
+        if data.len() < 4 {
+            bail!("Request too short");
+        }
+        let key = (data[0], LittleEndian::read_u16(&data[1..3]));
+        let param = BeacnParameter::from_ids(key.0, key.1).ok_or_else(|| anyhow!("Unknown parameter {:?}", key))?;
+
+        let mut values = self.values.lock().map_err(|_| anyhow!("Simulator Lock Poisoned"))?;
+        match data[3] {
+            0xa3 => {
+                let value = match param {
+                    BeacnParameter::Meter(meter) => self.meter_value(meter),
+                    _ => values[&key],
+                };
+
+                let mut response = [0; 8];
+                response[0..3].copy_from_slice(&data[0..3]);
+                response[3] = 0xa4;
+                response[4..8].copy_from_slice(&value);
+                *self.response.lock().map_err(|_| anyhow!("Simulator Lock Poisoned"))? = Some(response);
+            }
+            0xa4 if data.len() == 8 => {
+                values.insert(key, data[4..8].try_into()?);
+            }
+            _ => bail!("Unknown request {:02x?}", data),
+        }
+        Ok(())
+    }
+
+    fn read(&self, buf: &mut [u8], _timeout: Duration) -> Result<usize> {
+        let response = self.response.lock().map_err(|_| anyhow!("Simulator Lock Poisoned"))?.take();
+        let response = response.ok_or_else(|| anyhow!("Nothing to read"))?;
+
+        let length = buf.len().min(response.len());
+        buf[..length].copy_from_slice(&response[..length]);
+        Ok(length)
+    }
+}
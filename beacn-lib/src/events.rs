@@ -0,0 +1,19 @@
+use crate::messages::{BeacnParameter, BeacnValue};
+
+// Things which happen on the device that listeners may want to react to
+#[derive(Debug, Clone)]
+pub enum DeviceEvent {
+    // Includes presses of the mute button on the Mic itself. Sent once for each change, setting the
+    // mute to what it already is doesn't count.
+    MuteChanged(bool),
+
+    // A value was successfully set through the handler, by any client. Mute is left to MuteChanged.
+    ParameterChanged(BeacnParameter, BeacnValue),
+
+    // Every value in a profile was set, with its name if it was loaded by name
+    ProfileApplied(Option<String>),
+
+    // The Mic was unplugged, or came back afterwards. Being connected at the start isn't an event.
+    Disconnected,
+    Connected,
+}
@@ -0,0 +1,23 @@
+// The protocol naming (SET, FETCH, LED, RGB) follows the device, rather than Rust conventions.
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod blocking;
+pub mod client;
+pub mod device;
+pub mod events;
+pub mod messages;
+pub mod meter;
+pub mod profile;
+pub mod state;
+
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+
+pub const VID_BEACN_MIC: u16 = 0x33ae;
+pub const PID_BEACN_MIC: u16 = 0x0001;
+
+// Where profiles and other settings are kept
+pub fn config_directory() -> Result<PathBuf> {
+    let config = dirs::config_dir().ok_or_else(|| anyhow!("Unable to locate config directory"))?;
+    Ok(config.join("beacn-mic"))
+}
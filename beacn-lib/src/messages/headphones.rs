@@ -0,0 +1,63 @@
+use strum_macros::EnumIter;
+use crate::messages::GetId;
+use crate::messages::schema::{ParameterSchema, ParameterValue, ValueKind};
+
+const HEADPHONE_TYPES: &[(u32, &str)] = &[(0, "line-level"), (1, "normal"), (2, "high-impedance"), (3, "in-ear")];
+
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq)]
+pub enum HeadphoneParameter {
+    Level,
+    MicMonitor,
+    HeadphoneType,
+}
+
+// UNVERIFIED, as is the group (see BeacnParameter) and the HEADPHONE_TYPES values
+impl GetId<u16> for HeadphoneParameter {
+    fn get_id(&self) -> u16 {
+        match self {
+            HeadphoneParameter::Level => 0,
+            HeadphoneParameter::MicMonitor => 1,
+            HeadphoneParameter::HeadphoneType => 2,
+        }
+    }
+}
+
+impl HeadphoneParameter {
+    pub fn schema(&self) -> ParameterSchema {
+        match self {
+            HeadphoneParameter::Level => ParameterSchema::new("headphones.level", "Headphone output level", ValueKind::Float(-70.0, 0.0), ParameterValue::Float(-20.0)).unit("dB"),
+            HeadphoneParameter::MicMonitor => ParameterSchema::new("headphones.mic_monitor", "Amount of the Mic mixed into the headphones", ValueKind::Float(-70.0, 0.0), ParameterValue::Float(-40.0)).unit("dB"),
+            HeadphoneParameter::HeadphoneType => ParameterSchema::new("headphones.type", "Headphone amp setting for the connected headphones", ValueKind::Enum(HEADPHONE_TYPES), ParameterValue::UInt(1)),
+        }
+    }
+}
+
+// The headphone amp is tuned based on what's plugged in, these are the values the device accepts
+// for HeadphoneParameter::HeadphoneType
+#[derive(EnumIter, Clone, Copy, PartialEq)]
+pub enum HeadphoneType {
+    LineLevel,
+    NormalPower,
+    HighImpedance,
+    InEarMonitors,
+}
+
+impl HeadphoneType {
+    pub fn value(&self) -> u32 {
+        match self {
+            HeadphoneType::LineLevel => 0,
+            HeadphoneType::NormalPower => 1,
+            HeadphoneType::HighImpedance => 2,
+            HeadphoneType::InEarMonitors => 3,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HeadphoneType::LineLevel => "Line Level",
+            HeadphoneType::NormalPower => "Normal Power",
+            HeadphoneType::HighImpedance => "High Impedance",
+            HeadphoneType::InEarMonitors => "In-Ear Monitors",
+        }
+    }
+}
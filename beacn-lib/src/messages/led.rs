@@ -0,0 +1,127 @@
+use strum_macros::EnumIter;
+use crate::messages::{GetId, RGB};
+use crate::messages::schema::{ParameterSchema, ParameterValue, ValueKind};
+
+const MODES: &[(u32, &str)] = &[
+    (0x00, "solid"),
+    (0x01, "spectrum"),
+    (0x03, "gradient"),
+    (0x05, "meter-ring"),
+    (0x06, "meter-up"),
+    (0x07, "meter-down"),
+    (0x0a, "sparkle-random"),
+    (0x0b, "sparkle-meter"),
+];
+const METER_SOURCES: &[(u32, &str)] = &[(0, "microphone"), (1, "headphones")];
+const MUTE_MODES: &[(u32, &str)] = &[(0, "nothing"), (1, "solid"), (2, "off")];
+const SUSPEND_MODES: &[(u32, &str)] = &[(0, "nothing"), (1, "off"), (2, "brightness")];
+
+const fn colour(red: u8, green: u8, blue: u8) -> ParameterValue {
+    ParameterValue::Colour(RGB { red, green, blue, alpha: 0 })
+}
+
+
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq)]
+pub enum LEDParameter {
+    Mode,
+    Colour1,
+    Colour2,
+    // Colour3, - Not called by the official App
+    Speed,
+    Brightness,
+    MeterSource,
+    MeterSensitivity,
+    MuteMode,
+    MuteColour,
+    SuspendMode,
+    SuspendBrightness,
+}
+
+impl GetId<u16> for LEDParameter {
+    fn get_id(&self) -> u16 {
+        match self {
+            LEDParameter::Mode => 0,
+            LEDParameter::Colour1 => 1,
+            LEDParameter::Colour2 => 2,
+            //LEDMessage::Colour3 => 3,
+            LEDParameter::Speed => 4,
+            LEDParameter::Brightness => 5,
+            LEDParameter::MeterSource => 6,
+            LEDParameter::MeterSensitivity => 7,
+            LEDParameter::MuteMode => 8,
+            LEDParameter::MuteColour => 9,
+            LEDParameter::SuspendMode => 11,
+            LEDParameter::SuspendBrightness => 12
+        }
+    }
+}
+
+impl LEDParameter {
+    pub fn schema(&self) -> ParameterSchema {
+        match self {
+            LEDParameter::Mode => ParameterSchema::new("led.mode", "Lighting style of the ring", ValueKind::Enum(MODES), ParameterValue::UInt(0x00)),
+            LEDParameter::Colour1 => ParameterSchema::new("led.colour1", "Primary colour", ValueKind::Colour, colour(255, 255, 255)),
+            LEDParameter::Colour2 => ParameterSchema::new("led.colour2", "Secondary colour", ValueKind::Colour, colour(0, 0, 255)),
+            LEDParameter::Speed => ParameterSchema::new("led.speed", "Animation speed, negative values reverse direction", ValueKind::Int(-10, 10), ParameterValue::Int(0)),
+            LEDParameter::Brightness => ParameterSchema::new("led.brightness", "Ring brightness", ValueKind::Int(0, 100), ParameterValue::Int(100)).unit("%"),
+            LEDParameter::MeterSource => ParameterSchema::new("led.meter_source", "Audio source for the meter modes", ValueKind::Enum(METER_SOURCES), ParameterValue::UInt(0)),
+            LEDParameter::MeterSensitivity => ParameterSchema::new("led.meter_sensitivity", "Sensitivity of the meter modes", ValueKind::Float(0.0, 10.0), ParameterValue::Float(5.0)),
+            LEDParameter::MuteMode => ParameterSchema::new("led.mute_mode", "What the ring does when muted", ValueKind::Enum(MUTE_MODES), ParameterValue::UInt(0)),
+            LEDParameter::MuteColour => ParameterSchema::new("led.mute_colour", "Ring colour when muted", ValueKind::Colour, colour(255, 0, 0)),
+            LEDParameter::SuspendMode => ParameterSchema::new("led.suspend_mode", "What the ring does when USB is suspended", ValueKind::Enum(SUSPEND_MODES), ParameterValue::UInt(0)),
+            LEDParameter::SuspendBrightness => ParameterSchema::new("led.suspend_brightness", "Ring brightness when USB is suspended", ValueKind::UInt(0, 100), ParameterValue::UInt(20)).unit("%"),
+        }
+    }
+}
+
+// Where the reactive meter modes take their level from, for LEDParameter::MeterSource
+#[derive(EnumIter, Clone, Copy, PartialEq)]
+pub enum LEDMeterSource {
+    Microphone,
+    Headphones,
+}
+
+impl LEDMeterSource {
+    pub fn from_value(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(LEDMeterSource::Microphone),
+            1 => Some(LEDMeterSource::Headphones),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self) -> u32 {
+        match self {
+            LEDMeterSource::Microphone => 0,
+            LEDMeterSource::Headphones => 1,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LEDMeterSource::Microphone => "Microphone",
+
+            // This is the mix going out to the headphones (see HeadphoneParameter), rather than
+            // anything coming back from them.
+            LEDMeterSource::Headphones => "Headphone Output",
+        }
+    }
+}
+
+// The colour slots on the ring, for when a colour needs setting without caring about the mode
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq)]
+pub enum LEDColour {
+    Primary,
+    Secondary,
+    Mute,
+}
+
+impl LEDColour {
+    pub fn parameter(&self) -> LEDParameter {
+        match self {
+            LEDColour::Primary => LEDParameter::Colour1,
+            LEDColour::Secondary => LEDParameter::Colour2,
+            LEDColour::Mute => LEDParameter::MuteColour,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use strum_macros::EnumIter;
+use crate::messages::GetId;
+use crate::messages::schema::{ParameterSchema, ParameterValue, ValueKind};
+
+const LEVEL: ValueKind = ValueKind::Float(-100.0, 0.0);
+const SILENCE: ParameterValue = ParameterValue::Float(-100.0);
+
+// These are read-only, the device reports levels in dBFS as an f32. The ids are UNVERIFIED, like
+// the group (see BeacnParameter), so on real hardware the levels may not be what they claim.
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq)]
+pub enum MeterParameter {
+    MicRms,
+    MicPeak,
+    HeadphoneRms,
+    HeadphonePeak,
+}
+
+impl GetId<u16> for MeterParameter {
+    fn get_id(&self) -> u16 {
+        match self {
+            MeterParameter::MicRms => 0,
+            MeterParameter::MicPeak => 1,
+            MeterParameter::HeadphoneRms => 2,
+            MeterParameter::HeadphonePeak => 3,
+        }
+    }
+}
+
+impl MeterParameter {
+    pub fn schema(&self) -> ParameterSchema {
+        match self {
+            MeterParameter::MicRms => ParameterSchema::new("meter.mic_rms", "Microphone RMS level", LEVEL, SILENCE).unit("dB").read_only(),
+            MeterParameter::MicPeak => ParameterSchema::new("meter.mic_peak", "Microphone peak level", LEVEL, SILENCE).unit("dB").read_only(),
+            MeterParameter::HeadphoneRms => ParameterSchema::new("meter.headphone_rms", "Headphone RMS level", LEVEL, SILENCE).unit("dB").read_only(),
+            MeterParameter::HeadphonePeak => ParameterSchema::new("meter.headphone_peak", "Headphone peak level", LEVEL, SILENCE).unit("dB").read_only(),
+        }
+    }
+}
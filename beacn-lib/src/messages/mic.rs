@@ -0,0 +1,26 @@
+use strum_macros::EnumIter;
+use crate::messages::GetId;
+use crate::messages::schema::{ParameterSchema, ParameterValue, ValueKind};
+
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq)]
+pub enum MicParameter {
+    Mute,
+}
+
+// UNVERIFIED, like the group (see BeacnParameter). Mute is polled constantly while anything is
+// listening for events, so this needs checking against real hardware before it's relied on.
+impl GetId<u16> for MicParameter {
+    fn get_id(&self) -> u16 {
+        match self {
+            MicParameter::Mute => 0,
+        }
+    }
+}
+
+impl MicParameter {
+    pub fn schema(&self) -> ParameterSchema {
+        match self {
+            MicParameter::Mute => ParameterSchema::new("mic.mute", "Whether the Mic is muted", ValueKind::Bool, ParameterValue::Bool(false)).transient(),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use byteorder::{ByteOrder, LittleEndian};
+use strum::IntoEnumIterator;
+use crate::messages::headphones::HeadphoneParameter;
+use crate::messages::led::LEDParameter;
+use crate::messages::meter::MeterParameter;
+use crate::messages::mic::MicParameter;
+use crate::messages::schema::ParameterSchema;
+
+pub mod headphones;
+pub mod led;
+pub mod meter;
+pub mod mic;
+pub mod schema;
+
+pub type BeacnValue = [u8; 4];
+
+pub trait GetId<T> {
+    fn get_id(&self) -> T;
+}
+
+
+pub enum Message {
+    QUIT,
+
+    FETCH(BeacnParameter),
+    SET((BeacnParameter, BeacnValue)),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeacnParameter {
+    LED(LEDParameter),
+    Headphones(HeadphoneParameter),
+    Meter(MeterParameter),
+    Mic(MicParameter),
+}
+
+// Only the LED group comes from watching the official app. The others are UNVERIFIED, they
+// haven't been seen in a capture or checked against real hardware, and are guesses following on
+// from the LED group. Until they're confirmed they may do nothing, or something else entirely.
+impl GetId<u8> for BeacnParameter {
+    fn get_id(&self) -> u8 {
+        match self {
+            BeacnParameter::LED(_) => 0x01,
+            BeacnParameter::Headphones(_) => 0x02,
+            BeacnParameter::Meter(_) => 0x03,
+            BeacnParameter::Mic(_) => 0x04,
+        }
+    }
+}
+
+impl BeacnParameter {
+    pub fn get_child_id(&self) -> u16 {
+        match self {
+            BeacnParameter::LED(v) => v.get_id(),
+            BeacnParameter::Headphones(v) => v.get_id(),
+            BeacnParameter::Meter(v) => v.get_id(),
+            BeacnParameter::Mic(v) => v.get_id(),
+        }
+    }
+
+    pub fn schema(&self) -> ParameterSchema {
+        match self {
+            BeacnParameter::LED(v) => v.schema(),
+            BeacnParameter::Headphones(v) => v.schema(),
+            BeacnParameter::Meter(v) => v.schema(),
+            BeacnParameter::Mic(v) => v.schema(),
+        }
+    }
+
+    pub fn all() -> Vec<BeacnParameter> {
+        let mut all = vec![];
+        all.extend(LEDParameter::iter().map(BeacnParameter::LED));
+        all.extend(HeadphoneParameter::iter().map(BeacnParameter::Headphones));
+        all.extend(MeterParameter::iter().map(BeacnParameter::Meter));
+        all.extend(MicParameter::iter().map(BeacnParameter::Mic));
+        all
+    }
+
+    pub fn from_name(name: &str) -> Option<BeacnParameter> {
+        Self::all().into_iter().find(|param| param.schema().name == name)
+    }
+
+    pub fn from_ids(id: u8, child_id: u16) -> Option<BeacnParameter> {
+        Self::all().into_iter().find(|param| param.get_id() == id && param.get_child_id() == child_id)
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct RGB {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+impl RGB {
+    pub fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self { red, green, blue, alpha: 0 }
+    }
+
+    // Hue is 0 to 360, saturation and value are 0 to 1
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.) / 60.;
+        let chroma = value * saturation;
+        let x = chroma * (1. - (hue % 2. - 1.).abs());
+        let (red, green, blue) = match hue as u32 {
+            0 => (chroma, x, 0.),
+            1 => (x, chroma, 0.),
+            2 => (0., chroma, x),
+            3 => (0., x, chroma),
+            4 => (x, 0., chroma),
+            _ => (chroma, 0., x),
+        };
+
+        let offset = value - chroma;
+        let channel = |c: f32| ((c + offset).clamp(0., 1.) * 255.).round() as u8;
+        Self::new(channel(red), channel(green), channel(blue))
+    }
+
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (red, green, blue) = (self.red as f32 / 255., self.green as f32 / 255., self.blue as f32 / 255.);
+        let max = red.max(green).max(blue);
+        let delta = max - red.min(green).min(blue);
+
+        let hue = if delta == 0. {
+            0.
+        } else if max == red {
+            60. * ((green - blue) / delta).rem_euclid(6.)
+        } else if max == green {
+            60. * ((blue - red) / delta + 2.)
+        } else {
+            60. * ((red - green) / delta + 4.)
+        };
+        let saturation = if max == 0. { 0. } else { delta / max };
+        (hue, saturation, max)
+    }
+}
+
+pub struct MessageValue<T>(pub T);
+
+impl From<BeacnValue> for MessageValue<RGB> {
+    fn from(value: BeacnValue) -> Self {
+        Self(
+            RGB {
+                red: value[2],
+                green: value[1],
+                blue: value[0],
+                alpha: value[3],
+            }
+        )
+    }
+}
+
+impl From<MessageValue<RGB>> for BeacnValue {
+    fn from(value: MessageValue<RGB>) -> Self {
+        // The format for this is ARGB, but little endian..
+        [value.0.blue, value.0.green, value.0.red, 0]
+    }
+}
+
+impl From<BeacnValue> for MessageValue<u32> {
+    fn from(value: BeacnValue) -> Self {
+        Self(LittleEndian::read_u32(&value))
+    }
+}
+
+impl From<MessageValue<u32>> for BeacnValue {
+    fn from(value: MessageValue<u32>) -> Self {
+        let mut buf = [0; 4];
+        LittleEndian::write_u32(&mut buf, value.0);
+
+        buf
+    }
+}
+
+impl From<BeacnValue> for MessageValue<i32> {
+    fn from(value: BeacnValue) -> Self {
+        Self(LittleEndian::read_i32(&value))
+    }
+}
+
+impl From<MessageValue<i32>> for BeacnValue {
+    fn from(value: MessageValue<i32>) -> Self {
+        let mut buffer = [0; 4];
+        LittleEndian::write_i32(&mut buffer, value.0);
+        buffer
+    }
+}
+
+impl From<BeacnValue> for MessageValue<f32> {
+    fn from(value: BeacnValue) -> Self {
+        Self(LittleEndian::read_f32(&value))
+    }
+}
+
+impl From<MessageValue<f32>> for BeacnValue {
+    fn from(value: MessageValue<f32>) -> Self {
+        let mut buffer = [0; 4];
+        LittleEndian::write_f32(&mut buffer, value.0);
+        buffer
+    }
+}
+
+impl From<BeacnValue> for MessageValue<bool> {
+    fn from(value: BeacnValue) -> Self {
+        Self(LittleEndian::read_u32(&value) != 0)
+    }
+}
+
+impl From<MessageValue<bool>> for BeacnValue {
+    fn from(value: MessageValue<bool>) -> Self {
+        let mut buffer = [0; 4];
+        LittleEndian::write_u32(&mut buffer, value.0 as u32);
+        buffer
+    }
+}
\ No newline at end of file
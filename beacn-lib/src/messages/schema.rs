@@ -0,0 +1,317 @@
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use anyhow::{anyhow, bail, Result};
+use crate::messages::{BeacnValue, MessageValue, RGB};
+
+// Describes what a parameter holds, and what the device will accept for it
+#[derive(Debug, Clone, Copy)]
+pub enum ValueKind {
+    // A fixed set of values, each with a name which can be used in place of the number
+    Enum(&'static [(u32, &'static str)]),
+    UInt(u32, u32),
+    Int(i32, i32),
+    Float(f32, f32),
+    Bool,
+    Colour,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterValue {
+    UInt(u32),
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+    Colour(RGB),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParameterSchema {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: ValueKind,
+    pub unit: Option<&'static str>,
+    pub default: ParameterValue,
+    pub read_only: bool,
+
+    // Live state (such as mute) rather than a setting, these aren't stored in profiles
+    pub transient: bool,
+}
+
+impl ParameterSchema {
+    pub const fn new(name: &'static str, description: &'static str, kind: ValueKind, default: ParameterValue) -> Self {
+        Self {
+            name,
+            description,
+            kind,
+            unit: None,
+            default,
+            read_only: false,
+            transient: false,
+        }
+    }
+
+    pub const fn unit(mut self, unit: &'static str) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    pub const fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    pub const fn transient(mut self) -> Self {
+        self.transient = true;
+        self
+    }
+
+    pub fn decode(&self, value: BeacnValue) -> ParameterValue {
+        match self.kind {
+            ValueKind::Enum(_) | ValueKind::UInt(..) => ParameterValue::UInt(MessageValue::<u32>::from(value).0),
+            ValueKind::Int(..) => ParameterValue::Int(MessageValue::<i32>::from(value).0),
+            ValueKind::Float(..) => ParameterValue::Float(MessageValue::<f32>::from(value).0),
+            ValueKind::Bool => ParameterValue::Bool(MessageValue::<bool>::from(value).0),
+            ValueKind::Colour => ParameterValue::Colour(MessageValue::<RGB>::from(value).0),
+        }
+    }
+
+    // Checks the value is of the correct type and inside the valid range, before encoding it
+    pub fn encode(&self, value: ParameterValue) -> Result<BeacnValue> {
+        match (self.kind, value) {
+            (ValueKind::Enum(options), ParameterValue::UInt(v)) => {
+                if !options.iter().any(|(option, _)| *option == v) {
+                    bail!("{} is not a valid option for {}", v, self.name);
+                }
+                Ok(MessageValue::<u32>(v).into())
+            }
+            (ValueKind::UInt(min, max), ParameterValue::UInt(v)) => {
+                check_range(self.name, v, min..=max)?;
+                Ok(MessageValue::<u32>(v).into())
+            }
+            (ValueKind::Int(min, max), ParameterValue::Int(v)) => {
+                check_range(self.name, v, min..=max)?;
+                Ok(MessageValue::<i32>(v).into())
+            }
+            (ValueKind::Float(min, max), ParameterValue::Float(v)) => {
+                check_range(self.name, v, min..=max)?;
+                Ok(MessageValue::<f32>(v).into())
+            }
+            (ValueKind::Bool, ParameterValue::Bool(v)) => Ok(MessageValue::<bool>(v).into()),
+            (ValueKind::Colour, ParameterValue::Colour(v)) => Ok(MessageValue::<RGB>(v).into()),
+            (_, value) => bail!("{:?} is the wrong type of value for {}", value, self.name),
+        }
+    }
+
+    // Used before sending a SET, to make sure we never hand the device something it can't handle
+    pub fn validate(&self, value: BeacnValue) -> Result<()> {
+        if self.read_only {
+            bail!("{} is read only", self.name);
+        }
+        self.encode(self.decode(value)).map(|_| ())
+    }
+
+    pub fn parse(&self, input: &str) -> Result<ParameterValue> {
+        // Accept values as they come out of format(), with the unit attached
+        let mut input = input.trim();
+        if let Some(unit) = self.unit {
+            input = input.strip_suffix(unit).unwrap_or(input).trim_end();
+        }
+        let error = || anyhow!("'{}' is not a valid value for {} ({})", input, self.name, self.kind);
+
+        let value = match self.kind {
+            ValueKind::Enum(options) => {
+                let named = options.iter().find(|(_, name)| name.eq_ignore_ascii_case(input));
+                match named {
+                    Some((value, _)) => ParameterValue::UInt(*value),
+                    None => ParameterValue::UInt(input.parse().map_err(|_| error())?),
+                }
+            }
+            ValueKind::UInt(..) => ParameterValue::UInt(input.parse().map_err(|_| error())?),
+            ValueKind::Int(..) => ParameterValue::Int(input.parse().map_err(|_| error())?),
+            ValueKind::Float(..) => ParameterValue::Float(input.parse().map_err(|_| error())?),
+            ValueKind::Bool => match input.to_ascii_lowercase().as_str() {
+                "true" | "on" | "1" => ParameterValue::Bool(true),
+                "false" | "off" | "0" => ParameterValue::Bool(false),
+                _ => return Err(error()),
+            },
+            ValueKind::Colour => ParameterValue::Colour(parse_colour(input).ok_or_else(error)?),
+        };
+
+        // Run it through the encoder so range problems are reported here
+        self.encode(value)?;
+        Ok(value)
+    }
+
+    pub fn format(&self, value: ParameterValue) -> String {
+        let unit = self.unit.unwrap_or_default();
+        match (self.kind, value) {
+            (ValueKind::Enum(options), ParameterValue::UInt(v)) => {
+                match options.iter().find(|(option, _)| *option == v) {
+                    Some((_, name)) => name.to_string(),
+                    None => v.to_string(),
+                }
+            }
+            (_, ParameterValue::UInt(v)) => format!("{}{}", v, unit),
+            (_, ParameterValue::Int(v)) => format!("{}{}", v, unit),
+            (_, ParameterValue::Float(v)) => format!("{:.2}{}", v, unit),
+            (_, ParameterValue::Bool(v)) => v.to_string(),
+            (_, ParameterValue::Colour(v)) => format!("#{:02x}{:02x}{:02x}", v.red, v.green, v.blue),
+        }
+    }
+
+    // These are for building widgets, and will panic if asked for the wrong kind
+    pub fn uint_range(&self) -> RangeInclusive<u32> {
+        match self.kind {
+            ValueKind::UInt(min, max) => min..=max,
+            _ => panic!("{} is not an unsigned integer", self.name),
+        }
+    }
+
+    pub fn int_range(&self) -> RangeInclusive<i32> {
+        match self.kind {
+            ValueKind::Int(min, max) => min..=max,
+            _ => panic!("{} is not an integer", self.name),
+        }
+    }
+
+    pub fn float_range(&self) -> RangeInclusive<f32> {
+        match self.kind {
+            ValueKind::Float(min, max) => min..=max,
+            _ => panic!("{} is not a float", self.name),
+        }
+    }
+}
+
+impl Display for ValueKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueKind::Enum(options) => {
+                let names: Vec<&str> = options.iter().map(|(_, name)| *name).collect();
+                write!(f, "one of {}", names.join(", "))
+            }
+            ValueKind::UInt(min, max) => write!(f, "{} to {}", min, max),
+            ValueKind::Int(min, max) => write!(f, "{} to {}", min, max),
+            ValueKind::Float(min, max) => write!(f, "{:.1} to {:.1}", min, max),
+            ValueKind::Bool => write!(f, "true or false"),
+            ValueKind::Colour => write!(f, "#rrggbb"),
+        }
+    }
+}
+
+fn check_range<T: PartialOrd + Display>(name: &str, value: T, range: RangeInclusive<T>) -> Result<()> {
+    if !range.contains(&value) {
+        bail!("{} is out of range for {} ({} to {})", value, name, range.start(), range.end());
+    }
+    Ok(())
+}
+
+fn parse_colour(input: &str) -> Option<RGB> {
+    // from_str_radix would take a sign as well
+    let hex = input.strip_prefix('#').unwrap_or(input);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+
+    Some(RGB {
+        red: (value >> 16) as u8,
+        green: (value >> 8) as u8,
+        blue: value as u8,
+        alpha: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: &[(u32, &str)] = &[(0, "solid"), (2, "spectrum_cycle")];
+
+    const MODE: ParameterSchema = ParameterSchema::new("test.mode", "", ValueKind::Enum(MODES), ParameterValue::UInt(0));
+    const SPEED: ParameterSchema = ParameterSchema::new("test.speed", "", ValueKind::UInt(1, 10), ParameterValue::UInt(5)).unit("x");
+    const BRIGHTNESS: ParameterSchema = ParameterSchema::new("test.brightness", "", ValueKind::Int(-10, 100), ParameterValue::Int(100)).unit("%");
+    const LEVEL: ParameterSchema = ParameterSchema::new("test.level", "", ValueKind::Float(-70., 6.), ParameterValue::Float(0.)).unit("dB");
+    const MUTED: ParameterSchema = ParameterSchema::new("test.muted", "", ValueKind::Bool, ParameterValue::Bool(false));
+    const BLACK: RGB = RGB { red: 0, green: 0, blue: 0, alpha: 0 };
+    const COLOUR: ParameterSchema = ParameterSchema::new("test.colour", "", ValueKind::Colour, ParameterValue::Colour(BLACK));
+
+    #[test]
+    fn rejects_values_out_of_range() {
+        assert!(SPEED.parse("0").is_err());
+        assert!(SPEED.parse("11").is_err());
+        assert!(SPEED.parse("-1").is_err());
+        assert_eq!(SPEED.parse("10").unwrap(), ParameterValue::UInt(10));
+        assert!(BRIGHTNESS.parse("-11").is_err());
+        assert_eq!(BRIGHTNESS.parse("-10").unwrap(), ParameterValue::Int(-10));
+        assert!(LEVEL.parse("6.01").is_err());
+
+        // Values straight from the device are checked too
+        assert!(SPEED.validate(MessageValue::<u32>(11).into()).is_err());
+        assert!(SPEED.validate(MessageValue::<u32>(1).into()).is_ok());
+        assert!(SPEED.encode(ParameterValue::Int(5)).is_err());
+    }
+
+    #[test]
+    fn enums_take_names_in_any_case_or_numbers() {
+        assert_eq!(MODE.parse("spectrum_cycle").unwrap(), ParameterValue::UInt(2));
+        assert_eq!(MODE.parse(" SOLID ").unwrap(), ParameterValue::UInt(0));
+        assert_eq!(MODE.parse("2").unwrap(), ParameterValue::UInt(2));
+        assert!(MODE.parse("1").is_err());
+        assert!(MODE.parse("rainbow").is_err());
+        assert_eq!(MODE.format(ParameterValue::UInt(2)), "spectrum_cycle");
+        assert_eq!(MODE.format(ParameterValue::UInt(7)), "7");
+    }
+
+    #[test]
+    fn parses_colours() {
+        assert_eq!(COLOUR.parse("#ff8800").unwrap(), ParameterValue::Colour(RGB::new(255, 136, 0)));
+        assert_eq!(COLOUR.parse("FF8800").unwrap(), ParameterValue::Colour(RGB::new(255, 136, 0)));
+        for bad in ["#fff", "#ff88000", "#gg0000", "+12345", "#-12345", ""] {
+            assert!(COLOUR.parse(bad).is_err(), "{} was accepted", bad);
+        }
+    }
+
+    #[test]
+    fn floats_take_their_unit_and_must_be_numbers() {
+        assert_eq!(LEVEL.parse("-12dB").unwrap(), ParameterValue::Float(-12.));
+        assert_eq!(LEVEL.parse("-12.5 dB").unwrap(), ParameterValue::Float(-12.5));
+        assert_eq!(LEVEL.parse("3").unwrap(), ParameterValue::Float(3.));
+        assert_eq!(LEVEL.format(ParameterValue::Float(-12.)), "-12.00dB");
+        for bad in ["NaN", "nan", "inf", "-inf", "loud"] {
+            assert!(LEVEL.parse(bad).is_err(), "{} was accepted", bad);
+        }
+        assert!(LEVEL.validate(MessageValue::<f32>(f32::NAN).into()).is_err());
+        assert!(BRIGHTNESS.parse("50dB").is_err());
+    }
+
+    #[test]
+    fn bools_take_common_spellings() {
+        for (input, expected) in [("true", true), ("ON", true), ("1", true), ("False", false), ("off", false), ("0", false)] {
+            assert_eq!(MUTED.parse(input).unwrap(), ParameterValue::Bool(expected), "{}", input);
+        }
+        assert!(MUTED.parse("yes").is_err());
+    }
+
+    #[test]
+    fn read_only_values_cannot_be_set() {
+        let meter = LEVEL.read_only();
+        assert!(meter.validate(MessageValue::<f32>(0.).into()).is_err());
+    }
+
+    // Whatever format() gives, parse() takes back, and the device encoding survives too
+    #[test]
+    fn values_survive_the_round_trips() {
+        let values = [
+            (MODE, ParameterValue::UInt(2)),
+            (SPEED, ParameterValue::UInt(7)),
+            (BRIGHTNESS, ParameterValue::Int(-5)),
+            (LEVEL, ParameterValue::Float(-12.25)),
+            (MUTED, ParameterValue::Bool(true)),
+            (COLOUR, ParameterValue::Colour(RGB::new(18, 52, 86))),
+        ];
+        for (schema, value) in values {
+            assert_eq!(schema.parse(&schema.format(value)).unwrap(), value, "{}", schema.name);
+            assert_eq!(schema.decode(schema.encode(value).unwrap()), value, "{}", schema.name);
+        }
+    }
+}
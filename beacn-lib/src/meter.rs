@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+// Anything below this is treated as silence when drawing meters
+pub const METER_FLOOR_DB: f32 = -60.0;
+
+// How long a peak is held before it's allowed to fall back to the current level
+const PEAK_HOLD_TIME: Duration = Duration::from_millis(1500);
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct MeterLevels {
+    pub mic: ChannelLevel,
+    pub headphones: ChannelLevel,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelLevel {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+impl Default for ChannelLevel {
+    fn default() -> Self {
+        Self {
+            rms: METER_FLOOR_DB,
+            peak: METER_FLOOR_DB,
+        }
+    }
+}
+
+impl ChannelLevel {
+    // Returns where a dB value sits between the floor and 0dBFS, for drawing.
+    pub fn fraction(db: f32) -> f32 {
+        ((db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0)
+    }
+}
+
+pub struct PeakHold {
+    value: f32,
+    held_at: Instant,
+}
+
+impl Default for PeakHold {
+    fn default() -> Self {
+        Self {
+            value: METER_FLOOR_DB,
+            held_at: Instant::now(),
+        }
+    }
+}
+
+impl PeakHold {
+    pub fn update(&mut self, peak: f32) -> f32 {
+        let now = Instant::now();
+        if peak >= self.value || now.duration_since(self.held_at) > PEAK_HOLD_TIME {
+            self.value = peak;
+            self.held_at = now;
+        }
+        self.value
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+}
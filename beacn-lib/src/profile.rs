@@ -0,0 +1,126 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use crate::messages::BeacnParameter;
+use crate::messages::schema::ParameterValue;
+
+const PROFILE_EXTENSION: &str = "profile";
+
+// A set of values which can be saved, loaded and applied to the Mic. A profile doesn't need to
+// contain every parameter, anything missing is left alone when it's applied.
+//
+// On disk, a profile is a list of 'name = value' lines using the names and formats from the
+// parameter schema (see the 'list' command), lines starting with # are comments.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    // Only set for profiles loaded by name, so it can be reported when they're applied
+    pub name: Option<String>,
+    pub values: Vec<(BeacnParameter, ParameterValue)>,
+}
+
+impl Profile {
+    // Everything that can be stored in a profile, in the order it should be applied
+    pub fn parameters() -> Vec<BeacnParameter> {
+        BeacnParameter::all().into_iter().filter(|param| {
+            let schema = param.schema();
+            !schema.read_only && !schema.transient
+        }).collect()
+    }
+
+    pub fn get(&self, param: BeacnParameter) -> Option<ParameterValue> {
+        self.values.iter().find(|(existing, _)| *existing == param).map(|(_, value)| *value)
+    }
+
+    pub fn set(&mut self, param: BeacnParameter, value: ParameterValue) {
+        match self.values.iter_mut().find(|(existing, _)| *existing == param) {
+            Some((_, existing)) => *existing = value,
+            None => self.values.push((param, value)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut profile = Profile::default();
+
+        for (number, line) in text.lines().enumerate() {
+            // Colours also start with a #, so comments have to be on their own line
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |message: &str| anyhow!("Line {}: {}", number + 1, message);
+            let (name, value) = line.split_once('=').ok_or_else(|| error("Expected 'name = value'"))?;
+
+            let name = name.trim();
+            let param = BeacnParameter::from_name(name).ok_or_else(|| error(&format!("Unknown parameter '{}'", name)))?;
+            if !Self::parameters().contains(&param) {
+                return Err(error(&format!("{} can't be stored in a profile", name)));
+            }
+
+            let value = param.schema().parse(value).map_err(|e| error(&e.to_string()))?;
+            profile.set(param, value);
+        }
+        Ok(profile)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Unable to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_string()).with_context(|| format!("Unable to write {}", path.display()))
+    }
+
+    // Named profiles live in the user's config directory
+    pub fn directory() -> Result<PathBuf> {
+        Ok(crate::config_directory()?.join("profiles"))
+    }
+
+    pub fn path_for(name: &str) -> Result<PathBuf> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            bail!("'{}' is not a valid profile name", name);
+        }
+        Ok(Self::directory()?.join(format!("{}.{}", name, PROFILE_EXTENSION)))
+    }
+
+    pub fn load_named(name: &str) -> Result<Self> {
+        let profile = Self::load(&Self::path_for(name)?)?;
+        Ok(Self { name: Some(name.to_string()), ..profile })
+    }
+
+    pub fn save_named(&self, name: &str) -> Result<()> {
+        self.save(&Self::path_for(name)?)
+    }
+
+    pub fn list_named() -> Result<Vec<String>> {
+        let directory = Self::directory()?;
+        if !directory.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut names: Vec<String> = fs::read_dir(directory)?.filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != PROFILE_EXTENSION {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().to_string())
+        }).collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (param, value) in &self.values {
+            let schema = param.schema();
+            writeln!(f, "{} = {}", schema.name, schema.format(*value))?;
+        }
+        Ok(())
+    }
+}
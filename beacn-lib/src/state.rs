@@ -0,0 +1,98 @@
+use crate::messages::{BeacnParameter, BeacnValue, MessageValue, RGB};
+use crate::messages::headphones::HeadphoneParameter;
+use crate::messages::led::LEDParameter;
+use crate::messages::mic::MicParameter;
+
+#[derive(Default, Debug)]
+pub struct DeviceState {
+    pub led: LEDState,
+    pub headphones: HeadphoneState,
+    pub mic: MicState,
+}
+
+impl DeviceState {
+    // Meters aren't part of the state, so they're ignored
+    pub fn set_param(&mut self, param: BeacnParameter, value: BeacnValue) {
+        match param {
+            BeacnParameter::LED(param) => self.set_led_param(param, value),
+            BeacnParameter::Headphones(param) => self.set_headphone_param(param, value),
+            BeacnParameter::Mic(param) => self.set_mic_param(param, value),
+            BeacnParameter::Meter(_) => {}
+        }
+    }
+
+    pub fn set_led_param(&mut self, param: LEDParameter, value: BeacnValue) {
+        self.led.set_param(param, value);
+    }
+
+    pub fn set_headphone_param(&mut self, param: HeadphoneParameter, value: BeacnValue) {
+        self.headphones.set_param(param, value);
+    }
+
+    pub fn set_mic_param(&mut self, param: MicParameter, value: BeacnValue) {
+        self.mic.set_param(param, value);
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct LEDState {
+    pub mode: u32,
+    pub colour1: RGB,
+    pub colour2: RGB,
+    pub speed: i32,
+    pub brightness: i32,
+    pub meter_source: u32,
+    pub meter_sensitivity: f32,
+    pub mute_mode: u32,
+    pub mute_colour: RGB,
+    pub suspend_mode: u32,
+    pub suspend_brightness: u32,
+}
+
+impl LEDState {
+    fn set_param(&mut self, param: LEDParameter, value: BeacnValue) {
+        match param {
+            LEDParameter::Mode => self.mode = MessageValue::<u32>::from(value).0,
+            LEDParameter::Colour1 => self.colour1 = MessageValue::<RGB>::from(value).0,
+            LEDParameter::Colour2 => self.colour2 = MessageValue::<RGB>::from(value).0,
+            LEDParameter::Speed => self.speed = MessageValue::<i32>::from(value).0,
+            LEDParameter::Brightness => self.brightness = MessageValue::<i32>::from(value).0,
+            LEDParameter::MeterSource => self.meter_source = MessageValue::<u32>::from(value).0,
+            LEDParameter::MeterSensitivity => self.meter_sensitivity = MessageValue::<f32>::from(value).0,
+            LEDParameter::MuteMode => self.mute_mode = MessageValue::<u32>::from(value).0,
+            LEDParameter::MuteColour => self.mute_colour = MessageValue::<RGB>::from(value).0,
+            LEDParameter::SuspendMode => self.suspend_mode = MessageValue::<u32>::from(value).0,
+            LEDParameter::SuspendBrightness => self.suspend_brightness = MessageValue::<u32>::from(value).0,
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct HeadphoneState {
+    pub level: f32,
+    pub mic_monitor: f32,
+    pub headphone_type: u32,
+}
+
+impl HeadphoneState {
+    fn set_param(&mut self, param: HeadphoneParameter, value: BeacnValue) {
+        match param {
+            HeadphoneParameter::Level => self.level = MessageValue::<f32>::from(value).0,
+            HeadphoneParameter::MicMonitor => self.mic_monitor = MessageValue::<f32>::from(value).0,
+            HeadphoneParameter::HeadphoneType => self.headphone_type = MessageValue::<u32>::from(value).0,
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct MicState {
+    pub muted: bool,
+}
+
+impl MicState {
+    fn set_param(&mut self, param: MicParameter, value: BeacnValue) {
+        match param {
+            MicParameter::Mute => self.muted = MessageValue::<bool>::from(value).0,
+        }
+    }
+}
\ No newline at end of file
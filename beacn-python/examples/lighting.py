@@ -0,0 +1,21 @@
+# Cycles the ring through a few colours, then puts everything back the way it was.
+#
+#   maturin develop -m beacn-python/Cargo.toml
+#   python beacn-python/examples/lighting.py [--simulate]
+
+import sys
+import time
+
+import beacn
+
+mic = beacn.Mic.simulated() if "--simulate" in sys.argv else beacn.Mic()
+print(f"Connected to {mic.info['product']} ({mic.info['serial']})")
+
+original = mic.capture_profile()
+try:
+    mic.set("led.mode", "solid")
+    for colour in [(255, 0, 0), (0, 255, 0), (0, 0, 255), "#ff8800"]:
+        print("led.colour1 =", mic.set("led.colour1", colour))
+        time.sleep(0.5)
+finally:
+    mic.apply_profile(original)
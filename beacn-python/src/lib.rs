@@ -0,0 +1,77 @@
+// Python bindings, built as the 'beacn' module:
+//
+//   import beacn
+//   mic = beacn.Mic()            # or beacn.Mic.simulated()
+//   mic.set("led.mode", "spectrum")
+//   mic.set("led.colour1", (255, 136, 0))
+//   print(mic.state()["headphones"]["level"])
+
+mod mic;
+mod profile;
+mod value;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyKeyError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use beacn_lib::device::DeviceInfo;
+use beacn_lib::messages::BeacnParameter;
+use crate::mic::Mic;
+use crate::profile::Profile;
+use crate::value::to_python;
+
+create_exception!(beacn, BeacnError, PyException);
+
+fn to_error(error: anyhow::Error) -> PyErr {
+    BeacnError::new_err(format!("{:#}", error))
+}
+
+fn find_parameter(name: &str) -> PyResult<BeacnParameter> {
+    BeacnParameter::from_name(name).ok_or_else(|| PyKeyError::new_err(format!("Unknown parameter '{}'", name)))
+}
+
+fn info_dict<'py>(py: Python<'py>, info: &DeviceInfo) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("manufacturer", &info.manufacturer)?;
+    dict.set_item("product", &info.product)?;
+    dict.set_item("serial", &info.serial)?;
+    dict.set_item("version", &info.version)?;
+    dict.set_item("vendor_id", info.vendor_id)?;
+    dict.set_item("product_id", info.product_id)?;
+    dict.set_item("bus_number", info.bus_number)?;
+    dict.set_item("address", info.address)?;
+    Ok(dict)
+}
+
+// The details of every connected Mic
+#[pyfunction]
+fn list_devices(py: Python<'_>) -> PyResult<Vec<Bound<'_, PyDict>>> {
+    let devices = py.detach(beacn_lib::device::list_devices);
+    devices.iter().map(|info| info_dict(py, info)).collect()
+}
+
+// Every parameter with its description and valid values, the same as the 'list' command
+#[pyfunction]
+fn parameters(py: Python<'_>) -> PyResult<Vec<Bound<'_, PyDict>>> {
+    BeacnParameter::all().into_iter().map(|param| {
+        let schema = param.schema();
+        let dict = PyDict::new(py);
+        dict.set_item("name", schema.name)?;
+        dict.set_item("description", schema.description)?;
+        dict.set_item("values", schema.kind.to_string())?;
+        dict.set_item("unit", schema.unit)?;
+        dict.set_item("default", to_python(py, &schema, schema.default)?)?;
+        dict.set_item("read_only", schema.read_only)?;
+        Ok(dict)
+    }).collect()
+}
+
+#[pymodule(name = "beacn")]
+fn beacn_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("BeacnError", m.py().get_type::<BeacnError>())?;
+    m.add_class::<Mic>()?;
+    m.add_class::<Profile>()?;
+    m.add_function(wrap_pyfunction!(list_devices, m)?)?;
+    m.add_function(wrap_pyfunction!(parameters, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use beacn_lib::blocking::BlockingClient;
+use beacn_lib::profile;
+use crate::profile::Profile;
+use crate::value::{from_python, to_python};
+use crate::{find_parameter, info_dict, to_error};
+
+// Calls into the device release the GIL, so other Python threads keep running while we wait
+#[pyclass(module = "beacn", frozen)]
+pub struct Mic {
+    client: BlockingClient,
+}
+
+#[pymethods]
+impl Mic {
+    // Opens the first connected Mic, timeout is how long (in seconds) to wait for a response
+    #[new]
+    #[pyo3(signature = (timeout = 3.0))]
+    fn new(py: Python<'_>, timeout: f64) -> PyResult<Self> {
+        let timeout = Duration::try_from_secs_f64(timeout).map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        let client = py.detach(|| BlockingClient::open(timeout)).map_err(to_error)?;
+        Ok(Self { client })
+    }
+
+    #[staticmethod]
+    fn simulated() -> Self {
+        Self { client: BlockingClient::open_simulated() }
+    }
+
+    #[getter]
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        info_dict(py, self.client.info())
+    }
+
+    fn get<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyAny>> {
+        let param = find_parameter(name)?;
+        let value = py.detach(|| self.client.get_value(param)).map_err(to_error)?;
+        to_python(py, &param.schema(), value)
+    }
+
+    // Returns the value as read back from the Mic
+    fn set<'py>(&self, py: Python<'py>, name: &str, value: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        let param = find_parameter(name)?;
+        let schema = param.schema();
+        if schema.read_only {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!("{} is read only", schema.name)));
+        }
+
+        let value = from_python(&schema, value)?;
+        let value = py.detach(|| self.client.set_value(param, value)).map_err(to_error)?;
+        to_python(py, &schema, value)
+    }
+
+    // Every setting, grouped the same way as the parameter names (eg. state["led"]["mode"])
+    fn state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let state = PyDict::new(py);
+        for param in beacn_lib::messages::BeacnParameter::all() {
+            let schema = param.schema();
+            if schema.read_only {
+                continue;
+            }
+
+            let value = py.detach(|| self.client.get_value(param)).map_err(to_error)?;
+            let (group, name) = schema.name.split_once('.').unwrap_or(("", schema.name));
+            let group = match state.get_item(group)? {
+                Some(group) => group.cast_into::<PyDict>()?,
+                None => {
+                    let dict = PyDict::new(py);
+                    state.set_item(group, &dict)?;
+                    dict
+                }
+            };
+            group.set_item(name, to_python(py, &schema, value)?)?;
+        }
+        Ok(state)
+    }
+
+    fn capture_profile(&self, py: Python<'_>) -> PyResult<Profile> {
+        let profile = py.detach(|| self.client.capture_profile()).map_err(to_error)?;
+        Ok(Profile { profile })
+    }
+
+    // Takes either a Profile, or the name of a saved one
+    fn apply_profile(&self, py: Python<'_>, profile: &Bound<'_, PyAny>) -> PyResult<()> {
+        let profile = match profile.extract::<String>() {
+            Ok(name) => profile::Profile::load_named(&name).map_err(to_error)?,
+            Err(_) => profile.extract::<Profile>()?.profile,
+        };
+        py.detach(|| self.client.apply_profile(&profile)).map_err(to_error)
+    }
+
+    #[getter]
+    fn get_muted(&self, py: Python<'_>) -> PyResult<bool> {
+        py.detach(|| self.client.get_muted()).map_err(to_error)
+    }
+
+    #[setter]
+    fn set_muted(&self, py: Python<'_>, muted: bool) -> PyResult<()> {
+        py.detach(|| self.client.set_muted(muted)).map(|_| ()).map_err(to_error)
+    }
+
+    // Returns the new mute state
+    fn toggle_mute(&self, py: Python<'_>) -> PyResult<bool> {
+        py.detach(|| self.client.toggle_mute()).map_err(to_error)
+    }
+}
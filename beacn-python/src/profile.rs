@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use beacn_lib::profile;
+use crate::value::{from_python, to_python};
+use crate::{find_parameter, to_error};
+
+// A set of values which can be applied to a Mic, see beacn_lib::profile for the file format
+#[pyclass(module = "beacn", from_py_object)]
+#[derive(Clone, Default)]
+pub struct Profile {
+    pub profile: profile::Profile,
+}
+
+#[pymethods]
+impl Profile {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    // Loads a profile saved with 'beacn-mic-test profile save' or Profile.save()
+    #[staticmethod]
+    fn load(name: &str) -> PyResult<Self> {
+        let profile = profile::Profile::load_named(name).map_err(to_error)?;
+        Ok(Self { profile })
+    }
+
+    #[staticmethod]
+    fn load_file(path: PathBuf) -> PyResult<Self> {
+        let profile = profile::Profile::load(&path).map_err(to_error)?;
+        Ok(Self { profile })
+    }
+
+    #[staticmethod]
+    fn list() -> PyResult<Vec<String>> {
+        profile::Profile::list_named().map_err(to_error)
+    }
+
+    fn save(&self, name: &str) -> PyResult<()> {
+        self.profile.save_named(name).map_err(to_error)
+    }
+
+    fn save_file(&self, path: PathBuf) -> PyResult<()> {
+        self.profile.save(&path).map_err(to_error)
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (param, value) in &self.profile.values {
+            let schema = param.schema();
+            dict.set_item(schema.name, to_python(py, &schema, *value)?)?;
+        }
+        Ok(dict)
+    }
+
+    fn __getitem__<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyAny>> {
+        let param = find_parameter(name)?;
+        match self.profile.get(param) {
+            Some(value) => to_python(py, &param.schema(), value),
+            None => Err(pyo3::exceptions::PyKeyError::new_err(name.to_string())),
+        }
+    }
+
+    fn __setitem__(&mut self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let param = find_parameter(name)?;
+        let schema = param.schema();
+        if schema.read_only || schema.transient {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!("{} can't be stored in a profile", schema.name)));
+        }
+        self.profile.set(param, from_python(&schema, value)?);
+        Ok(())
+    }
+
+    fn __len__(&self) -> usize {
+        self.profile.values.len()
+    }
+
+    fn __str__(&self) -> String {
+        self.profile.to_string()
+    }
+}
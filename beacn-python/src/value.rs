@@ -0,0 +1,59 @@
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyFloat, PyInt, PyString};
+use pyo3::IntoPyObjectExt;
+use beacn_lib::messages::RGB;
+use beacn_lib::messages::schema::{ParameterSchema, ParameterValue, ValueKind};
+
+// Enums come out as their names, colours as (red, green, blue), everything else as the
+// matching Python type.
+pub fn to_python<'py>(py: Python<'py>, schema: &ParameterSchema, value: ParameterValue) -> PyResult<Bound<'py, PyAny>> {
+    match (schema.kind, value) {
+        (ValueKind::Enum(options), ParameterValue::UInt(v)) => {
+            match options.iter().find(|(option, _)| *option == v) {
+                Some((_, name)) => name.into_bound_py_any(py),
+                None => v.into_bound_py_any(py),
+            }
+        }
+        (_, ParameterValue::UInt(v)) => v.into_bound_py_any(py),
+        (_, ParameterValue::Int(v)) => v.into_bound_py_any(py),
+        (_, ParameterValue::Float(v)) => v.into_bound_py_any(py),
+        (_, ParameterValue::Bool(v)) => v.into_bound_py_any(py),
+        (_, ParameterValue::Colour(v)) => (v.red, v.green, v.blue).into_bound_py_any(py),
+    }
+}
+
+// Accepts anything to_python() produces, as well as strings in the same format as the CLI
+pub fn from_python(schema: &ParameterSchema, value: &Bound<'_, PyAny>) -> PyResult<ParameterValue> {
+    let value = if value.is_instance_of::<PyString>() {
+        return schema.parse(&value.extract::<String>()?).map_err(|e| PyValueError::new_err(e.to_string()));
+    } else if value.is_instance_of::<PyBool>() {
+        // bool is a subclass of int, so this has to be checked first
+        if !matches!(schema.kind, ValueKind::Bool) {
+            return Err(wrong_type(schema, value));
+        }
+        ParameterValue::Bool(value.extract()?)
+    } else if value.is_instance_of::<PyInt>() {
+        let out_of_range = |_| PyValueError::new_err(format!("{} is out of range for {}", value, schema.name));
+        match schema.kind {
+            ValueKind::Enum(_) | ValueKind::UInt(..) => ParameterValue::UInt(value.extract().map_err(out_of_range)?),
+            ValueKind::Int(..) => ParameterValue::Int(value.extract().map_err(out_of_range)?),
+            ValueKind::Float(..) => ParameterValue::Float(value.extract()?),
+            _ => return Err(wrong_type(schema, value)),
+        }
+    } else if value.is_instance_of::<PyFloat>() {
+        ParameterValue::Float(value.extract()?)
+    } else if let Ok((red, green, blue)) = value.extract::<(u8, u8, u8)>() {
+        ParameterValue::Colour(RGB { red, green, blue, alpha: 0 })
+    } else {
+        return Err(wrong_type(schema, value));
+    };
+
+    // Check the range here, so the error is raised as a ValueError rather than a device error
+    schema.encode(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(value)
+}
+
+fn wrong_type(schema: &ParameterSchema, value: &Bound<'_, PyAny>) -> PyErr {
+    PyTypeError::new_err(format!("{} expects {}, not {}", schema.name, schema.kind, value.get_type()))
+}
@@ -0,0 +1,306 @@
+use std::io::{stdout, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use tokio::select;
+use tokio::signal::ctrl_c;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use beacn_lib::client::BeacnClient;
+use beacn_lib::device::DeviceInfo;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::{BeacnParameter, RGB};
+use beacn_lib::meter::{ChannelLevel, MeterLevels, PeakHold};
+use beacn_lib::profile::Profile;
+use beacn_lib::state::DeviceState;
+use crate::palette::{hex, Palette};
+
+#[derive(Parser)]
+#[command(about = "Configuration tool for the Beacn Mic")]
+pub struct Cli {
+    /// Talk to a simulated Mic instead of a real one
+    #[arg(long, global = true)]
+    pub simulate: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Show the details of the connected Mic, including firmware version and serial
+    Info,
+
+    /// Print the device details and all current settings, for attaching to bug reports
+    Dump,
+
+    /// List every parameter, with its type, valid range and default
+    List,
+
+    /// Read a parameter from the Mic (see 'list' for names)
+    Get {
+        name: String,
+    },
+
+    /// Change a parameter on the Mic (see 'list' for names and valid values)
+    Set {
+        name: String,
+        value: String,
+    },
+
+    /// Display live Microphone and Headphone levels until interrupted
+    Meter,
+
+    /// Mute the Microphone
+    Mute,
+
+    /// Unmute the Microphone
+    Unmute,
+
+    /// Flip the Microphone between muted and unmuted
+    ToggleMute,
+
+    /// Print the mute state whenever it changes (including from the button on the Mic)
+    WatchMute,
+
+    /// Run the configured services (D-Bus etc.) without a window, until interrupted
+    Daemon,
+
+    /// Save, load and list named profiles
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+
+    /// Suggest ring colours to match a PNG or JPEG image
+    Palette {
+        image: PathBuf,
+
+        /// Put one of the suggestions (by number) on the Mic
+        #[arg(long)]
+        apply: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    /// List saved profiles
+    List,
+
+    /// Save the current settings as a named profile
+    Save {
+        name: String,
+    },
+
+    /// Apply a named profile to the Mic
+    Apply {
+        name: String,
+    },
+
+    /// Print the contents of a named profile
+    Show {
+        name: String,
+    },
+}
+
+fn find_parameter(name: &str) -> Result<BeacnParameter> {
+    BeacnParameter::from_name(name).ok_or_else(|| anyhow!("Unknown parameter '{}', see 'list' for valid names", name))
+}
+
+pub fn list() {
+    println!("{:<23} {:<59} {:<15} Description", "Name", "Values", "Default");
+    for param in BeacnParameter::all() {
+        let schema = param.schema();
+
+        let mut values = schema.kind.to_string();
+        if let Some(unit) = schema.unit {
+            values = format!("{} {}", values, unit);
+        }
+        if schema.read_only {
+            values = format!("{} (read only)", values);
+        }
+
+        println!("{:<23} {:<59} {:<15} {}", schema.name, values, schema.format(schema.default), schema.description);
+    }
+}
+
+pub async fn get(client: &BeacnClient, name: &str) -> Result<()> {
+    let param = find_parameter(name)?;
+    let schema = param.schema();
+
+    println!("{}", schema.format(client.get_value(param).await?));
+    Ok(())
+}
+
+pub async fn set(client: &BeacnClient, name: &str, value: &str) -> Result<()> {
+    let param = find_parameter(name)?;
+    let schema = param.schema();
+    if schema.read_only {
+        return Err(anyhow!("{} is read only", schema.name));
+    }
+
+    let value = client.set_value(param, schema.parse(value)?).await?;
+    println!("{}", schema.format(value));
+    Ok(())
+}
+
+pub fn profile_offline(command: &ProfileCommand) -> Result<()> {
+    match command {
+        ProfileCommand::List => {
+            for name in Profile::list_named()? {
+                println!("{}", name);
+            }
+        }
+        ProfileCommand::Show { name } => print!("{}", Profile::load_named(name)?),
+        _ => unreachable!("This command needs a device"),
+    }
+    Ok(())
+}
+
+pub async fn profile(client: &BeacnClient, command: &ProfileCommand) -> Result<()> {
+    match command {
+        ProfileCommand::Save { name } => {
+            client.capture_profile().await?.save_named(name)?;
+            println!("Saved {}", Profile::path_for(name)?.display());
+        }
+        ProfileCommand::Apply { name } => {
+            client.apply_profile(&Profile::load_named(name)?).await?;
+            println!("Applied {}", name);
+        }
+        _ => profile_offline(command)?,
+    }
+    Ok(())
+}
+
+// Shows the colours as well as their values, where the terminal can
+fn swatch(colour: RGB) -> String {
+    match stdout().is_terminal() {
+        true => format!("\x1b[38;2;{};{};{}m\u{2588}\u{2588}\x1b[0m", colour.red, colour.green, colour.blue),
+        false => String::new(),
+    }
+}
+
+pub fn palette(image: &Path) -> Result<Palette> {
+    let palette = Palette::from_image(image)?;
+
+    println!("Colours");
+    for found in &palette.swatches {
+        println!("  {} {}  {:3.0}%", swatch(found.colour), hex(found.colour), found.share * 100.);
+    }
+    println!();
+    println!("Suggestions");
+    for (index, pairing) in palette.pairings.iter().enumerate() {
+        println!("  {}. {} {}{}", index + 1, pairing, swatch(pairing.colour1), swatch(pairing.colour2));
+    }
+    Ok(palette)
+}
+
+pub async fn apply_palette(client: &BeacnClient, image: &Path, number: usize) -> Result<()> {
+    let palette = palette(image)?;
+    let pairing = number.checked_sub(1).and_then(|index| palette.pairings.get(index));
+    let pairing = pairing.ok_or_else(|| anyhow!("There's no suggestion {}, pick from 1 to {}", number, palette.pairings.len()))?;
+
+    for (param, value) in pairing.values()? {
+        client.set_value(BeacnParameter::LED(param), value).await?;
+    }
+    println!();
+    println!("Applied {}", pairing);
+    Ok(())
+}
+
+pub async fn set_mute(client: &BeacnClient, muted: bool) -> Result<()> {
+    let muted = client.set_muted(muted).await?;
+    println!("{}", if muted { "Muted" } else { "Unmuted" });
+    Ok(())
+}
+
+pub async fn toggle_mute(client: &BeacnClient) -> Result<()> {
+    let muted = client.toggle_mute().await?;
+    println!("{}", if muted { "Muted" } else { "Unmuted" });
+    Ok(())
+}
+
+pub async fn watch_mute(client: &BeacnClient) -> Result<()> {
+    let mut events = client.subscribe();
+    println!("{}", if client.get_muted().await? { "Muted" } else { "Unmuted" });
+
+    loop {
+        select! {
+            event = events.recv() => {
+                match event {
+                    Ok(DeviceEvent::MuteChanged(muted)) => {
+                        println!("{}", if muted { "Muted" } else { "Unmuted" });
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            _ = ctrl_c() => break,
+        }
+    }
+    Ok(())
+}
+
+pub async fn daemon() -> Result<()> {
+    ctrl_c().await?;
+    Ok(())
+}
+
+pub fn dump(info: &DeviceInfo, state: &DeviceState) {
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    println!();
+    print!("{}", info);
+    println!();
+    println!("{:#?}", state);
+}
+
+const METER_WIDTH: usize = 20;
+
+pub async fn meter(mut meters: broadcast::Receiver<MeterLevels>) -> Result<()> {
+    let mut mic_hold = PeakHold::default();
+    let mut headphone_hold = PeakHold::default();
+
+    loop {
+        select! {
+            levels = meters.recv() => {
+                let levels = match levels {
+                    Ok(levels) => levels,
+
+                    // We only care about the latest reading, so skipped ones can be ignored
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                mic_hold.update(levels.mic.peak);
+                headphone_hold.update(levels.headphones.peak);
+
+                print!(
+                    "\rMic {}  Headphones {}",
+                    format_channel(levels.mic, &mic_hold),
+                    format_channel(levels.headphones, &headphone_hold)
+                );
+                stdout().flush()?;
+            }
+            _ = ctrl_c() => break,
+        }
+    }
+    println!();
+    Ok(())
+}
+
+fn format_channel(level: ChannelLevel, hold: &PeakHold) -> String {
+    let filled = (ChannelLevel::fraction(level.rms) * METER_WIDTH as f32).round() as usize;
+    let peak = (ChannelLevel::fraction(hold.value()) * METER_WIDTH as f32).round() as usize;
+
+    let bar: String = (0..METER_WIDTH).map(|i| {
+        if i < filled {
+            '#'
+        } else if i + 1 == peak {
+            '|'
+        } else {
+            '-'
+        }
+    }).collect();
+
+    format!("[{}] {:6.1} / {:6.1} dB", bar, level.rms, hold.value())
+}
@@ -0,0 +1,456 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::ParameterValue;
+
+// Settings for the services (see services/), read from config.toml in the config directory. Every
+// section is optional, and anything missing falls back to its default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub dbus: DbusConfig,
+    pub http: HttpConfig,
+    pub osc: OscConfig,
+    pub midi: MidiConfig,
+    pub openrgb: OpenRgbConfig,
+    pub mqtt: MqttConfig,
+    pub obs: ObsConfig,
+    pub scripts: ScriptsConfig,
+    pub schedule: ScheduleConfig,
+    pub apps: AppsConfig,
+    pub hooks: HooksConfig,
+    pub status: StatusConfig,
+    pub hotkeys: HotkeysConfig,
+    pub privacy: PrivacyConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DbusConfig {
+    pub enabled: bool,
+}
+
+impl Default for DbusConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+// The HTTP API is off unless asked for, and won't start without a token
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    pub address: String,
+    pub token: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: String::from("127.0.0.1:7890"),
+            token: None,
+        }
+    }
+}
+
+// OSC has no authentication, so only listens locally unless told otherwise
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OscConfig {
+    pub enabled: bool,
+    pub address: String,
+
+    // Addresses (host:port) which are always sent changes, without needing to subscribe
+    pub clients: Vec<String>,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: String::from("127.0.0.1:9000"),
+            clients: vec![],
+        }
+    }
+}
+
+// Mappings are kept in midi.toml (see services/midi), as they're learned from the UI
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiConfig {
+    pub enabled: bool,
+
+    // A raw MIDI device such as /dev/snd/midiC1D0, the first one found is used if this isn't set
+    pub device: Option<String>,
+}
+
+// OpenRGB runs its own SDK server on 6742, so we sit next to it rather than on top of it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenRgbConfig {
+    pub enabled: bool,
+    pub address: String,
+}
+
+impl Default for OpenRgbConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: String::from("127.0.0.1:6743"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    // Our topics go under <topic>/<id>, discovery configs under <discovery_prefix>
+    pub topic: String,
+    pub discovery_prefix: String,
+
+    // Seconds between publishing the mic level, 0 leaves out the sensor (and stops the polling)
+    pub meter_interval: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::from("localhost"),
+            port: 1883,
+            username: None,
+            password: None,
+            topic: String::from("beacn"),
+            discovery_prefix: String::from("homeassistant"),
+            meter_interval: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObsConfig {
+    pub enabled: bool,
+    pub address: String,
+    pub password: Option<String>,
+
+    // How the ring changes, overlays left out do nothing. Scenes are by name.
+    pub streaming: Option<LightingOverlay>,
+    pub recording: Option<LightingOverlay>,
+    pub scenes: BTreeMap<String, LightingOverlay>,
+}
+
+impl Default for ObsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: String::from("ws://localhost:4455"),
+            password: None,
+
+            // On air
+            streaming: Some(LightingOverlay {
+                mode: Some(String::from("solid")),
+                colour1: Some(String::from("#ff0000")),
+                ..Default::default()
+            }),
+            recording: None,
+            scenes: BTreeMap::new(),
+        }
+    }
+}
+
+// Values are in the same format as the CLI takes them, anything not set is left alone
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightingOverlay {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colour1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colour2: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<i32>,
+}
+
+impl LightingOverlay {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Checks and converts everything that's set, ready for sending to the Mic
+    pub fn values(&self) -> Result<Vec<(LEDParameter, ParameterValue)>> {
+        let text = [
+            (LEDParameter::Mode, &self.mode),
+            (LEDParameter::Colour1, &self.colour1),
+            (LEDParameter::Colour2, &self.colour2),
+        ];
+
+        let mut values = vec![];
+        for (param, value) in text {
+            if let Some(value) = value {
+                values.push((param, param.schema().parse(value)?));
+            }
+        }
+        if let Some(brightness) = self.brightness {
+            let value = ParameterValue::Int(brightness);
+            LEDParameter::Brightness.schema().encode(value)?;
+            values.push((LEDParameter::Brightness, value));
+        }
+        Ok(values)
+    }
+}
+
+// Scripts run with full access to the device, so they have to be turned on first
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptsConfig {
+    pub enabled: bool,
+}
+
+// Actions are kept in schedule.toml (see services/schedule), as they can be changed from the UI
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    pub enabled: bool,
+}
+
+// Rules are checked in order, the first one which matches picks the profile
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppsConfig {
+    pub enabled: bool,
+
+    // Seconds between checking what's running
+    pub interval: u64,
+
+    // Applied when no rules match any more, otherwise the Mic goes back to how it was beforehand
+    pub default: Option<String>,
+
+    #[serde(rename = "rule")]
+    pub rules: Vec<AppRule>,
+}
+
+impl Default for AppsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 2,
+            default: None,
+            rules: vec![],
+        }
+    }
+}
+
+// A rule with both a process and a window needs both to match. Names are compared ignoring case.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppRule {
+    pub process: Option<String>,
+
+    // The class of the focused window, which is only available under X11 (or XWayland)
+    pub window: Option<String>,
+    pub profile: String,
+}
+
+// Commands are run with 'sh -c', see services/hooks for what they're told about the event
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub enabled: bool,
+
+    // Seconds a command can run for before it's killed
+    pub timeout: u64,
+
+    #[serde(rename = "command")]
+    pub commands: Vec<HookCommand>,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: 10,
+            commands: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookCommand {
+    pub on: HookEvent,
+    pub run: String,
+
+    // Change hooks can be limited to a single parameter, eg. "led.brightness"
+    pub parameter: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    Connect,
+    Disconnect,
+    Mute,
+    Profile,
+    Change,
+}
+
+impl HookEvent {
+    // As it's written in the config, and given to the commands
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::Connect => "connect",
+            HookEvent::Disconnect => "disconnect",
+            HookEvent::Mute => "mute",
+            HookEvent::Profile => "profile",
+            HookEvent::Change => "change",
+        }
+    }
+}
+
+// Turns the ring into a status light, see services/status for the sources
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusConfig {
+    pub enabled: bool,
+    pub source: StatusSource,
+
+    // The type of thermal zone to read (eg. "x86_pkg_temp"), otherwise the hottest is used
+    pub zone: Option<String>,
+    pub path: Option<PathBuf>,
+    pub command: Option<String>,
+
+    // Seconds between readings
+    pub interval: u64,
+
+    // How far a reading has to move before the ring changes, so it doesn't flicker on the boundary
+    pub hysteresis: f64,
+
+    #[serde(rename = "stop")]
+    pub stops: Vec<StatusStop>,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: StatusSource::Temperature,
+            zone: None,
+            path: None,
+            command: None,
+            interval: 5,
+            hysteresis: 0.,
+            stops: vec![],
+        }
+    }
+}
+
+// Where the readings come from, file needs the path and command the command
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StatusSource {
+    Temperature,
+    Load,
+    File,
+    Command,
+}
+
+// A point on the gradient, readings between two stops get a mix of them. Brightness is left alone
+// unless stops set it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusStop {
+    pub value: f64,
+    pub colour: String,
+    pub brightness: Option<i32>,
+}
+
+// Keys are read straight from the input devices, so they work under any desktop (including
+// Wayland), see services/hotkeys
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HotkeysConfig {
+    pub enabled: bool,
+
+    // Event devices to read (eg. /dev/input/by-id/...-event-kbd), otherwise any with the keys are used
+    pub devices: Vec<PathBuf>,
+
+    #[serde(rename = "key")]
+    pub keys: Vec<Hotkey>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hotkey {
+    // Key names joined with +, eg. "ctrl+shift+m" or "KEY_F13"
+    pub keys: String,
+
+    #[serde(flatten)]
+    pub action: HotkeyAction,
+}
+
+// Given as action = "...", with the profile alongside for profile. The unit-like variants have
+// braces so that a stray profile is rejected rather than ignored.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum HotkeyAction {
+    ToggleMute {},
+    PushToTalk {},
+    Profile { profile: String },
+}
+
+// Mutes the Mic while the session is locked or the system is asleep, see services/privacy
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrivacyConfig {
+    pub enabled: bool,
+
+    // Also while the desktop reports the session as idle
+    pub idle: bool,
+
+    // How the ring looks meanwhile, anything not set is left alone
+    pub lighting: LightingOverlay,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle: false,
+
+            // A dark ring
+            lighting: LightingOverlay {
+                mode: Some(String::from("solid")),
+                colour1: Some(String::from("#000000")),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Result<PathBuf> {
+        Ok(beacn_lib::config_directory()?.join("config.toml"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = fs::read_to_string(&path).with_context(|| format!("Unable to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Unable to parse {}", path.display()))
+    }
+}
@@ -0,0 +1,273 @@
+// Picks ring colours to match a picture (such as stream overlay art). The main colours are found by
+// median cut, then paired up for the two-colour modes. The Mic only takes two colours, beacn-lib
+// doesn't know what the third slot in the protocol does yet.
+
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use image::imageops::FilterType;
+use beacn_lib::messages::RGB;
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::ParameterValue;
+use crate::services::animation::blend;
+
+// Plenty for finding the main colours, and quick to go through
+const SAMPLE_SIZE: u32 = 128;
+
+const SWATCHES: usize = 8;
+const GRADIENTS: usize = 3;
+const SPARKLES: usize = 2;
+
+// Swatches closer than this are treated as the same colour
+const SIMILAR: f32 = 24.;
+
+// How far apart colours need to be to tell them apart on the ring
+const DISTINCT: f32 = 96.;
+
+// Below these a colour is too grey or too dark to show up on the ring
+const MIN_SATURATION: f32 = 0.2;
+const MIN_VALUE: f32 = 0.1;
+
+// One of the main colours in the picture, and how much of it is that colour
+#[derive(Debug, Clone, Copy)]
+pub struct Swatch {
+    pub colour: RGB,
+    pub share: f32,
+}
+
+// Colours for one of the modes, ready to put on the ring
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pairing {
+    pub mode: &'static str,
+    pub colour1: RGB,
+    pub colour2: RGB,
+}
+
+impl Pairing {
+    pub fn values(&self) -> Result<Vec<(LEDParameter, ParameterValue)>> {
+        Ok(vec![
+            (LEDParameter::Mode, LEDParameter::Mode.schema().parse(self.mode)?),
+            (LEDParameter::Colour1, ParameterValue::Colour(self.colour1)),
+            (LEDParameter::Colour2, ParameterValue::Colour(self.colour2)),
+        ])
+    }
+}
+
+impl Display for Pairing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<15} {} {}", self.mode, hex(self.colour1), hex(self.colour2))
+    }
+}
+
+pub fn hex(colour: RGB) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour.red, colour.green, colour.blue)
+}
+
+pub struct Palette {
+    pub swatches: Vec<Swatch>,
+    pub pairings: Vec<Pairing>,
+}
+
+impl Palette {
+    pub fn from_image(path: &Path) -> Result<Self> {
+        let image = image::open(path).with_context(|| format!("Unable to load {}", path.display()))?;
+        let image = image.resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle).to_rgba8();
+
+        // Transparent parts of an overlay aren't part of the art
+        let pixels: Vec<[u8; 3]> = image.pixels().filter(|pixel| pixel[3] >= 128).map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect();
+        if pixels.is_empty() {
+            bail!("{} has no visible pixels", path.display());
+        }
+
+        let swatches = median_cut(pixels, SWATCHES);
+        let pairings = pairings(&swatches);
+        Ok(Self { swatches, pairings })
+    }
+}
+
+// Repeatedly splits a box of colours at the median of its widest channel, until there are enough
+// boxes. Big boxes with a wide range are split first, so a small speck of colour doesn't take
+// the place of something which covers half the picture. Each box's average is a swatch, and
+// similar ones are merged, largest first.
+fn median_cut(pixels: Vec<[u8; 3]>, count: usize) -> Vec<Swatch> {
+    let total = pixels.len() as f32;
+    let mut boxes = vec![pixels];
+
+    while boxes.len() < count {
+        let widest = boxes.iter().enumerate()
+            .map(|(index, pixels)| (index, widest_channel(pixels), pixels.len()))
+            .filter(|(_, (_, range), _)| *range > 0)
+            .max_by_key(|(_, (_, range), len)| *range as usize * len);
+        let Some((index, (channel, _), _)) = widest else {
+            break;
+        };
+
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    let mut swatches: Vec<Swatch> = vec![];
+    for pixels in boxes {
+        let sum = |channel: usize| pixels.iter().map(|pixel| pixel[channel] as u32).sum::<u32>();
+        let average = |channel: usize| (sum(channel) as f32 / pixels.len() as f32).round() as u8;
+        let swatch = Swatch { colour: RGB::new(average(0), average(1), average(2)), share: pixels.len() as f32 / total };
+
+        match swatches.iter_mut().find(|existing| distance(existing.colour, swatch.colour) < SIMILAR) {
+            Some(existing) => {
+                let share = existing.share + swatch.share;
+                existing.colour = blend(existing.colour, swatch.colour, swatch.share / share);
+                existing.share = share;
+            }
+            None => swatches.push(swatch),
+        }
+    }
+    swatches.sort_by(|a, b| b.share.total_cmp(&a.share));
+    swatches
+}
+
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3).map(|channel| {
+        let min = pixels.iter().map(|pixel| pixel[channel]).min().unwrap_or(0);
+        let max = pixels.iter().map(|pixel| pixel[channel]).max().unwrap_or(0);
+        (channel, max - min)
+    }).max_by_key(|(_, range)| *range).unwrap_or((0, 0))
+}
+
+fn saturation_value(colour: RGB) -> (f32, f32) {
+    let max = colour.red.max(colour.green).max(colour.blue) as f32;
+    let min = colour.red.min(colour.green).min(colour.blue) as f32;
+    let saturation = if max == 0. { 0. } else { (max - min) / max };
+    (saturation, max / 255.)
+}
+
+// The ring has its own brightness setting, so colours are shown at full strength. Otherwise dark
+// art would just give a dim ring.
+fn full_strength(colour: RGB) -> RGB {
+    let max = colour.red.max(colour.green).max(colour.blue);
+    if max == 0 {
+        return RGB::new(255, 255, 255);
+    }
+    let scale = |channel: u8| (channel as f32 * 255. / max as f32).round() as u8;
+    RGB::new(scale(colour.red), scale(colour.green), scale(colour.blue))
+}
+
+fn distance(a: RGB, b: RGB) -> f32 {
+    let channel = |a: u8, b: u8| (a as f32 - b as f32).powi(2);
+    (channel(a.red, b.red) + channel(a.green, b.green) + channel(a.blue, b.blue)).sqrt()
+}
+
+// Gradients want two colours which are both prominent and far apart. Sparkles want a main colour
+// with a vivid accent against it, which can be a much smaller part of the picture.
+fn pairings(swatches: &[Swatch]) -> Vec<Pairing> {
+    let vivid: Vec<Swatch> = swatches.iter()
+        .filter(|swatch| {
+            let (saturation, value) = saturation_value(swatch.colour);
+            saturation >= MIN_SATURATION && value >= MIN_VALUE
+        })
+        .copied()
+        .collect();
+
+    // A mostly grey picture still gets something, even if it's white on white
+    let candidates: Vec<Swatch> = match vivid.len() {
+        0 | 1 => swatches.to_vec(),
+        _ => vivid,
+    };
+    let candidates: Vec<Swatch> = candidates.iter().map(|swatch| Swatch { colour: full_strength(swatch.colour), ..*swatch }).collect();
+
+    let mut pairs = vec![];
+    for (index, a) in candidates.iter().enumerate() {
+        for b in &candidates[index + 1..] {
+            pairs.push((a, b, distance(a.colour, b.colour) * (a.share * b.share).sqrt()));
+        }
+    }
+    // Two shades of the same colour make a dull gradient, so they're only used if there's nothing else
+    if pairs.iter().any(|(a, b, _)| distance(a.colour, b.colour) >= DISTINCT) {
+        pairs.retain(|(a, b, _)| distance(a.colour, b.colour) >= DISTINCT);
+    }
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut pairings = vec![];
+    for (a, b, _) in pairs.iter().take(GRADIENTS) {
+        add(&mut pairings, Pairing { mode: "gradient", colour1: a.colour, colour2: b.colour });
+    }
+
+    for base in candidates.iter().take(SPARKLES) {
+        let accent = candidates.iter()
+            .filter(|accent| accent.colour != base.colour)
+            .max_by(|a, b| accent_score(base, a).total_cmp(&accent_score(base, b)));
+        if let Some(accent) = accent {
+            add(&mut pairings, Pairing { mode: "sparkle-random", colour1: base.colour, colour2: accent.colour });
+        }
+    }
+
+    // Only one colour in the whole picture
+    if let [only] = &candidates[..] {
+        add(&mut pairings, Pairing { mode: "gradient", colour1: only.colour, colour2: only.colour });
+    }
+    pairings
+}
+
+fn add(pairings: &mut Vec<Pairing>, pairing: Pairing) {
+    if !pairings.contains(&pairing) {
+        pairings.push(pairing);
+    }
+}
+
+fn accent_score(base: &Swatch, accent: &Swatch) -> f32 {
+    distance(base.colour, accent.colour) * (0.5 + saturation_value(accent.colour).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(colours: &[([u8; 3], usize)]) -> Vec<[u8; 3]> {
+        colours.iter().flat_map(|(colour, count)| std::iter::repeat_n(*colour, *count)).collect()
+    }
+
+    #[test]
+    fn two_colours_give_two_swatches() {
+        let swatches = median_cut(image(&[([0, 0, 100], 25), ([200, 0, 0], 75)]), SWATCHES);
+        assert_eq!(swatches.len(), 2);
+        assert_eq!(swatches[0].colour, RGB::new(200, 0, 0));
+        assert_eq!(swatches[0].share, 0.75);
+        assert_eq!(swatches[1].colour, RGB::new(0, 0, 100));
+        assert_eq!(swatches[1].share, 0.25);
+    }
+
+    #[test]
+    fn similar_colours_are_merged() {
+        let swatches = median_cut(image(&[([200, 0, 0], 50), ([210, 0, 0], 50), ([0, 200, 0], 100)]), SWATCHES);
+        assert_eq!(swatches.len(), 2);
+        assert_eq!(swatches[0].share, 0.5);
+        assert_eq!(swatches[1].share, 0.5);
+        assert!(swatches.iter().any(|swatch| swatch.colour == RGB::new(205, 0, 0)));
+    }
+
+    #[test]
+    fn two_colours_are_paired_at_full_strength() {
+        let swatches = median_cut(image(&[([0, 0, 100], 25), ([200, 0, 0], 75)]), SWATCHES);
+        let red = RGB::new(255, 0, 0);
+        let blue = RGB::new(0, 0, 255);
+        assert_eq!(pairings(&swatches), vec![
+            Pairing { mode: "gradient", colour1: red, colour2: blue },
+            Pairing { mode: "sparkle-random", colour1: red, colour2: blue },
+            Pairing { mode: "sparkle-random", colour1: blue, colour2: red },
+        ]);
+    }
+
+    #[test]
+    fn grey_is_only_used_when_nothing_else_is_vivid() {
+        let swatches = median_cut(image(&[([128, 128, 128], 60), ([200, 0, 0], 20), ([0, 200, 0], 20)]), SWATCHES);
+        let found = pairings(&swatches);
+        assert_eq!(found[0], Pairing { mode: "gradient", colour1: RGB::new(255, 0, 0), colour2: RGB::new(0, 255, 0) });
+        assert!(found.iter().all(|pairing| pairing.colour1 != RGB::new(255, 255, 255) && pairing.colour2 != RGB::new(255, 255, 255)));
+
+        // A single colour still gives something to show
+        let swatches = median_cut(image(&[([128, 128, 128], 10)]), SWATCHES);
+        assert_eq!(pairings(&swatches), vec![Pairing { mode: "gradient", colour1: RGB::new(255, 255, 255), colour2: RGB::new(255, 255, 255) }]);
+    }
+}
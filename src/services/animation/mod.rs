@@ -0,0 +1,418 @@
+// Plays animations on the ring from here, as the Mic only has its fixed modes. Frames are sent as
+// changes to the primary colour and brightness, no faster than FRAME_INTERVAL, and only for values
+// which have actually changed. Starting a new animation stops the one playing first.
+//
+// Animations can be played through the HTTP API, D-Bus and scripts. See sequence.rs for what's
+// available.
+//
+// This is also the one place which changes the ring for a while and puts it back afterwards.
+// Services such as OBS and privacy set a layer of values (see overrides.rs) rather than saving and
+// restoring the ring themselves, so however they overlap it ends up back how it was. Changes made
+// meanwhile by anything else (a profile, the UI, D-Bus etc.) become what's underneath, and layers
+// are put back on top of them.
+
+mod overrides;
+mod sequence;
+
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{self, MissedTickBehavior};
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::{BeacnParameter, RGB};
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::ParameterValue;
+
+pub use overrides::{Layer, Values};
+pub use sequence::{blend, parse_colour, Animation};
+use overrides::Overrides;
+use sequence::Frame;
+
+// Every change is a write and a read back over USB, and is shared with everything else talking
+// to the Mic, so this is about as quick as it's sensible to go
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+// The ring shows the primary colour as it is in this mode
+const SOLID_MODE: u32 = 0x00;
+
+// What a temporary animation takes over while it plays
+const ANIMATED: [LEDParameter; 3] = [LEDParameter::Mode, LEDParameter::Colour1, LEDParameter::Brightness];
+
+// Change events can be missed if we fall behind (see BeacnClient), so our own writes aren't
+// remembered forever
+const MAX_WRITTEN: usize = 64;
+
+enum Command {
+    Play(Animation),
+    Stop,
+    Layer(Layer, Values, oneshot::Sender<Result<()>>),
+    Set(Values, oneshot::Sender<Result<()>>),
+    Underlying(oneshot::Sender<Values>),
+}
+
+// Can be cloned and used from anywhere, including outside the runtime
+#[derive(Clone)]
+pub struct AnimationHandle {
+    sender: mpsc::UnboundedSender<Command>,
+}
+
+impl AnimationHandle {
+    fn send(&self, command: Command) -> Result<()> {
+        self.sender.send(command).map_err(|_| anyhow!("Animations have stopped"))
+    }
+
+    pub fn play(&self, animation: Animation) -> Result<()> {
+        animation.validate()?;
+        self.send(Command::Play(animation))
+    }
+
+    // A notification, which blinks the ring and then puts it back
+    pub fn flash(&self, colour: &str, count: u32) -> Result<()> {
+        self.play(Animation::flash(colour, count))
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        self.send(Command::Stop)
+    }
+
+    // Puts values on the ring until the layer is set again, an empty layer takes it off. The layer
+    // is kept even if the ring can't be changed, and tried again when the Mic reconnects.
+    pub async fn layer(&self, layer: Layer, values: Values) -> Result<()> {
+        let (reply, result) = oneshot::channel();
+        self.send(Command::Layer(layer, values, reply))?;
+        result.await.map_err(|_| anyhow!("Animations have stopped"))?
+    }
+
+    // A lasting change, which goes underneath any layers (and waits for an animation to finish)
+    pub async fn set(&self, values: Values) -> Result<()> {
+        let (reply, result) = oneshot::channel();
+        self.send(Command::Set(values, reply))?;
+        result.await.map_err(|_| anyhow!("Animations have stopped"))?
+    }
+
+    // What the ring has underneath any layers, for the values they've changed
+    pub async fn underlying(&self) -> Result<Values> {
+        let (reply, result) = oneshot::channel();
+        self.send(Command::Underlying(reply))?;
+        result.await.map_err(|_| anyhow!("Animations have stopped"))
+    }
+}
+
+pub fn start(client: BeacnClient) -> AnimationHandle {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let ring = Ring { leds: Leds { client, written: vec![] }, overrides: Overrides::default(), playing: None };
+    super::spawn("Animation", ring.run(receiver));
+    AnimationHandle { sender }
+}
+
+struct Playing {
+    animation: Animation,
+    started: Instant,
+    start_colour: RGB,
+    last: Frame,
+}
+
+// The ring's values on the Mic, remembering what we've written so the change events for them aren't
+// taken as someone else changing the ring
+struct Leds {
+    client: BeacnClient,
+    written: Vec<(LEDParameter, ParameterValue)>,
+}
+
+impl Leds {
+    async fn get(&self, param: LEDParameter) -> Result<ParameterValue> {
+        self.client.get_value(BeacnParameter::LED(param)).await
+    }
+
+    async fn set(&mut self, param: LEDParameter, value: ParameterValue) -> Result<()> {
+        if self.written.len() >= MAX_WRITTEN {
+            self.written.remove(0);
+        }
+        self.written.push((param, value));
+        if let Err(e) = self.client.set_value(BeacnParameter::LED(param), value).await {
+            self.is_ours(param, value);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Whether a change event is for one of our writes, which it then stops waiting for
+    fn is_ours(&mut self, param: LEDParameter, value: ParameterValue) -> bool {
+        let index = self.written.iter().position(|written| *written == (param, value));
+        index.map(|index| self.written.remove(index)).is_some()
+    }
+}
+
+struct Ring {
+    leds: Leds,
+    overrides: Overrides,
+    playing: Option<Playing>,
+}
+
+impl Ring {
+    async fn run(mut self, mut commands: mpsc::UnboundedReceiver<Command>) -> Result<()> {
+        let mut frames = time::interval(FRAME_INTERVAL);
+        frames.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut events = self.leds.client.subscribe();
+
+        loop {
+            select! {
+                command = commands.recv() => {
+                    let Some(command) = command else {
+                        break;
+                    };
+                    self.command(command).await;
+                }
+                _ = frames.tick(), if self.playing.is_some() => self.next_frame().await,
+                event = events.recv() => {
+                    let result = match event {
+                        Ok(DeviceEvent::ParameterChanged(BeacnParameter::LED(param), value)) => {
+                            self.changed(param, param.schema().decode(value)).await
+                        }
+
+                        // Anything which failed while the Mic was away can be done now
+                        Ok(DeviceEvent::Connected) => self.apply().await,
+                        Ok(_) => continue,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    };
+                    if let Err(e) = result {
+                        warn!("Unable to update the ring: {:#}", e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn command(&mut self, command: Command) {
+        match command {
+            Command::Play(animation) => {
+                self.finish().await;
+                if let Err(e) = self.begin(animation).await {
+                    warn!("Unable to start animation: {:#}", e);
+                    self.playing = None;
+                }
+            }
+            Command::Stop => self.finish().await,
+            Command::Layer(layer, values, reply) => {
+                debug!("Setting the {:?} layer to {:?}", layer, values);
+                self.overrides.set_layer(layer, values);
+                let _ = reply.send(self.apply().await);
+            }
+            Command::Set(values, reply) => {
+                let _ = reply.send(self.set(values).await);
+            }
+            Command::Underlying(reply) => {
+                let _ = reply.send(self.overrides.underlying());
+            }
+        }
+    }
+
+    // The values a temporary animation is using, which are left alone until it's done
+    fn held(&self) -> &'static [LEDParameter] {
+        match &self.playing {
+            Some(playing) if playing.animation.is_temporary() => &ANIMATED,
+            _ => &[],
+        }
+    }
+
+    // Saves anything newly taken, then brings the ring into line with the layers
+    async fn apply(&mut self) -> Result<()> {
+        let held = self.held();
+        for param in self.overrides.unsaved(held) {
+            let value = self.leds.get(param).await?;
+            self.overrides.save(param, value);
+        }
+        for (param, value) in self.overrides.targets(held) {
+            self.write(param, value).await?;
+            self.overrides.release(param, held);
+        }
+        Ok(())
+    }
+
+    // Only written if it's different, so nothing else sees a change which isn't one
+    async fn write(&mut self, param: LEDParameter, value: ParameterValue) -> Result<()> {
+        if self.leds.get(param).await? != value {
+            self.leds.set(param, value).await?;
+        }
+        Ok(())
+    }
+
+    // Someone else changing a value we've taken changes what's underneath, and we put ours back
+    async fn changed(&mut self, param: LEDParameter, value: ParameterValue) -> Result<()> {
+        if self.leds.is_ours(param, value) || self.overrides.set_base(param, value, self.held()) {
+            return Ok(());
+        }
+        debug!("{} changed underneath us to {:?}", param.schema().name, value);
+        self.apply().await
+    }
+
+    async fn set(&mut self, values: Values) -> Result<()> {
+        let held = self.held();
+        for (param, value) in values {
+            if self.overrides.set_base(param, value, held) {
+                self.write(param, value).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn begin(&mut self, animation: Animation) -> Result<()> {
+        debug!("Playing animation {:?}", animation);
+        let colour = self.leds.get(LEDParameter::Colour1).await?;
+        let start_colour = match colour {
+            ParameterValue::Colour(colour) => colour,
+            _ => RGB::new(255, 255, 255),
+        };
+
+        let temporary = animation.is_temporary();
+        self.playing = Some(Playing { animation, started: Instant::now(), start_colour, last: Frame::default() });
+        if temporary {
+            self.apply().await?;
+            self.leds.set(LEDParameter::Mode, ParameterValue::UInt(SOLID_MODE)).await?;
+        }
+        Ok(())
+    }
+
+    async fn next_frame(&mut self) {
+        let Some(current) = self.playing.as_mut() else {
+            return;
+        };
+        let Some(frame) = current.animation.frame(current.started.elapsed(), current.start_colour) else {
+            return self.finish().await;
+        };
+
+        if let Err(e) = show(&mut self.leds, frame, current.last).await {
+            warn!("Unable to show animation frame: {:#}", e);
+        }
+        current.last = frame;
+    }
+
+    // Stopped or done, either way the ring is put back (or left on the last frame)
+    async fn finish(&mut self) {
+        let Some(playing) = self.playing.take() else {
+            return;
+        };
+        let result = async {
+            if !playing.animation.is_temporary() {
+                let last = playing.animation.last_frame();
+                show(&mut self.leds, last, playing.last).await?;
+
+                // A layer may have the colour, in which case this is what goes back afterwards
+                if let Some(colour) = last.colour {
+                    self.overrides.set_base(LEDParameter::Colour1, ParameterValue::Colour(colour), &[]);
+                }
+            }
+            self.apply().await
+        }.await;
+
+        if let Err(e) = result {
+            warn!("Unable to finish animation: {:#}", e);
+        }
+    }
+}
+
+async fn show(leds: &mut Leds, frame: Frame, last: Frame) -> Result<()> {
+    if let Some(colour) = frame.colour.filter(|colour| Some(*colour) != last.colour) {
+        leds.set(LEDParameter::Colour1, ParameterValue::Colour(colour)).await?;
+    }
+    if let Some(brightness) = frame.brightness.filter(|brightness| Some(*brightness) != last.brightness) {
+        leds.set(LEDParameter::Brightness, ParameterValue::Int(brightness)).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use beacn_lib::profile::Profile;
+    use super::*;
+
+    async fn ring(client: &BeacnClient) -> Values {
+        let mut values = vec![];
+        for param in ANIMATED {
+            values.push((param, client.get_value(BeacnParameter::LED(param)).await.unwrap()));
+        }
+        values
+    }
+
+    fn colour(value: &str) -> ParameterValue {
+        ParameterValue::Colour(parse_colour(value).unwrap())
+    }
+
+    #[tokio::test]
+    async fn overlapping_overrides_put_the_ring_back() {
+        let client = BeacnClient::connect_simulated();
+        let animations = start(client.clone());
+        let before = ring(&client).await;
+
+        animations.layer(Layer::Obs, vec![(LEDParameter::Colour1, colour("#ff0000"))]).await.unwrap();
+        animations.play(Animation::Flash { colour: None, brightness: 100, count: 1, interval: 100 }).unwrap();
+        animations.layer(Layer::Privacy, vec![(LEDParameter::Colour1, colour("#000000"))]).await.unwrap();
+        animations.layer(Layer::Obs, vec![]).await.unwrap();
+        time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(client.get_value(BeacnParameter::LED(LEDParameter::Colour1)).await.unwrap(), colour("#000000"));
+
+        animations.layer(Layer::Privacy, vec![]).await.unwrap();
+        assert_eq!(ring(&client).await, before);
+    }
+
+    #[tokio::test]
+    async fn profiles_go_underneath_layers() {
+        let client = BeacnClient::connect_simulated();
+        let animations = start(client.clone());
+        let target = BeacnParameter::LED(LEDParameter::Colour1);
+
+        animations.layer(Layer::Privacy, vec![(LEDParameter::Colour1, colour("#000000"))]).await.unwrap();
+        let mut profile = Profile::default();
+        profile.set(target, colour("#00ff00"));
+        client.apply_profile(&profile).await.unwrap();
+
+        // The engine hears about the profile as an event, so wait for it to catch up
+        animations.underlying().await.unwrap();
+        time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(client.get_value(target).await.unwrap(), colour("#000000"));
+        assert_eq!(animations.underlying().await.unwrap(), vec![(LEDParameter::Colour1, colour("#00ff00"))]);
+
+        animations.layer(Layer::Privacy, vec![]).await.unwrap();
+        assert_eq!(client.get_value(target).await.unwrap(), colour("#00ff00"));
+    }
+
+    #[tokio::test]
+    async fn changes_from_elsewhere_go_underneath_layers() {
+        let client = BeacnClient::connect_simulated();
+        let animations = start(client.clone());
+        let target = BeacnParameter::LED(LEDParameter::Brightness);
+
+        animations.layer(Layer::Privacy, vec![(LEDParameter::Brightness, ParameterValue::Int(0))]).await.unwrap();
+        client.set_value(target, ParameterValue::Int(35)).await.unwrap();
+
+        // The layer goes back on top once the engine hears about it
+        time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(client.get_value(target).await.unwrap(), ParameterValue::Int(0));
+        assert_eq!(animations.underlying().await.unwrap(), vec![(LEDParameter::Brightness, ParameterValue::Int(35))]);
+
+        animations.layer(Layer::Privacy, vec![]).await.unwrap();
+        assert_eq!(client.get_value(target).await.unwrap(), ParameterValue::Int(35));
+    }
+
+    #[tokio::test]
+    async fn our_own_writes_are_not_changes() {
+        let client = BeacnClient::connect_simulated();
+        let animations = start(client.clone());
+        let before = ring(&client).await;
+
+        // Quick enough that the events for the first layer arrive after the second one is on
+        animations.layer(Layer::Obs, vec![(LEDParameter::Colour1, colour("#ff0000"))]).await.unwrap();
+        animations.layer(Layer::Obs, vec![(LEDParameter::Colour1, colour("#0000ff"))]).await.unwrap();
+        time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(animations.underlying().await.unwrap(), vec![(LEDParameter::Colour1, before[1].1)]);
+
+        animations.layer(Layer::Obs, vec![]).await.unwrap();
+        assert_eq!(ring(&client).await, before);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::ParameterValue;
+
+pub type Values = Vec<(LEDParameter, ParameterValue)>;
+
+// Services which change the ring for a while, later ones win when they change the same value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Obs,
+    Privacy,
+}
+
+// Which layers are on the ring, and what it had underneath them. Values are held while a temporary
+// animation is using them, so they're saved but not written until it's done.
+#[derive(Default)]
+pub struct Overrides {
+    layers: BTreeMap<Layer, Values>,
+
+    // What the ring has without any layers, for every value one of them (or an animation) has taken
+    base: Values,
+}
+
+impl Overrides {
+    pub fn set_layer(&mut self, layer: Layer, values: Values) {
+        match values.is_empty() {
+            true => self.layers.remove(&layer),
+            false => self.layers.insert(layer, values),
+        };
+    }
+
+    // The value from the topmost layer which sets it
+    fn top(&self, param: LEDParameter) -> Option<ParameterValue> {
+        self.layers.values().rev().find_map(|values| values.iter().find(|(layered, _)| *layered == param).map(|(_, value)| *value))
+    }
+
+    fn base(&self, param: LEDParameter) -> Option<ParameterValue> {
+        self.base.iter().find(|(saved, _)| *saved == param).map(|(_, value)| *value)
+    }
+
+    fn is_taken(&self, param: LEDParameter, held: &[LEDParameter]) -> bool {
+        held.contains(&param) || self.top(param).is_some()
+    }
+
+    // Values which have been taken, but not saved yet
+    pub fn unsaved(&self, held: &[LEDParameter]) -> Vec<LEDParameter> {
+        let layered = self.layers.values().flatten().map(|(param, _)| *param);
+        let mut unsaved = vec![];
+        for param in held.iter().copied().chain(layered) {
+            if self.base(param).is_none() && !unsaved.contains(&param) {
+                unsaved.push(param);
+            }
+        }
+        unsaved
+    }
+
+    pub fn save(&mut self, param: LEDParameter, value: ParameterValue) {
+        self.base.retain(|(saved, _)| *saved != param);
+        self.base.push((param, value));
+    }
+
+    // What the ring should be showing for everything saved, other than values which are held
+    pub fn targets(&self, held: &[LEDParameter]) -> Values {
+        self.base.iter()
+            .filter(|(param, _)| !held.contains(param))
+            .map(|(param, value)| (*param, self.top(*param).unwrap_or(*value)))
+            .collect()
+    }
+
+    // Once a value is back to how it was, and nothing has it, there's no need to keep it
+    pub fn release(&mut self, param: LEDParameter, held: &[LEDParameter]) {
+        if !self.is_taken(param, held) {
+            self.base.retain(|(saved, _)| *saved != param);
+        }
+    }
+
+    // A lasting change to a value, which goes underneath anything that's taken it. Returns whether
+    // it can be written straight away.
+    pub fn set_base(&mut self, param: LEDParameter, value: ParameterValue, held: &[LEDParameter]) -> bool {
+        if !self.is_taken(param, held) {
+            return true;
+        }
+        self.save(param, value);
+        false
+    }
+
+    // Everything saved, as the ring would be without any layers
+    pub fn underlying(&self) -> Values {
+        self.base.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use beacn_lib::messages::RGB;
+    use super::*;
+
+    const MODE: LEDParameter = LEDParameter::Mode;
+    const COLOUR: LEDParameter = LEDParameter::Colour1;
+    const BRIGHTNESS: LEDParameter = LEDParameter::Brightness;
+
+    fn colour(red: u8) -> ParameterValue {
+        ParameterValue::Colour(RGB::new(red, 0, 0))
+    }
+
+    // Does what the engine does with the ring, for the values in `ring`
+    fn apply(overrides: &mut Overrides, ring: &mut Values, held: &[LEDParameter]) {
+        for param in overrides.unsaved(held) {
+            let value = ring.iter().find(|(shown, _)| *shown == param).unwrap().1;
+            overrides.save(param, value);
+        }
+        for (param, value) in overrides.targets(held) {
+            ring.iter_mut().find(|(shown, _)| *shown == param).unwrap().1 = value;
+            overrides.release(param, held);
+        }
+    }
+
+    fn ring() -> Values {
+        vec![(MODE, ParameterValue::UInt(1)), (COLOUR, colour(10)), (BRIGHTNESS, ParameterValue::Int(80))]
+    }
+
+    #[test]
+    fn overlapping_layers_put_the_ring_back() {
+        let mut overrides = Overrides::default();
+        let mut shown = ring();
+
+        overrides.set_layer(Layer::Obs, vec![(COLOUR, colour(255))]);
+        apply(&mut overrides, &mut shown, &[]);
+        overrides.set_layer(Layer::Privacy, vec![(MODE, ParameterValue::UInt(0)), (COLOUR, colour(0))]);
+        apply(&mut overrides, &mut shown, &[]);
+        assert_eq!(shown, vec![(MODE, ParameterValue::UInt(0)), (COLOUR, colour(0)), (BRIGHTNESS, ParameterValue::Int(80))]);
+
+        // OBS stopping underneath privacy doesn't show through
+        overrides.set_layer(Layer::Obs, vec![]);
+        apply(&mut overrides, &mut shown, &[]);
+        assert_eq!(shown[1], (COLOUR, colour(0)));
+
+        overrides.set_layer(Layer::Privacy, vec![]);
+        apply(&mut overrides, &mut shown, &[]);
+        assert_eq!(shown, ring());
+        assert!(overrides.underlying().is_empty());
+    }
+
+    #[test]
+    fn lower_layers_come_back_when_the_top_one_goes() {
+        let mut overrides = Overrides::default();
+        let mut shown = ring();
+
+        overrides.set_layer(Layer::Obs, vec![(COLOUR, colour(255))]);
+        overrides.set_layer(Layer::Privacy, vec![(COLOUR, colour(0))]);
+        apply(&mut overrides, &mut shown, &[]);
+        overrides.set_layer(Layer::Privacy, vec![]);
+        apply(&mut overrides, &mut shown, &[]);
+        assert_eq!(shown[1], (COLOUR, colour(255)));
+
+        overrides.set_layer(Layer::Obs, vec![]);
+        apply(&mut overrides, &mut shown, &[]);
+        assert_eq!(shown, ring());
+    }
+
+    #[test]
+    fn held_values_wait_for_the_animation() {
+        let mut overrides = Overrides::default();
+        let mut shown = ring();
+        let held = [MODE, COLOUR, BRIGHTNESS];
+
+        // An animation starts, then privacy comes and goes while it's playing
+        apply(&mut overrides, &mut shown, &held);
+        shown[2].1 = ParameterValue::Int(0);
+        overrides.set_layer(Layer::Privacy, vec![(COLOUR, colour(0))]);
+        apply(&mut overrides, &mut shown, &held);
+        overrides.set_layer(Layer::Privacy, vec![]);
+        apply(&mut overrides, &mut shown, &held);
+        assert_eq!(shown[2].1, ParameterValue::Int(0));
+
+        apply(&mut overrides, &mut shown, &[]);
+        assert_eq!(shown, ring());
+        assert!(overrides.underlying().is_empty());
+    }
+
+    #[test]
+    fn lasting_changes_go_underneath() {
+        let mut overrides = Overrides::default();
+        let mut shown = ring();
+
+        overrides.set_layer(Layer::Privacy, vec![(BRIGHTNESS, ParameterValue::Int(0))]);
+        apply(&mut overrides, &mut shown, &[]);
+        assert!(!overrides.set_base(BRIGHTNESS, ParameterValue::Int(20), &[]));
+        assert!(overrides.set_base(COLOUR, colour(50), &[]));
+        assert_eq!(overrides.underlying(), vec![(BRIGHTNESS, ParameterValue::Int(20))]);
+
+        overrides.set_layer(Layer::Privacy, vec![]);
+        apply(&mut overrides, &mut shown, &[]);
+        assert_eq!(shown[2], (BRIGHTNESS, ParameterValue::Int(20)));
+    }
+}
@@ -0,0 +1,230 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use beacn_lib::messages::RGB;
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::ParameterValue;
+
+// What can be played, as JSON (or a map in scripts) this looks like
+//   {"type": "flash", "colour": "#ff0000", "count": 3}
+//
+// Times are in milliseconds, and the colour defaults to whatever the ring already has. Everything
+// except fade puts the ring back how it was when it's done.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Animation {
+    // Brightness rises and falls once
+    Pulse {
+        colour: Option<String>,
+        #[serde(default = "default_brightness")]
+        brightness: i32,
+        #[serde(default = "default_duration")]
+        duration: u64,
+    },
+
+    // Blinks on and off, each blink taking the interval
+    Flash {
+        colour: Option<String>,
+        #[serde(default = "default_brightness")]
+        brightness: i32,
+        #[serde(default = "default_count")]
+        count: u32,
+        #[serde(default = "default_interval")]
+        interval: u64,
+    },
+
+    // Slow pulses, until stopped if there's no count
+    Breathe {
+        colour: Option<String>,
+        #[serde(default = "default_brightness")]
+        brightness: i32,
+        count: Option<u32>,
+        #[serde(default = "default_period")]
+        period: u64,
+    },
+
+    // Moves the primary colour to a new one, and leaves it there
+    Fade {
+        colour: String,
+        #[serde(default = "default_duration")]
+        duration: u64,
+    },
+}
+
+// Each half of a flash (or breath) needs at least a frame, or it never shows
+const MIN_INTERVAL: u64 = 2 * super::FRAME_INTERVAL.as_millis() as u64;
+
+// A day is far longer than anything sensible, but keeps the sums well clear of overflowing
+const MAX_TIME: u64 = 24 * 60 * 60 * 1000;
+
+fn default_brightness() -> i32 {
+    100
+}
+
+fn default_duration() -> u64 {
+    1000
+}
+
+fn default_count() -> u32 {
+    3
+}
+
+fn default_interval() -> u64 {
+    400
+}
+
+fn default_period() -> u64 {
+    4000
+}
+
+// What the ring should show at a point in the animation, anything None is left alone
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Frame {
+    pub colour: Option<RGB>,
+    pub brightness: Option<i32>,
+}
+
+pub fn parse_colour(colour: &str) -> Result<RGB> {
+    match LEDParameter::Colour1.schema().parse(colour)? {
+        ParameterValue::Colour(colour) => Ok(colour),
+        _ => bail!("'{}' is not a colour", colour),
+    }
+}
+
+pub fn blend(from: RGB, to: RGB, fraction: f32) -> RGB {
+    let channel = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * fraction).round() as u8;
+    RGB::new(channel(from.red, to.red), channel(from.green, to.green), channel(from.blue, to.blue))
+}
+
+impl Animation {
+    pub fn flash(colour: &str, count: u32) -> Self {
+        Animation::Flash { colour: Some(colour.to_string()), brightness: default_brightness(), count, interval: default_interval() }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let (colour, brightness) = match self {
+            Animation::Pulse { colour, brightness, .. } | Animation::Flash { colour, brightness, .. } | Animation::Breathe { colour, brightness, .. } => {
+                (colour.as_deref(), Some(*brightness))
+            }
+            Animation::Fade { colour, .. } => (Some(colour.as_str()), None),
+        };
+        if let Some(colour) = colour {
+            parse_colour(colour)?;
+        }
+        if let Some(brightness) = brightness {
+            LEDParameter::Brightness.schema().encode(ParameterValue::Int(brightness))?;
+        }
+
+        let (time, repeat) = match self {
+            Animation::Pulse { duration, .. } | Animation::Fade { duration, .. } => (*duration, None),
+            Animation::Flash { interval, count, .. } => (interval.saturating_mul(*count as u64), Some(*interval)),
+            Animation::Breathe { period, count, .. } => (period.saturating_mul(count.unwrap_or(1) as u64), Some(*period)),
+        };
+        if repeat.is_some_and(|repeat| repeat < MIN_INTERVAL) {
+            bail!("The interval or period has to be at least {}ms", MIN_INTERVAL);
+        }
+        if time > MAX_TIME {
+            bail!("Animations can't be longer than {}ms", MAX_TIME);
+        }
+        Ok(())
+    }
+
+    // Temporary animations switch the ring to solid (so the colour shows as it is) and put it back
+    // afterwards, the others are left on their last frame
+    pub fn is_temporary(&self) -> bool {
+        !matches!(self, Animation::Fade { .. })
+    }
+
+    // The frame at a time since the start (given the primary colour at the start), or None once
+    // it's finished
+    pub fn frame(&self, elapsed: Duration, start_colour: RGB) -> Option<Frame> {
+        let millis = elapsed.as_millis() as u64;
+        let colour = |colour: &Option<String>| Some(colour.as_deref().and_then(|colour| parse_colour(colour).ok()).unwrap_or(start_colour));
+
+        match self {
+            Animation::Pulse { colour: wanted, brightness, duration } => {
+                let duration = (*duration).max(1);
+                if millis >= duration {
+                    return None;
+                }
+                let level = (PI * millis as f32 / duration as f32).sin();
+                Some(Frame { colour: colour(wanted), brightness: Some((*brightness as f32 * level).round() as i32) })
+            }
+            Animation::Flash { colour: wanted, brightness, count, interval } => {
+                let interval = (*interval).max(1);
+                if millis >= interval.saturating_mul(*count as u64) {
+                    return None;
+                }
+                let on = millis % interval < interval / 2;
+                Some(Frame { colour: colour(wanted), brightness: Some(if on { *brightness } else { 0 }) })
+            }
+            Animation::Breathe { colour: wanted, brightness, count, period } => {
+                let period = (*period).max(1);
+                if count.is_some_and(|count| millis >= period.saturating_mul(count as u64)) {
+                    return None;
+                }
+                let level = (1. - (2. * PI * (millis % period) as f32 / period as f32).cos()) / 2.;
+                Some(Frame { colour: colour(wanted), brightness: Some((*brightness as f32 * level).round() as i32) })
+            }
+            Animation::Fade { colour: target, duration } => {
+                if millis >= *duration {
+                    return None;
+                }
+                let target = parse_colour(target).unwrap_or(start_colour);
+                Some(Frame { colour: Some(blend(start_colour, target, millis as f32 / *duration as f32)), brightness: None })
+            }
+        }
+    }
+
+    // Where an animation which isn't temporary leaves the ring, as the frames may stop short
+    pub fn last_frame(&self) -> Frame {
+        match self {
+            Animation::Fade { colour, .. } => Frame { colour: parse_colour(colour).ok(), brightness: None },
+            _ => Frame::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Animation {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn rejects_intervals_too_short_to_show() {
+        assert!(parse(r#"{"type": "flash", "interval": 1}"#).validate().is_err());
+        assert!(parse(r#"{"type": "breathe", "period": 50}"#).validate().is_err());
+        assert!(parse(r#"{"type": "flash", "interval": 100}"#).validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_huge_times() {
+        assert!(parse(r#"{"type": "flash", "interval": 9223372036854775807, "count": 4294967295}"#).validate().is_err());
+        assert!(parse(r#"{"type": "breathe", "count": 4294967295}"#).validate().is_err());
+        assert!(parse(r#"{"type": "pulse", "duration": 18446744073709551615}"#).validate().is_err());
+    }
+
+    #[test]
+    fn huge_times_do_not_overflow() {
+        let black = RGB::new(0, 0, 0);
+        let flash = Animation::Flash { colour: None, brightness: 100, count: u32::MAX, interval: u64::MAX };
+        assert!(flash.frame(Duration::from_secs(1), black).is_some());
+        let breathe = Animation::Breathe { colour: None, brightness: 100, count: Some(u32::MAX), period: u64::MAX };
+        assert!(breathe.frame(Duration::from_secs(1), black).is_some());
+    }
+
+    #[test]
+    fn flash_lights_up_for_half_of_each_blink() {
+        let flash = parse(r#"{"type": "flash", "interval": 100, "count": 2}"#);
+        let black = RGB::new(0, 0, 0);
+        let brightness = |millis| flash.frame(Duration::from_millis(millis), black).map(|frame| frame.brightness);
+        assert_eq!(brightness(0), Some(Some(100)));
+        assert_eq!(brightness(50), Some(Some(0)));
+        assert_eq!(brightness(150), Some(Some(0)));
+        assert_eq!(brightness(200), None);
+    }
+}
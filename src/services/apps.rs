@@ -0,0 +1,293 @@
+// Switches profiles depending on what's running, eg. a quieter ring while in a call:
+//
+//   [[apps.rule]]
+//   process = "zoom"
+//   profile = "meeting"
+//
+//   [[apps.rule]]
+//   window = "discord"
+//   profile = "chat"
+//
+// Processes are found by scanning /proc, and match either their short name (as 'ps' shows it) or
+// the file name of the command. The focused window comes from _NET_ACTIVE_WINDOW, so only works
+// under X11 or for XWayland windows.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use anyhow::{bail, Result};
+use log::{debug, info, warn};
+use tokio::time::{self, MissedTickBehavior};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt, Window};
+use x11rb::rust_connection::RustConnection;
+use beacn_lib::client::BeacnClient;
+use beacn_lib::messages::BeacnParameter;
+use beacn_lib::profile::Profile;
+use crate::config::{AppRule, AppsConfig};
+use crate::services::animation::AnimationHandle;
+
+pub async fn run(client: BeacnClient, animations: AnimationHandle, config: AppsConfig) -> Result<()> {
+    for rule in &config.rules {
+        if rule.process.is_none() && rule.window.is_none() {
+            bail!("Rule for '{}' needs a process or window to match", rule.profile);
+        }
+        Profile::path_for(&rule.profile)?;
+    }
+
+    let windows = match config.rules.iter().any(|rule| rule.window.is_some()) {
+        true => FocusedWindow::connect().map_err(|e| warn!("Unable to watch the focused window, window rules won't match: {:#}", e)).ok(),
+        false => None,
+    };
+
+    let mut switcher = Switcher::new(config.default.clone());
+    let mut check = time::interval(Duration::from_secs(config.interval.max(1)));
+    check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        check.tick().await;
+
+        let processes = running_processes();
+        let window = windows.as_ref().map(|windows| windows.class().unwrap_or_else(|e| {
+            debug!("Unable to read the focused window: {:#}", e);
+            vec![]
+        }));
+        let Some(switch) = switcher.update(first_match(&config.rules, &processes, window.as_deref())) else {
+            continue;
+        };
+
+        let result = match switch {
+            Switch::Rule { index, save } => {
+                let rule = &config.rules[index];
+                info!("Switching to profile '{}' for {}", rule.profile, describe(rule));
+                if save {
+                    switcher.previous = capture(&client, &animations).await.map_err(|e| warn!("Unable to save the current settings: {:#}", e)).ok();
+                }
+                apply(&client, Profile::load_named(&rule.profile)).await
+            }
+            Switch::Default(default) => {
+                info!("No rules match, switching to profile '{}'", default);
+                apply(&client, Profile::load_named(&default)).await
+            }
+            Switch::Restore(profile) => {
+                info!("No rules match, restoring previous settings");
+                apply(&client, Ok(profile)).await
+            }
+        };
+
+        // If it didn't work, it's not tried again until something else changes
+        if let Err(e) = result {
+            warn!("Unable to switch profile: {:#}", e);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Switch {
+    // With save, the current settings are captured first to put back when no rules match
+    Rule { index: usize, save: bool },
+    Default(String),
+    Restore(Profile),
+}
+
+// Tracks the rule currently applied, and what the Mic had before any of them were
+struct Switcher {
+    default: Option<String>,
+    active: Option<usize>,
+    previous: Option<Profile>,
+}
+
+impl Switcher {
+    fn new(default: Option<String>) -> Self {
+        Self { default, active: None, previous: None }
+    }
+
+    // Given the rule which matches now, works out what (if anything) needs to change
+    fn update(&mut self, matched: Option<usize>) -> Option<Switch> {
+        if matched == self.active {
+            return None;
+        }
+        let was_active = std::mem::replace(&mut self.active, matched);
+
+        match matched {
+            Some(index) => Some(Switch::Rule { index, save: was_active.is_none() && self.default.is_none() }),
+            None => match (&self.default, self.previous.take()) {
+                (Some(default), _) => Some(Switch::Default(default.clone())),
+                (None, Some(profile)) => Some(Switch::Restore(profile)),
+                (None, None) => None,
+            },
+        }
+    }
+}
+
+async fn apply(client: &BeacnClient, profile: Result<Profile>) -> Result<()> {
+    client.apply_profile(&profile?).await
+}
+
+// The ring as it is underneath anything on it for now (such as OBS), so that isn't what comes back
+async fn capture(client: &BeacnClient, animations: &AnimationHandle) -> Result<Profile> {
+    let mut profile = client.capture_profile().await?;
+    for (param, value) in animations.underlying().await? {
+        profile.set(BeacnParameter::LED(param), value);
+    }
+    Ok(profile)
+}
+
+fn describe(rule: &AppRule) -> String {
+    match (&rule.process, &rule.window) {
+        (Some(process), Some(window)) => format!("{} ({})", process, window),
+        (Some(name), None) | (None, Some(name)) => name.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+// Earlier rules win when more than one matches
+fn first_match(rules: &[AppRule], processes: &[String], window: Option<&[String]>) -> Option<usize> {
+    rules.iter().position(|rule| matches(rule, processes, window))
+}
+
+fn matches(rule: &AppRule, processes: &[String], window: Option<&[String]>) -> bool {
+    let found = |names: &[String], wanted: &str| names.iter().any(|name| name.eq_ignore_ascii_case(wanted));
+
+    let process = rule.process.as_ref().is_none_or(|process| found(processes, process));
+    let window = rule.window.as_ref().is_none_or(|wanted| window.is_some_and(|classes| found(classes, wanted)));
+    process && window
+}
+
+// The names of everything running which we can see. comm is cut short at 15 characters, so the
+// command's file name is included too.
+fn running_processes() -> Vec<String> {
+    let mut names = vec![];
+    for entry in fs::read_dir("/proc").into_iter().flatten().flatten() {
+        let path = entry.path();
+        if !path.file_name().is_some_and(|name| name.to_string_lossy().bytes().all(|byte| byte.is_ascii_digit())) {
+            continue;
+        }
+
+        let comm = fs::read_to_string(path.join("comm")).ok();
+        let cmdline = fs::read(path.join("cmdline")).ok();
+        names.extend(process_names(comm.as_deref(), cmdline.as_deref()));
+    }
+    names
+}
+
+// A process can be matched by either of these, they're only different for long names or scripts
+fn process_names(comm: Option<&str>, cmdline: Option<&[u8]>) -> Vec<String> {
+    let mut names = vec![];
+    if let Some(comm) = comm {
+        names.push(comm.trim_end().to_string());
+    }
+    if let Some(cmdline) = cmdline {
+        let command = cmdline.split(|byte| *byte == 0).next().unwrap_or_default();
+        if let Some(name) = Path::new(&*String::from_utf8_lossy(command)).file_name() {
+            names.push(name.to_string_lossy().to_string());
+        }
+    }
+    names
+}
+
+struct FocusedWindow {
+    connection: RustConnection,
+    root: Window,
+    active_window: Atom,
+}
+
+impl FocusedWindow {
+    fn connect() -> Result<Self> {
+        let (connection, screen) = x11rb::connect(None)?;
+        let root = connection.setup().roots[screen].root;
+        let active_window = connection.intern_atom(false, b"_NET_ACTIVE_WINDOW")?.reply()?.atom;
+        Ok(Self { connection, root, active_window })
+    }
+
+    // WM_CLASS holds two names (the instance and the class), a rule can match either
+    fn class(&self) -> Result<Vec<String>> {
+        let reply = self.connection.get_property(false, self.root, self.active_window, AtomEnum::WINDOW, 0, 1)?.reply()?;
+        let Some(window) = reply.value32().and_then(|mut values| values.next()).filter(|window| *window != 0) else {
+            return Ok(vec![]);
+        };
+
+        let reply = self.connection.get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)?.reply()?;
+        Ok(reply.value.split(|byte| *byte == 0).filter(|name| !name.is_empty()).map(|name| String::from_utf8_lossy(name).to_string()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(process: Option<&str>, window: Option<&str>, profile: &str) -> AppRule {
+        AppRule { process: process.map(String::from), window: window.map(String::from), profile: profile.to_string() }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn earlier_rules_win() {
+        let rules = [rule(Some("obs"), None, "streaming"), rule(Some("zoom"), None, "meeting"), rule(None, Some("discord"), "chat")];
+        let classes = names(&["discord", "Discord"]);
+
+        assert_eq!(first_match(&rules, &names(&["zoom", "obs"]), None), Some(0));
+        assert_eq!(first_match(&rules, &names(&["zoom"]), Some(&classes)), Some(1));
+        assert_eq!(first_match(&rules, &names(&["bash"]), Some(&classes)), Some(2));
+        assert_eq!(first_match(&rules, &names(&["bash"]), None), None);
+    }
+
+    #[test]
+    fn rules_with_both_need_both() {
+        let rule = rule(Some("firefox"), Some("Navigator"), "browsing");
+        let classes = names(&["navigator", "firefox"]);
+        assert!(matches(&rule, &names(&["Firefox"]), Some(&classes)));
+        assert!(!matches(&rule, &names(&["firefox"]), None));
+        assert!(!matches(&rule, &names(&["bash"]), Some(&classes)));
+    }
+
+    #[test]
+    fn processes_match_by_comm_or_command() {
+        // comm is cut short, the command isn't
+        let cmdline = b"/usr/lib/signal-desktop/signal-desktop-beta\0--no-sandbox\0";
+        let found = process_names(Some("signal-desktop-\n"), Some(cmdline));
+        assert_eq!(found, names(&["signal-desktop-", "signal-desktop-beta"]));
+        assert!(matches(&rule(Some("signal-desktop-beta"), None, "chat"), &found, None));
+
+        // Scripts show as their interpreter in the command, but by name in comm
+        let found = process_names(Some("backup.sh\n"), Some(b"/bin/sh\0/home/user/backup.sh\0"));
+        assert!(matches(&rule(Some("backup.sh"), None, "quiet"), &found, None));
+
+        // Kernel threads have no command
+        assert_eq!(process_names(Some("kworker/0:1\n"), Some(b"")), names(&["kworker/0:1"]));
+        assert_eq!(process_names(None, None), names(&[]));
+    }
+
+    #[test]
+    fn settings_are_restored_without_a_default() {
+        let mut switcher = Switcher::new(None);
+        assert_eq!(switcher.update(None), None);
+
+        // Only the first rule captures, moving between rules keeps what was there before either
+        assert_eq!(switcher.update(Some(1)), Some(Switch::Rule { index: 1, save: true }));
+        switcher.previous = Some(Profile::default());
+        assert_eq!(switcher.update(Some(1)), None);
+        assert_eq!(switcher.update(Some(0)), Some(Switch::Rule { index: 0, save: false }));
+
+        assert_eq!(switcher.update(None), Some(Switch::Restore(Profile::default())));
+        assert_eq!(switcher.previous, None);
+    }
+
+    #[test]
+    fn nothing_is_restored_if_capturing_failed() {
+        let mut switcher = Switcher::new(None);
+        assert_eq!(switcher.update(Some(0)), Some(Switch::Rule { index: 0, save: true }));
+        assert_eq!(switcher.update(None), None);
+    }
+
+    #[test]
+    fn default_is_applied_when_set() {
+        let mut switcher = Switcher::new(Some(String::from("normal")));
+        assert_eq!(switcher.update(None), None);
+        assert_eq!(switcher.update(Some(0)), Some(Switch::Rule { index: 0, save: false }));
+        assert_eq!(switcher.update(None), Some(Switch::Default(String::from("normal"))));
+        assert_eq!(switcher.update(None), None);
+    }
+}
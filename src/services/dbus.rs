@@ -0,0 +1,290 @@
+// Exposes the Mic on the session bus, so desktop widgets and scripts can control it, eg.
+//
+//   busctl --user set-property com.beacn.Mic /com/beacn/Mic com.beacn.Mic1 Mode s spectrum
+//   busctl --user call com.beacn.Mic /com/beacn/Mic com.beacn.Mic1 SetColour ss primary '#ff8800'
+//   busctl --user call com.beacn.Mic /com/beacn/Mic com.beacn.Mic1 Flash su '#00ff00' 3
+//
+// Enums and colours are strings, in the same format as the CLI uses.
+
+use anyhow::Result;
+use log::{debug, warn};
+use tokio::sync::broadcast::error::RecvError;
+use zbus::{connection, fdo, interface};
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::BeacnParameter;
+use beacn_lib::messages::led::{LEDColour, LEDParameter};
+use beacn_lib::messages::schema::ParameterValue;
+use beacn_lib::profile::Profile;
+use crate::services::animation::{Animation, AnimationHandle};
+
+const BUS_NAME: &str = "com.beacn.Mic";
+const OBJECT_PATH: &str = "/com/beacn/Mic";
+
+struct MicInterface {
+    client: BeacnClient,
+    animations: AnimationHandle,
+}
+
+pub async fn run(client: BeacnClient, animations: AnimationHandle) -> Result<()> {
+    // Subscribe before registering, so nothing is missed in between
+    let mut events = client.subscribe();
+
+    let connection = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, MicInterface { client, animations })?
+        .build()
+        .await?;
+    debug!("Registered {} on the session bus", BUS_NAME);
+
+    // Changes can come from anywhere (the UI, the button on the Mic, other services), so the
+    // signals are driven from the device events rather than our own setters.
+    let interface = connection.object_server().interface::<_, MicInterface>(OBJECT_PATH).await?;
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+
+        let emitter = interface.signal_emitter();
+        let mic = interface.get().await;
+        let result = match event {
+            DeviceEvent::MuteChanged(_) => mic.muted_changed(emitter).await,
+            DeviceEvent::ParameterChanged(BeacnParameter::LED(param), _) => match param {
+                LEDParameter::Mode => mic.mode_changed(emitter).await,
+                LEDParameter::Colour1 => mic.colour1_changed(emitter).await,
+                LEDParameter::Colour2 => mic.colour2_changed(emitter).await,
+                LEDParameter::Speed => mic.speed_changed(emitter).await,
+                LEDParameter::Brightness => mic.brightness_changed(emitter).await,
+                LEDParameter::MeterSource => mic.meter_source_changed(emitter).await,
+                LEDParameter::MeterSensitivity => mic.meter_sensitivity_changed(emitter).await,
+                LEDParameter::MuteMode => mic.mute_mode_changed(emitter).await,
+                LEDParameter::MuteColour => mic.mute_colour_changed(emitter).await,
+                LEDParameter::SuspendMode => mic.suspend_mode_changed(emitter).await,
+                LEDParameter::SuspendBrightness => mic.suspend_brightness_changed(emitter).await,
+            },
+            _ => continue,
+        };
+
+        if let Err(e) = result {
+            warn!("Unable to send PropertiesChanged: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn failed(error: anyhow::Error) -> fdo::Error {
+    fdo::Error::Failed(format!("{:#}", error))
+}
+
+fn invalid(error: anyhow::Error) -> fdo::Error {
+    fdo::Error::InvalidArgs(format!("{:#}", error))
+}
+
+impl MicInterface {
+    async fn value(&self, param: LEDParameter) -> fdo::Result<ParameterValue> {
+        self.client.get_value(BeacnParameter::LED(param)).await.map_err(failed)
+    }
+
+    async fn set_value(&self, param: LEDParameter, value: ParameterValue) -> fdo::Result<()> {
+        let param = BeacnParameter::LED(param);
+        param.schema().encode(value).map_err(invalid)?;
+        self.client.set_value(param, value).await.map_err(failed)?;
+        Ok(())
+    }
+
+    // Used for enums and colours, which are sent as their names and #rrggbb
+    async fn text(&self, param: LEDParameter) -> fdo::Result<String> {
+        let value = self.value(param).await?;
+        Ok(param.schema().format(value))
+    }
+
+    async fn set_text(&self, param: LEDParameter, value: &str) -> fdo::Result<()> {
+        let value = param.schema().parse(value).map_err(invalid)?;
+        self.set_value(param, value).await
+    }
+
+    async fn int(&self, param: LEDParameter) -> fdo::Result<i32> {
+        match self.value(param).await? {
+            ParameterValue::Int(value) => Ok(value),
+            value => Err(fdo::Error::Failed(format!("Unexpected value {:?}", value))),
+        }
+    }
+
+    async fn uint(&self, param: LEDParameter) -> fdo::Result<u32> {
+        match self.value(param).await? {
+            ParameterValue::UInt(value) => Ok(value),
+            value => Err(fdo::Error::Failed(format!("Unexpected value {:?}", value))),
+        }
+    }
+
+    async fn float(&self, param: LEDParameter) -> fdo::Result<f64> {
+        match self.value(param).await? {
+            ParameterValue::Float(value) => Ok(value as f64),
+            value => Err(fdo::Error::Failed(format!("Unexpected value {:?}", value))),
+        }
+    }
+}
+
+#[interface(name = "com.beacn.Mic1")]
+impl MicInterface {
+    // Slot is one of primary, secondary or mute
+    async fn set_colour(&self, slot: &str, colour: &str) -> fdo::Result<()> {
+        let slot = match slot.to_ascii_lowercase().as_str() {
+            "primary" | "colour1" => LEDColour::Primary,
+            "secondary" | "colour2" => LEDColour::Secondary,
+            "mute" => LEDColour::Mute,
+            _ => return Err(fdo::Error::InvalidArgs(format!("Unknown colour '{}', expected primary, secondary or mute", slot))),
+        };
+        self.set_text(slot.parameter(), colour).await
+    }
+
+    // The Mode property has the same name, so this needs naming explicitly
+    #[zbus(name = "SetMode")]
+    async fn set_mode_method(&self, mode: &str) -> fdo::Result<()> {
+        self.set_text(LEDParameter::Mode, mode).await
+    }
+
+    // Takes the name of a saved profile (see 'profile save')
+    async fn apply_profile(&self, name: &str) -> fdo::Result<()> {
+        let profile = Profile::load_named(name).map_err(invalid)?;
+        self.client.apply_profile(&profile).await.map_err(failed)
+    }
+
+    // Blinks the ring, then puts it back how it was
+    async fn flash(&self, colour: &str, count: u32) -> fdo::Result<()> {
+        self.animations.flash(colour, count).map_err(invalid)
+    }
+
+    // Takes an animation as JSON, the same as the HTTP API
+    async fn animate(&self, animation: &str) -> fdo::Result<()> {
+        let animation: Animation = serde_json::from_str(animation).map_err(|e| invalid(e.into()))?;
+        self.animations.play(animation).map_err(invalid)
+    }
+
+    async fn stop_animation(&self) -> fdo::Result<()> {
+        self.animations.stop().map_err(failed)
+    }
+
+    #[zbus(property)]
+    async fn mode(&self) -> fdo::Result<String> {
+        self.text(LEDParameter::Mode).await
+    }
+
+    #[zbus(property)]
+    async fn set_mode(&self, value: String) -> fdo::Result<()> {
+        self.set_text(LEDParameter::Mode, &value).await
+    }
+
+    #[zbus(property)]
+    async fn colour1(&self) -> fdo::Result<String> {
+        self.text(LEDParameter::Colour1).await
+    }
+
+    #[zbus(property)]
+    async fn set_colour1(&self, value: String) -> fdo::Result<()> {
+        self.set_text(LEDParameter::Colour1, &value).await
+    }
+
+    #[zbus(property)]
+    async fn colour2(&self) -> fdo::Result<String> {
+        self.text(LEDParameter::Colour2).await
+    }
+
+    #[zbus(property)]
+    async fn set_colour2(&self, value: String) -> fdo::Result<()> {
+        self.set_text(LEDParameter::Colour2, &value).await
+    }
+
+    #[zbus(property)]
+    async fn speed(&self) -> fdo::Result<i32> {
+        self.int(LEDParameter::Speed).await
+    }
+
+    #[zbus(property)]
+    async fn set_speed(&self, value: i32) -> fdo::Result<()> {
+        self.set_value(LEDParameter::Speed, ParameterValue::Int(value)).await
+    }
+
+    #[zbus(property)]
+    async fn brightness(&self) -> fdo::Result<i32> {
+        self.int(LEDParameter::Brightness).await
+    }
+
+    #[zbus(property)]
+    async fn set_brightness(&self, value: i32) -> fdo::Result<()> {
+        self.set_value(LEDParameter::Brightness, ParameterValue::Int(value)).await
+    }
+
+    #[zbus(property)]
+    async fn meter_source(&self) -> fdo::Result<String> {
+        self.text(LEDParameter::MeterSource).await
+    }
+
+    #[zbus(property)]
+    async fn set_meter_source(&self, value: String) -> fdo::Result<()> {
+        self.set_text(LEDParameter::MeterSource, &value).await
+    }
+
+    #[zbus(property)]
+    async fn meter_sensitivity(&self) -> fdo::Result<f64> {
+        self.float(LEDParameter::MeterSensitivity).await
+    }
+
+    #[zbus(property)]
+    async fn set_meter_sensitivity(&self, value: f64) -> fdo::Result<()> {
+        self.set_value(LEDParameter::MeterSensitivity, ParameterValue::Float(value as f32)).await
+    }
+
+    #[zbus(property)]
+    async fn mute_mode(&self) -> fdo::Result<String> {
+        self.text(LEDParameter::MuteMode).await
+    }
+
+    #[zbus(property)]
+    async fn set_mute_mode(&self, value: String) -> fdo::Result<()> {
+        self.set_text(LEDParameter::MuteMode, &value).await
+    }
+
+    #[zbus(property)]
+    async fn mute_colour(&self) -> fdo::Result<String> {
+        self.text(LEDParameter::MuteColour).await
+    }
+
+    #[zbus(property)]
+    async fn set_mute_colour(&self, value: String) -> fdo::Result<()> {
+        self.set_text(LEDParameter::MuteColour, &value).await
+    }
+
+    #[zbus(property)]
+    async fn suspend_mode(&self) -> fdo::Result<String> {
+        self.text(LEDParameter::SuspendMode).await
+    }
+
+    #[zbus(property)]
+    async fn set_suspend_mode(&self, value: String) -> fdo::Result<()> {
+        self.set_text(LEDParameter::SuspendMode, &value).await
+    }
+
+    #[zbus(property)]
+    async fn suspend_brightness(&self) -> fdo::Result<u32> {
+        self.uint(LEDParameter::SuspendBrightness).await
+    }
+
+    #[zbus(property)]
+    async fn set_suspend_brightness(&self, value: u32) -> fdo::Result<()> {
+        self.set_value(LEDParameter::SuspendBrightness, ParameterValue::UInt(value)).await
+    }
+
+    #[zbus(property)]
+    async fn muted(&self) -> fdo::Result<bool> {
+        self.client.get_muted().await.map_err(failed)
+    }
+
+    #[zbus(property)]
+    async fn set_muted(&self, value: bool) -> fdo::Result<()> {
+        self.client.set_muted(value).await.map_err(failed)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+// Runs commands when things happen on the Mic, for tying it into other scripts, eg.
+//
+//   [[hooks.command]]
+//   on = "mute"
+//   run = "notify-send 'Beacn Mic' \"Muted: $BEACN_MUTED\""
+//
+// The events are connect (at the start, and when the Mic is plugged back in), disconnect, mute,
+// profile and change (for everything but the mute, which has its own event). Commands are told about the event in environment variables:
+//
+//   BEACN_EVENT       Always set
+//   BEACN_MUTED       For mute, true or false
+//   BEACN_PROFILE     For profile, the name (empty if it wasn't loaded by name)
+//   BEACN_PARAMETER   For change, the parameter's name and its new value, in the same format as
+//   BEACN_VALUE       the HTTP API uses
+//
+// and get the same as JSON on stdin, eg. {"event":"change","parameter":"led.brightness","value":40}.
+// Commands run in the background, so a slow one doesn't hold up the others.
+
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use log::{debug, warn};
+use serde_json::{Map, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time;
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::BeacnParameter;
+use beacn_lib::messages::mic::MicParameter;
+use crate::config::{HookCommand, HookEvent, HooksConfig};
+use crate::services::json;
+
+// An event, and the details commands are given about it
+struct Hook {
+    event: HookEvent,
+    parameter: Option<BeacnParameter>,
+    details: Map<String, Value>,
+}
+
+impl Hook {
+    fn new(event: HookEvent) -> Self {
+        let mut details = Map::new();
+        details.insert(String::from("event"), Value::from(event.name()));
+        Self { event, parameter: None, details }
+    }
+
+    fn with(mut self, name: &str, value: Value) -> Self {
+        self.details.insert(name.to_string(), value);
+        self
+    }
+
+    fn environment(&self) -> Vec<(String, String)> {
+        self.details.iter().map(|(name, value)| {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                Value::Null => String::new(),
+                value => value.to_string(),
+            };
+            (format!("BEACN_{}", name.to_ascii_uppercase()), value)
+        }).collect()
+    }
+
+    fn wanted_by(&self, command: &HookCommand) -> bool {
+        command.on == self.event && command.parameter.as_ref().is_none_or(|name| self.parameter.is_some_and(|param| param.schema().name == name))
+    }
+}
+
+pub async fn run(client: BeacnClient, config: HooksConfig) -> Result<()> {
+    for command in &config.commands {
+        if let Some(parameter) = &command.parameter {
+            if command.on != HookEvent::Change {
+                bail!("Only change hooks can have a parameter");
+            }
+            let param = BeacnParameter::from_name(parameter).ok_or_else(|| anyhow!("Unknown parameter '{}'", parameter))?;
+            if param == BeacnParameter::Mic(MicParameter::Mute) {
+                bail!("Mute changes are the mute event, rather than change");
+            }
+        }
+    }
+
+    let config = Arc::new(config);
+    let mut events = client.subscribe();
+
+    fire(&config, Hook::new(HookEvent::Connect));
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+
+        match event {
+            DeviceEvent::ParameterChanged(param, value) => {
+                let value = param.schema().decode(value);
+                let mut hook = Hook::new(HookEvent::Change)
+                    .with("parameter", Value::from(param.schema().name))
+                    .with("value", json::to_json(&param.schema(), value));
+                hook.parameter = Some(param);
+                fire(&config, hook);
+            }
+            DeviceEvent::MuteChanged(value) => fire(&config, Hook::new(HookEvent::Mute).with("muted", Value::from(value))),
+            DeviceEvent::ProfileApplied(name) => fire(&config, Hook::new(HookEvent::Profile).with("profile", Value::from(name))),
+            DeviceEvent::Connected => fire(&config, Hook::new(HookEvent::Connect)),
+            DeviceEvent::Disconnected => fire(&config, Hook::new(HookEvent::Disconnect)),
+        }
+    }
+    Ok(())
+}
+
+fn fire(config: &Arc<HooksConfig>, hook: Hook) {
+    let hook = Arc::new(hook);
+    for (index, command) in config.commands.iter().enumerate() {
+        if !hook.wanted_by(command) {
+            continue;
+        }
+
+        let config = config.clone();
+        let hook = hook.clone();
+        tokio::spawn(async move {
+            let command = &config.commands[index].run;
+            if let Err(e) = execute(command, &hook, Duration::from_secs(config.timeout)).await {
+                warn!("Hook for {} failed ({}): {:#}", hook.event.name(), command, e);
+            }
+        });
+    }
+}
+
+async fn execute(command: &str, hook: &Hook, timeout: Duration) -> Result<()> {
+    debug!("Running hook for {}: {}", hook.event.name(), command);
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(hook.environment())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    // Giving up drops the child, which kills it
+    let output = time::timeout(timeout, async {
+        if let Some(mut stdin) = child.stdin.take() {
+            // The command doesn't have to read it
+            let _ = stdin.write_all(Value::Object(hook.details.clone()).to_string().as_bytes()).await;
+        }
+        child.wait_with_output().await
+    }).await.map_err(|_| anyhow!("Timed out after {}s", timeout.as_secs()))??;
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        debug!("[{}] {}", hook.event.name(), line);
+    }
+    if !output.status.success() {
+        bail!("{}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use beacn_lib::messages::led::LEDParameter;
+    use super::*;
+
+    fn command(text: &str) -> Result<HookCommand> {
+        Ok(toml::from_str(&format!("run = 'true'\n{}", text))?)
+    }
+
+    #[test]
+    fn events_are_parsed_from_config() {
+        assert_eq!(command("on = 'mute'").unwrap().on, HookEvent::Mute);
+        assert_eq!(command("on = 'change'").unwrap().on, HookEvent::Change);
+        assert!(command("on = 'muted'").is_err());
+        assert!(command("").is_err());
+    }
+
+    #[test]
+    fn change_hooks_can_be_limited_to_a_parameter() {
+        let command = command("on = 'change'\nparameter = 'led.brightness'").unwrap();
+
+        let mut hook = Hook::new(HookEvent::Change);
+        hook.parameter = Some(BeacnParameter::LED(LEDParameter::Brightness));
+        assert!(hook.wanted_by(&command));
+
+        hook.parameter = Some(BeacnParameter::LED(LEDParameter::Colour1));
+        assert!(!hook.wanted_by(&command));
+        assert!(!Hook::new(HookEvent::Mute).wanted_by(&command));
+    }
+
+    #[test]
+    fn details_are_given_as_environment() {
+        let hook = Hook::new(HookEvent::Mute).with("muted", Value::from(true));
+        let mut environment = hook.environment();
+        environment.sort();
+        assert_eq!(environment, vec![
+            (String::from("BEACN_EVENT"), String::from("mute")),
+            (String::from("BEACN_MUTED"), String::from("true")),
+        ]);
+    }
+}
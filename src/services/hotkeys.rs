@@ -0,0 +1,384 @@
+// Global hotkeys, read straight from the keyboards in /dev/input so they work in any app and under
+// any desktop (including Wayland), eg.
+//
+//   [[hotkeys.key]]
+//   keys = "ctrl+shift+m"
+//   action = "toggle-mute"
+//
+//   [[hotkeys.key]]
+//   keys = "F13"
+//   action = "push-to-talk"
+//
+//   [[hotkeys.key]]
+//   keys = "ctrl+alt+1"
+//   action = "profile"
+//   profile = "streaming"
+//
+// Keys are evdev names, with or without the KEY_ (eg. "m", "F13", "KEY_PAUSE" or "BTN_EXTRA" for a
+// mouse button), and ctrl, shift, alt and super match either side. Push-to-talk unmutes while the
+// keys are held, if the Mic was muted to begin with.
+//
+// The keys are only read, so they still reach the focused app as well. Reading input devices
+// usually needs the user to be in the 'input' group.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use evdev::{Device, EventSummary, KeyCode};
+use log::{debug, info, warn};
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::time::{self, MissedTickBehavior};
+use beacn_lib::client::BeacnClient;
+use beacn_lib::profile::Profile;
+use crate::config::{Hotkey, HotkeyAction, HotkeysConfig};
+
+const INPUT_DIRECTORY: &str = "/dev/input";
+
+// How often to look for keyboards which have been plugged in
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+// Key values from evdev, repeats (2) are ignored
+const RELEASED: i32 = 0;
+const PRESSED: i32 = 1;
+
+// Each group is satisfied by any one of its keys, so 'ctrl' can be either control key
+struct Binding {
+    groups: Vec<Vec<KeyCode>>,
+    action: HotkeyAction,
+    active: bool,
+}
+
+impl Binding {
+    fn from_config(hotkey: &Hotkey) -> Result<Self> {
+        let groups = hotkey.keys.split('+').map(|name| parse_key(name.trim())).collect::<Result<Vec<_>>>()?;
+        Ok(Self { groups, action: hotkey.action.clone(), active: false })
+    }
+
+    fn uses(&self, key: KeyCode) -> bool {
+        self.groups.iter().any(|group| group.contains(&key))
+    }
+
+    fn held(&self, keys: &Keys) -> bool {
+        self.groups.iter().all(|group| group.iter().any(|key| keys.is_held(*key)))
+    }
+}
+
+fn parse_key(name: &str) -> Result<Vec<KeyCode>> {
+    let upper = name.to_ascii_uppercase();
+    let either = |left: KeyCode, right: KeyCode| Ok(vec![left, right]);
+    match upper.as_str() {
+        "CTRL" | "CONTROL" => either(KeyCode::KEY_LEFTCTRL, KeyCode::KEY_RIGHTCTRL),
+        "SHIFT" => either(KeyCode::KEY_LEFTSHIFT, KeyCode::KEY_RIGHTSHIFT),
+        "ALT" => either(KeyCode::KEY_LEFTALT, KeyCode::KEY_RIGHTALT),
+        "SUPER" | "META" => either(KeyCode::KEY_LEFTMETA, KeyCode::KEY_RIGHTMETA),
+        _ => {
+            let key = upper.parse().or_else(|_| format!("KEY_{}", upper).parse());
+            key.map(|key| vec![key]).map_err(|_| anyhow!("Unknown key '{}'", name))
+        }
+    }
+}
+
+// What's held down across every device, so a combination can be split between them
+#[derive(Default)]
+struct Keys(HashMap<PathBuf, HashSet<KeyCode>>);
+
+impl Keys {
+    fn is_held(&self, key: KeyCode) -> bool {
+        self.0.values().any(|keys| keys.contains(&key))
+    }
+}
+
+enum Input {
+    Key(PathBuf, KeyCode, bool),
+    Gone(PathBuf),
+}
+
+pub async fn run(client: BeacnClient, config: HotkeysConfig) -> Result<()> {
+    let mut bindings = config.keys.iter().map(Binding::from_config).collect::<Result<Vec<_>>>()?;
+    if bindings.is_empty() {
+        bail!("No hotkeys are set, add them as [[hotkeys.key]]");
+    }
+    let wanted: HashSet<KeyCode> = bindings.iter().flat_map(|binding| binding.groups.iter().flatten().copied()).collect();
+
+    let (sender, mut inputs) = mpsc::unbounded_channel();
+    let mut scanner = Scanner { wanted, devices: config.devices, reading: HashSet::new(), reported: None };
+    let mut rescan = time::interval(RESCAN_INTERVAL);
+    rescan.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut keys = Keys::default();
+
+    // Whether push-to-talk unmuted the Mic, and so should mute it again
+    let mut talking = false;
+
+    loop {
+        select! {
+            _ = rescan.tick() => scanner.scan(&sender)?,
+            input = inputs.recv() => {
+                let Some(input) = input else {
+                    break;
+                };
+                let pressed = match input {
+                    Input::Key(path, key, pressed) => {
+                        let held = keys.0.entry(path).or_default();
+                        if pressed { held.insert(key) } else { held.remove(&key) };
+                        pressed.then_some(key)
+                    }
+
+                    // Anything held on it is let go, so push-to-talk can't get stuck
+                    Input::Gone(path) => {
+                        info!("Stopped reading {}", path.display());
+                        keys.0.remove(&path);
+                        scanner.reading.remove(&path);
+                        None
+                    }
+                };
+
+                for binding in &mut bindings {
+                    // Only pressing one of its keys starts a binding, not letting go of something else
+                    let held = binding.held(&keys);
+                    if held == binding.active || (held && !pressed.is_some_and(|key| binding.uses(key))) {
+                        continue;
+                    }
+                    binding.active = held;
+                    if let Err(e) = perform(&client, &binding.action, held, &mut talking).await {
+                        warn!("Hotkey failed: {:#}", e);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn perform(client: &BeacnClient, action: &HotkeyAction, pressed: bool, talking: &mut bool) -> Result<()> {
+    match action {
+        HotkeyAction::ToggleMute {} if pressed => {
+            client.toggle_mute().await?;
+        }
+        HotkeyAction::PushToTalk {} => push_to_talk(client, pressed, talking).await?,
+        HotkeyAction::Profile { profile } if pressed => {
+            info!("Switching to profile '{}'", profile);
+            client.apply_profile(&Profile::load_named(profile)?).await?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn push_to_talk(client: &BeacnClient, pressed: bool, talking: &mut bool) -> Result<()> {
+    if pressed && client.get_muted().await? {
+        debug!("Push-to-talk pressed, unmuting");
+        client.set_muted(false).await?;
+        *talking = true;
+    } else if !pressed && std::mem::take(talking) {
+        debug!("Push-to-talk released, muting");
+        client.set_muted(true).await?;
+    }
+    Ok(())
+}
+
+struct Scanner {
+    wanted: HashSet<KeyCode>,
+    devices: Vec<PathBuf>,
+    reading: HashSet<PathBuf>,
+
+    // The last problem logged, so it isn't repeated on every scan
+    reported: Option<String>,
+}
+
+impl Scanner {
+    // Opens any devices which can send our keys, and aren't already being read
+    fn scan(&mut self, sender: &mpsc::UnboundedSender<Input>) -> Result<()> {
+        let paths = match self.devices.is_empty() {
+            true => event_devices()?,
+            false => self.devices.clone(),
+        };
+
+        let mut denied = vec![];
+        for path in paths {
+            if self.reading.contains(&path) {
+                continue;
+            }
+
+            let device = match Device::open(&path) {
+                Ok(device) => device,
+                Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                    denied.push(path);
+                    continue;
+                }
+                Err(e) => {
+                    debug!("Unable to open {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            let has_keys = device.supported_keys().is_some_and(|keys| self.wanted.iter().any(|key| keys.contains(*key)));
+            if !has_keys {
+                continue;
+            }
+
+            info!("Reading hotkeys from {} ({})", device.name().unwrap_or("Unnamed"), path.display());
+            let stream = match device.into_event_stream() {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Unable to read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            self.reading.insert(path.clone());
+            tokio::spawn(read(path, stream, sender.clone()));
+        }
+
+        // Only a problem if it means there's nothing to read from
+        let problem = match (self.reading.is_empty(), denied.first()) {
+            (true, Some(path)) => Some(permission_problem(path)),
+            (true, None) => Some(String::from("No readable input devices have the keys used by the hotkeys")),
+            (false, _) => None,
+        };
+        if problem != self.reported {
+            if let Some(problem) = &problem {
+                warn!("{}", problem);
+            }
+            self.reported = problem;
+        }
+        Ok(())
+    }
+}
+
+fn event_devices() -> Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(INPUT_DIRECTORY).map_err(|e| anyhow!("Unable to list {}: {}", INPUT_DIRECTORY, e))?;
+    let mut paths: Vec<PathBuf> = entries.flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+// Says who can read the device, and how to join them
+fn permission_problem(path: &Path) -> String {
+    let group = fs::metadata(path).ok().and_then(|metadata| group_name(metadata.gid()));
+    match group {
+        Some(group) => format!(
+            "Permission denied reading {} (and maybe others), which needs the '{}' group. Add yourself with 'sudo usermod -aG {} $USER' and log in again.",
+            path.display(), group, group
+        ),
+        None => format!("Permission denied reading {} (and maybe others), check the permissions of {}", path.display(), INPUT_DIRECTORY),
+    }
+}
+
+fn group_name(gid: u32) -> Option<String> {
+    let groups = fs::read_to_string("/etc/group").ok()?;
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)? == gid.to_string()).then(|| name.to_string())
+    })
+}
+
+async fn read(path: PathBuf, mut stream: evdev::EventStream, sender: mpsc::UnboundedSender<Input>) {
+    loop {
+        // An error here is almost always the device being unplugged
+        let event = match stream.next_event().await {
+            Ok(event) => event,
+            Err(e) => {
+                debug!("Unable to read {}: {}", path.display(), e);
+                break;
+            }
+        };
+
+        if let EventSummary::Key(_, key, value @ (RELEASED | PRESSED)) = event.destructure() {
+            if sender.send(Input::Key(path.clone(), key, value == PRESSED)).is_err() {
+                return;
+            }
+        }
+    }
+    let _ = sender.send(Input::Gone(path));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hotkey(text: &str) -> Result<Hotkey> {
+        Ok(toml::from_str(text)?)
+    }
+
+    fn binding(keys: &str) -> Binding {
+        let hotkey = Hotkey { keys: keys.to_string(), action: HotkeyAction::ToggleMute {} };
+        Binding::from_config(&hotkey).unwrap()
+    }
+
+    fn holding(keys: &[KeyCode]) -> Keys {
+        let mut held = Keys::default();
+        held.0.insert(PathBuf::from("/dev/input/event0"), keys.iter().copied().collect());
+        held
+    }
+
+    #[test]
+    fn modifiers_match_either_side() {
+        assert_eq!(parse_key("ctrl").unwrap(), vec![KeyCode::KEY_LEFTCTRL, KeyCode::KEY_RIGHTCTRL]);
+        assert_eq!(parse_key("Control").unwrap(), vec![KeyCode::KEY_LEFTCTRL, KeyCode::KEY_RIGHTCTRL]);
+        assert_eq!(parse_key("SHIFT").unwrap(), vec![KeyCode::KEY_LEFTSHIFT, KeyCode::KEY_RIGHTSHIFT]);
+        assert_eq!(parse_key("alt").unwrap(), vec![KeyCode::KEY_LEFTALT, KeyCode::KEY_RIGHTALT]);
+        assert_eq!(parse_key("super").unwrap(), vec![KeyCode::KEY_LEFTMETA, KeyCode::KEY_RIGHTMETA]);
+        assert_eq!(parse_key("meta").unwrap(), vec![KeyCode::KEY_LEFTMETA, KeyCode::KEY_RIGHTMETA]);
+
+        let binding = binding("ctrl+m");
+        assert!(binding.held(&holding(&[KeyCode::KEY_LEFTCTRL, KeyCode::KEY_M])));
+        assert!(binding.held(&holding(&[KeyCode::KEY_RIGHTCTRL, KeyCode::KEY_M])));
+        assert!(!binding.held(&holding(&[KeyCode::KEY_M])));
+    }
+
+    #[test]
+    fn keys_are_named_with_or_without_prefix() {
+        assert_eq!(parse_key("m").unwrap(), vec![KeyCode::KEY_M]);
+        assert_eq!(parse_key("F13").unwrap(), vec![KeyCode::KEY_F13]);
+        assert_eq!(parse_key("KEY_PAUSE").unwrap(), vec![KeyCode::KEY_PAUSE]);
+        assert_eq!(parse_key("key_pause").unwrap(), vec![KeyCode::KEY_PAUSE]);
+        assert_eq!(parse_key("BTN_EXTRA").unwrap(), vec![KeyCode::BTN_EXTRA]);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(parse_key("nope").is_err());
+        assert!(parse_key("").is_err());
+
+        let hotkey = Hotkey { keys: String::from("ctrl+nope"), action: HotkeyAction::ToggleMute {} };
+        let error = Binding::from_config(&hotkey).err().unwrap();
+        assert_eq!(error.to_string(), "Unknown key 'nope'");
+    }
+
+    #[test]
+    fn combinations_can_be_split_across_devices() {
+        let binding = binding("ctrl + shift + m");
+        assert!(binding.uses(KeyCode::KEY_RIGHTSHIFT));
+        assert!(!binding.uses(KeyCode::KEY_N));
+
+        let mut keys = holding(&[KeyCode::KEY_LEFTCTRL, KeyCode::KEY_LEFTSHIFT]);
+        assert!(!binding.held(&keys));
+        keys.0.insert(PathBuf::from("/dev/input/event1"), HashSet::from([KeyCode::KEY_M]));
+        assert!(binding.held(&keys));
+    }
+
+    #[test]
+    fn actions_are_parsed_from_config() {
+        assert_eq!(hotkey("keys = 'F13'\naction = 'toggle-mute'").unwrap().action, HotkeyAction::ToggleMute {});
+        assert_eq!(hotkey("keys = 'F13'\naction = 'push-to-talk'").unwrap().action, HotkeyAction::PushToTalk {});
+        assert_eq!(
+            hotkey("keys = 'F13'\naction = 'profile'\nprofile = 'streaming'").unwrap().action,
+            HotkeyAction::Profile { profile: String::from("streaming") }
+        );
+
+        // Profiles are needed for profile, and only for profile
+        assert!(hotkey("keys = 'F13'\naction = 'profile'").is_err());
+        assert!(hotkey("keys = 'F13'\naction = 'toggle-mute'\nprofile = 'streaming'").is_err());
+        assert!(hotkey("keys = 'F13'\naction = 'nope'").is_err());
+        assert!(hotkey("keys = 'F13'").is_err());
+    }
+}
@@ -0,0 +1,299 @@
+// A local HTTP API, for dashboards and other tools which can't use D-Bus:
+//
+//   GET  /state                Every setting, grouped as {"led": {"mode": "solid", ...}, ...}
+//   GET  /parameters           Every parameter, with its valid values (the same as 'list')
+//   GET  /{group}/{name}       A single value, eg. /led/brightness
+//   PUT  /{group}/{name}       Change a value, the body is the new value as JSON (eg. 50 or "#ff0000")
+//   GET  /ws[?meters=true]     A WebSocket streaming changes (and optionally meter levels)
+//   POST /animation            Play an animation on the ring (see services/animation), eg.
+//                              {"type": "flash", "colour": "#ff0000", "count": 3}
+//   DELETE /animation          Stop the animation playing
+//
+// Every request needs an 'Authorization: Bearer <token>' header. Browsers can't add headers to a
+// WebSocket, so a 'token' query parameter is accepted as well.
+
+use std::sync::Arc;
+use anyhow::{anyhow, Result};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tower_http::cors::CorsLayer;
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::BeacnParameter;
+use beacn_lib::messages::mic::MicParameter;
+use beacn_lib::messages::schema::ParameterValue;
+use beacn_lib::meter::MeterLevels;
+use crate::config::HttpConfig;
+use crate::services::animation::{Animation, AnimationHandle};
+use crate::services::json;
+
+#[derive(Clone)]
+struct ApiState {
+    client: BeacnClient,
+    animations: AnimationHandle,
+    token: Arc<str>,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+fn bad_request(error: anyhow::Error) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, format!("{:#}", error))
+}
+
+fn device_error(error: anyhow::Error) -> ApiError {
+    ApiError(StatusCode::BAD_GATEWAY, format!("{:#}", error))
+}
+
+pub async fn run(client: BeacnClient, animations: AnimationHandle, config: HttpConfig) -> Result<()> {
+    let token = config.token.filter(|token| !token.is_empty());
+    let token = token.ok_or_else(|| anyhow!("http.token must be set to enable the HTTP server"))?;
+    let state = ApiState { client, animations, token: token.into() };
+
+    let listener = TcpListener::bind(&config.address).await?;
+    let address = listener.local_addr()?;
+    if !address.ip().is_loopback() {
+        warn!("HTTP server is listening on {}, which is reachable from other machines", address);
+    }
+    info!("HTTP server listening on {}", address);
+
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/state", get(get_state))
+        .route("/parameters", get(get_parameters))
+        .route("/ws", get(websocket))
+        .route("/animation", post(play_animation).delete(stop_animation))
+        .route("/{group}/{name}", get(get_parameter).put(put_parameter))
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        // This goes outside the authentication, as browsers don't send credentials with preflights
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
+
+async fn authenticate(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let header = request.headers().get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+    let header = header.and_then(|value| value.strip_prefix("Bearer "));
+    let query = request.uri().query().unwrap_or_default().split('&').find_map(|pair| pair.strip_prefix("token="));
+
+    match header.or(query) {
+        Some(token) if token == &*state.token => next.run(request).await,
+        _ => ApiError(StatusCode::UNAUTHORIZED, String::from("Missing or invalid token")).into_response(),
+    }
+}
+
+fn find_parameter(group: &str, name: &str) -> Result<BeacnParameter, ApiError> {
+    let name = format!("{}.{}", group, name);
+    match BeacnParameter::from_name(&name) {
+        Some(param) => Ok(param),
+        None => Err(ApiError(StatusCode::NOT_FOUND, format!("Unknown parameter '{}'", name))),
+    }
+}
+
+async fn get_state(State(state): State<ApiState>) -> Result<Json<Value>, ApiError> {
+    Ok(Json(json::state(&state.client).await.map_err(device_error)?))
+}
+
+async fn get_parameters() -> Json<Value> {
+    Json(json::parameters())
+}
+
+async fn get_parameter(State(state): State<ApiState>, Path((group, name)): Path<(String, String)>) -> Result<Json<Value>, ApiError> {
+    let param = find_parameter(&group, &name)?;
+    let value = state.client.get_value(param).await.map_err(device_error)?;
+    Ok(Json(json::to_json(&param.schema(), value)))
+}
+
+async fn put_parameter(State(state): State<ApiState>, Path((group, name)): Path<(String, String)>, Json(value): Json<Value>) -> Result<Json<Value>, ApiError> {
+    let param = find_parameter(&group, &name)?;
+    let schema = param.schema();
+    if schema.read_only {
+        return Err(ApiError(StatusCode::METHOD_NOT_ALLOWED, format!("{} is read only", schema.name)));
+    }
+
+    let value = json::from_json(&schema, &value).map_err(bad_request)?;
+    let value = state.client.set_value(param, value).await.map_err(device_error)?;
+    Ok(Json(json::to_json(&schema, value)))
+}
+
+async fn play_animation(State(state): State<ApiState>, Json(animation): Json<Animation>) -> Result<StatusCode, ApiError> {
+    state.animations.play(animation).map_err(bad_request)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn stop_animation(State(state): State<ApiState>) -> Result<StatusCode, ApiError> {
+    state.animations.stop().map_err(device_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct StreamOptions {
+    #[serde(default)]
+    meters: bool,
+}
+
+async fn websocket(State(state): State<ApiState>, Query(options): Query<StreamOptions>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| async move {
+        debug!("WebSocket client connected");
+        if let Err(e) = stream(socket, state.client, options.meters).await {
+            debug!("WebSocket client disconnected: {}", e);
+        }
+    })
+}
+
+// Sends the current state, followed by every change as it happens. Messages look like:
+//   {"type": "state", "state": {...}}
+//   {"type": "changed", "parameter": "led.mode", "value": "spectrum"}
+//   {"type": "meters", "mic": {"rms": -40.0, "peak": -30.0}, "headphones": {...}}
+async fn stream(mut socket: WebSocket, client: BeacnClient, meters: bool) -> Result<()> {
+    let mut events = client.subscribe();
+
+    // Only subscribe if asked, so the device isn't polled for levels that nobody wants
+    let mut meters = meters.then(|| client.subscribe_meters());
+
+    let state = json::state(&client).await?;
+    send(&mut socket, json!({ "type": "state", "state": state })).await?;
+
+    loop {
+        select! {
+            event = events.recv() => {
+                let (param, value) = match event {
+                    Ok(DeviceEvent::ParameterChanged(param, value)) => (param, param.schema().decode(value)),
+                    Ok(DeviceEvent::MuteChanged(muted)) => (BeacnParameter::Mic(MicParameter::Mute), ParameterValue::Bool(muted)),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                let schema = param.schema();
+                send(&mut socket, json!({ "type": "changed", "parameter": schema.name, "value": json::to_json(&schema, value) })).await?;
+            }
+            levels = next_levels(&mut meters) => {
+                let mut message = json::meters(&levels?);
+                message["type"] = json!("meters");
+                send(&mut socket, message).await?;
+            }
+            message = socket.recv() => {
+                // We don't expect anything from the client, other than it going away
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Waits forever when meters aren't wanted, so the select! above never picks this branch
+async fn next_levels(meters: &mut Option<broadcast::Receiver<MeterLevels>>) -> Result<MeterLevels> {
+    let Some(meters) = meters else {
+        return std::future::pending().await;
+    };
+    loop {
+        match meters.recv().await {
+            Ok(levels) => return Ok(levels),
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return Err(anyhow!("Meters are no longer available")),
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: Value) -> Result<()> {
+    socket.send(Message::Text(message.to_string().into())).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::tungstenite;
+    use crate::services::animation;
+    use super::*;
+
+    const TOKEN: &str = "secret";
+
+    async fn serve() -> SocketAddr {
+        let client = BeacnClient::connect_simulated();
+        let state = ApiState { animations: animation::start(client.clone()), client, token: TOKEN.into() };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        address
+    }
+
+    // Just enough HTTP/1.1 to check the status and body
+    async fn request(address: SocketAddr, method: &str, path: &str, token: Option<&str>, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n", method, path, address);
+        if let Some(token) = token {
+            request += &format!("Authorization: Bearer {}\r\n", token);
+        }
+        if !body.is_empty() {
+            request += &format!("Content-Type: application/json\r\nContent-Length: {}\r\n", body.len());
+        }
+        request += "\r\n";
+        request += body;
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response.split(' ').nth(1).unwrap().parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn reads_and_changes_a_parameter() {
+        let address = serve().await;
+        let (status, _) = request(address, "PUT", "/led/brightness", Some(TOKEN), "42").await;
+        assert_eq!(status, 200);
+        assert_eq!(request(address, "GET", "/led/brightness", Some(TOKEN), "").await, (200, json!(42)));
+    }
+
+    #[tokio::test]
+    async fn needs_the_token() {
+        let address = serve().await;
+        assert_eq!(request(address, "GET", "/led/brightness", None, "").await.0, 401);
+        assert_eq!(request(address, "GET", "/led/brightness", Some("wrong"), "").await.0, 401);
+        assert_eq!(request(address, "PUT", "/led/brightness", Some("wrong"), "42").await.0, 401);
+    }
+
+    #[tokio::test]
+    async fn streams_changes() {
+        let address = serve().await;
+        let url = format!("ws://{}/ws?token={}", address, TOKEN);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        let mut next = async || match socket.next().await.unwrap().unwrap() {
+            tungstenite::Message::Text(text) => serde_json::from_str::<Value>(&text).unwrap(),
+            message => panic!("Unexpected message {:?}", message),
+        };
+        assert_eq!(next().await["type"], "state");
+
+        request(address, "PUT", "/led/brightness", Some(TOKEN), "17").await;
+        assert_eq!(next().await, json!({ "type": "changed", "parameter": "led.brightness", "value": 17 }));
+    }
+}
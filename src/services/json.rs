@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use beacn_lib::client::BeacnClient;
+use beacn_lib::messages::BeacnParameter;
+use beacn_lib::messages::schema::{ParameterSchema, ParameterValue, ValueKind};
+use beacn_lib::meter::MeterLevels;
+
+// Values are sent as JSON numbers and booleans where possible, with enums as their names and
+// colours as #rrggbb (the same as the CLI).
+pub fn to_json(schema: &ParameterSchema, value: ParameterValue) -> Value {
+    match (schema.kind, value) {
+        (ValueKind::Enum(_), _) | (_, ParameterValue::Colour(_)) => Value::String(schema.format(value)),
+        (_, ParameterValue::UInt(v)) => json!(v),
+        (_, ParameterValue::Int(v)) => json!(v),
+        (_, ParameterValue::Float(v)) => json!(v),
+        (_, ParameterValue::Bool(v)) => json!(v),
+    }
+}
+
+// Accepts anything to_json() produces, and strings in any format the CLI accepts
+pub fn from_json(schema: &ParameterSchema, value: &Value) -> Result<ParameterValue> {
+    let invalid = || anyhow!("{} is not a valid value for {} ({})", value, schema.name, schema.kind);
+
+    let value = match (schema.kind, value) {
+        (_, Value::String(text)) => return schema.parse(text),
+        (ValueKind::Bool, Value::Bool(v)) => ParameterValue::Bool(*v),
+        (ValueKind::Enum(_) | ValueKind::UInt(..), Value::Number(v)) => {
+            ParameterValue::UInt(v.as_u64().and_then(|v| v.try_into().ok()).ok_or_else(invalid)?)
+        }
+        (ValueKind::Int(..), Value::Number(v)) => {
+            ParameterValue::Int(v.as_i64().and_then(|v| v.try_into().ok()).ok_or_else(invalid)?)
+        }
+        (ValueKind::Float(..), Value::Number(v)) => ParameterValue::Float(v.as_f64().ok_or_else(invalid)? as f32),
+        _ => return Err(invalid()),
+    };
+
+    schema.encode(value)?;
+    Ok(value)
+}
+
+// Every setting, grouped the same way as the parameter names (eg. {"led": {"mode": "solid"}})
+pub async fn state(client: &BeacnClient) -> Result<Value> {
+    let mut state = Map::new();
+    for param in BeacnParameter::all() {
+        let schema = param.schema();
+        if schema.read_only {
+            continue;
+        }
+
+        let value = to_json(&schema, client.get_value(param).await?);
+        let (group, name) = schema.name.split_once('.').unwrap_or(("", schema.name));
+        let group = state.entry(group).or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(group) = group {
+            group.insert(name.to_string(), value);
+        }
+    }
+    Ok(Value::Object(state))
+}
+
+pub fn parameters() -> Value {
+    let parameters = BeacnParameter::all().into_iter().map(|param| {
+        let schema = param.schema();
+        json!({
+            "name": schema.name,
+            "description": schema.description,
+            "values": schema.kind.to_string(),
+            "unit": schema.unit,
+            "default": to_json(&schema, schema.default),
+            "read_only": schema.read_only,
+        })
+    }).collect();
+    Value::Array(parameters)
+}
+
+pub fn meters(levels: &MeterLevels) -> Value {
+    json!({
+        "mic": { "rms": levels.mic.rms, "peak": levels.mic.peak },
+        "headphones": { "rms": levels.headphones.rms, "peak": levels.headphones.peak },
+    })
+}
@@ -0,0 +1,219 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use beacn_lib::messages::{BeacnParameter, RGB};
+use beacn_lib::messages::schema::{ParameterSchema, ParameterValue, ValueKind};
+use crate::services::midi::message::MidiMessage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MidiSource {
+    Control(u8),
+    Note(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mapping {
+    pub channel: u8,
+    pub source: MidiSource,
+    pub parameter: BeacnParameter,
+}
+
+// How a mapping is stored in midi.toml, eg.
+//
+//   [[mapping]]
+//   channel = 1
+//   control = 7
+//   parameter = "led.brightness"
+#[derive(Serialize, Deserialize)]
+struct StoredMapping {
+    channel: u8,
+    #[serde(flatten)]
+    source: MidiSource,
+    parameter: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct MappingFile {
+    #[serde(default)]
+    mapping: Vec<StoredMapping>,
+}
+
+impl Mapping {
+    // Kept apart from config.toml, as learning mappings rewrites it
+    pub fn path() -> Result<PathBuf> {
+        Ok(beacn_lib::config_directory()?.join("midi.toml"))
+    }
+
+    pub fn load_all() -> Result<Vec<Mapping>> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let text = fs::read_to_string(&path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let file: MappingFile = toml::from_str(&text).with_context(|| format!("Unable to parse {}", path.display()))?;
+        file.mapping.into_iter().map(|stored| {
+            let parameter = BeacnParameter::from_name(&stored.parameter).ok_or_else(|| anyhow!("Unknown parameter '{}'", stored.parameter))?;
+            if parameter.schema().read_only {
+                bail!("{} is read only", stored.parameter);
+            }
+            if !(1..=16).contains(&stored.channel) {
+                bail!("MIDI channel {} is not between 1 and 16", stored.channel);
+            }
+            let (MidiSource::Control(number) | MidiSource::Note(number)) = stored.source;
+            if number > 127 {
+                bail!("MIDI control or note {} is over 127", number);
+            }
+
+            Ok(Mapping { channel: stored.channel - 1, source: stored.source, parameter })
+        }).collect::<Result<_>>().with_context(|| format!("Invalid mapping in {}", path.display()))
+    }
+
+    pub fn save_all(mappings: &[Mapping]) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mapping = mappings.iter().map(|mapping| StoredMapping {
+            channel: mapping.channel + 1,
+            source: mapping.source,
+            parameter: mapping.parameter.schema().name.to_string(),
+        }).collect();
+        fs::write(&path, toml::to_string(&MappingFile { mapping })?).with_context(|| format!("Unable to write {}", path.display()))
+    }
+
+    // The note off for a mapped note is still 'for' the mapping, even though it does nothing
+    pub fn matches(&self, message: &MidiMessage) -> bool {
+        match (self.source, *message) {
+            (MidiSource::Control(number), MidiMessage::ControlChange { channel, control, .. }) => channel == self.channel && control == number,
+            (MidiSource::Note(number), MidiMessage::NoteOn { channel, note, .. } | MidiMessage::NoteOff { channel, note }) => {
+                channel == self.channel && note == number
+            }
+            _ => false,
+        }
+    }
+
+    // What to send back to the controller so it shows the value (eg. moving a motorised fader)
+    pub fn feedback(&self, value: ParameterValue) -> MidiMessage {
+        let scaled = from_parameter(&self.parameter.schema(), value);
+        match self.source {
+            MidiSource::Control(control) => MidiMessage::ControlChange { channel: self.channel, control, value: scaled },
+
+            // Button lights are usually lit by a note on, and turned off with a zero velocity
+            MidiSource::Note(note) => MidiMessage::NoteOn { channel: self.channel, note, velocity: scaled },
+        }
+    }
+}
+
+impl Display for Mapping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.source {
+            MidiSource::Control(control) => write!(f, "Channel {} CC {}", self.channel + 1, control),
+            MidiSource::Note(note) => write!(f, "Channel {} Note {}", self.channel + 1, note),
+        }
+    }
+}
+
+// Spreads a 7 bit MIDI value across the parameter's range. Enums are split into equal bands, and
+// colours go around the hue wheel.
+pub fn to_parameter(schema: &ParameterSchema, value: u8) -> ParameterValue {
+    let fraction = value.min(127) as f32 / 127.;
+    match schema.kind {
+        ValueKind::Enum(options) => {
+            let index = ((fraction * options.len() as f32) as usize).min(options.len() - 1);
+            ParameterValue::UInt(options[index].0)
+        }
+        ValueKind::UInt(min, max) => ParameterValue::UInt(min + (fraction * (max - min) as f32).round() as u32),
+        ValueKind::Int(min, max) => ParameterValue::Int(min + (fraction * (max - min) as f32).round() as i32),
+        ValueKind::Float(min, max) => ParameterValue::Float(min + fraction * (max - min)),
+        ValueKind::Bool => ParameterValue::Bool(value >= 64),
+        ValueKind::Colour => ParameterValue::Colour(RGB::from_hsv(fraction * 360., 1., 1.)),
+    }
+}
+
+pub fn from_parameter(schema: &ParameterSchema, value: ParameterValue) -> u8 {
+    let fraction = |value: f32, min: f32, max: f32| {
+        if max > min { ((value - min) / (max - min)).clamp(0., 1.) } else { 0. }
+    };
+
+    let fraction = match (schema.kind, value) {
+        (ValueKind::Enum(options), ParameterValue::UInt(value)) => {
+            let index = options.iter().position(|(option, _)| *option == value).unwrap_or_default();
+            // Aim for the middle of the band, so it maps back to the same option
+            (index as f32 + 0.5) / options.len() as f32
+        }
+        (ValueKind::UInt(min, max), ParameterValue::UInt(value)) => fraction(value as f32, min as f32, max as f32),
+        (ValueKind::Int(min, max), ParameterValue::Int(value)) => fraction(value as f32, min as f32, max as f32),
+        (ValueKind::Float(min, max), ParameterValue::Float(value)) => fraction(value, min, max),
+        (_, ParameterValue::Bool(value)) => f32::from(u8::from(value)),
+        (_, ParameterValue::Colour(value)) => value.to_hsv().0 / 360.,
+        _ => 0.,
+    };
+    (fraction * 127.).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use beacn_lib::messages::led::LEDParameter;
+    use super::*;
+
+    fn schema(param: LEDParameter) -> ParameterSchema {
+        BeacnParameter::LED(param).schema()
+    }
+
+    #[test]
+    fn scales_across_the_range() {
+        let brightness = schema(LEDParameter::Brightness);
+        assert_eq!(to_parameter(&brightness, 0), ParameterValue::Int(0));
+        assert_eq!(to_parameter(&brightness, 127), ParameterValue::Int(100));
+        assert_eq!(to_parameter(&brightness, 64), ParameterValue::Int(50));
+        assert_eq!(from_parameter(&brightness, ParameterValue::Int(100)), 127);
+        assert_eq!(from_parameter(&brightness, ParameterValue::Int(0)), 0);
+
+        let speed = schema(LEDParameter::Speed);
+        assert_eq!(to_parameter(&speed, 0), ParameterValue::Int(-10));
+        assert_eq!(from_parameter(&speed, ParameterValue::Int(0)), 64);
+    }
+
+    #[test]
+    fn values_survive_the_round_trip() {
+        let brightness = schema(LEDParameter::Brightness);
+        for value in 0..=100 {
+            let midi = from_parameter(&brightness, ParameterValue::Int(value));
+            assert_eq!(to_parameter(&brightness, midi), ParameterValue::Int(value));
+        }
+
+        let mode = schema(LEDParameter::Mode);
+        let ValueKind::Enum(options) = mode.kind else {
+            panic!("led.mode is an enum");
+        };
+        for (option, _) in options {
+            let midi = from_parameter(&mode, ParameterValue::UInt(*option));
+            assert_eq!(to_parameter(&mode, midi), ParameterValue::UInt(*option));
+        }
+    }
+
+    #[test]
+    fn out_of_range_is_clamped() {
+        let brightness = schema(LEDParameter::Brightness);
+        assert_eq!(to_parameter(&brightness, 255), ParameterValue::Int(100));
+        assert_eq!(from_parameter(&brightness, ParameterValue::Int(150)), 127);
+    }
+
+    #[test]
+    fn feedback_goes_back_to_the_source() {
+        let param = BeacnParameter::LED(LEDParameter::Brightness);
+        let fader = Mapping { channel: 2, source: MidiSource::Control(7), parameter: param };
+        let feedback = fader.feedback(ParameterValue::Int(100));
+        assert_eq!(feedback, MidiMessage::ControlChange { channel: 2, control: 7, value: 127 });
+        assert!(fader.matches(&feedback));
+        assert!(!fader.matches(&MidiMessage::ControlChange { channel: 3, control: 7, value: 127 }));
+
+        let button = Mapping { channel: 0, source: MidiSource::Note(36), parameter: param };
+        assert!(button.matches(&MidiMessage::NoteOff { channel: 0, note: 36 }));
+    }
+}
@@ -0,0 +1,117 @@
+// The handful of channel messages we map to parameters. Channels are 0 to 15 here, but shown to
+// people as 1 to 16.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiMessage {
+    ControlChange { channel: u8, control: u8, value: u8 },
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    NoteOff { channel: u8, note: u8 },
+}
+
+impl MidiMessage {
+    pub fn encode(&self) -> [u8; 3] {
+        match *self {
+            MidiMessage::ControlChange { channel, control, value } => [0xb0 | channel, control, value],
+            MidiMessage::NoteOn { channel, note, velocity } => [0x90 | channel, note, velocity],
+            MidiMessage::NoteOff { channel, note } => [0x80 | channel, note, 0],
+        }
+    }
+}
+
+// Turns a raw MIDI byte stream into messages, handling running status and skipping anything we
+// don't use (SysEx, clock, program changes etc.)
+#[derive(Default)]
+pub struct MidiParser {
+    status: Option<u8>,
+    data: Vec<u8>,
+}
+
+impl MidiParser {
+    pub fn push(&mut self, byte: u8) -> Option<MidiMessage> {
+        match byte {
+            // Real time messages can appear anywhere, even mid message, and don't affect anything
+            0xf8..=0xff => return None,
+
+            // SysEx and system common messages cancel running status, and their data is ignored
+            0xf0..=0xf7 => {
+                self.status = None;
+                return None;
+            }
+            0x80..=0xef => {
+                self.status = Some(byte);
+                self.data.clear();
+                return None;
+            }
+            _ => {}
+        }
+
+        let status = self.status?;
+        self.data.push(byte);
+
+        // Program change and channel pressure only have one data byte
+        let length = if matches!(status & 0xf0, 0xc0 | 0xd0) { 1 } else { 2 };
+        if self.data.len() < length {
+            return None;
+        }
+
+        // The status is kept, as the next message may reuse it (running status)
+        let data = std::mem::take(&mut self.data);
+        let channel = status & 0x0f;
+        match status & 0xf0 {
+            0xb0 => Some(MidiMessage::ControlChange { channel, control: data[0], value: data[1] }),
+            0x90 if data[1] > 0 => Some(MidiMessage::NoteOn { channel, note: data[0], velocity: data[1] }),
+            0x90 | 0x80 => Some(MidiMessage::NoteOff { channel, note: data[0] }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Vec<MidiMessage> {
+        let mut parser = MidiParser::default();
+        bytes.iter().filter_map(|byte| parser.push(*byte)).collect()
+    }
+
+    #[test]
+    fn parses_channel_messages() {
+        assert_eq!(parse(&[0xb3, 7, 100]), vec![MidiMessage::ControlChange { channel: 3, control: 7, value: 100 }]);
+        assert_eq!(parse(&[0x90, 60, 64]), vec![MidiMessage::NoteOn { channel: 0, note: 60, velocity: 64 }]);
+        assert_eq!(parse(&[0x8f, 60, 10]), vec![MidiMessage::NoteOff { channel: 15, note: 60 }]);
+    }
+
+    #[test]
+    fn note_on_without_velocity_is_note_off() {
+        assert_eq!(parse(&[0x91, 60, 0]), vec![MidiMessage::NoteOff { channel: 1, note: 60 }]);
+    }
+
+    #[test]
+    fn follows_running_status() {
+        let messages = parse(&[0xb0, 7, 1, 7, 2, 8, 3]);
+        let values: Vec<_> = messages.iter().map(|message| match message {
+            MidiMessage::ControlChange { control, value, .. } => (*control, *value),
+            _ => panic!("Unexpected {:?}", message),
+        }).collect();
+        assert_eq!(values, vec![(7, 1), (7, 2), (8, 3)]);
+    }
+
+    #[test]
+    fn skips_what_isnt_used() {
+        // Clock in the middle of a message, a program change (one data byte), and SysEx
+        assert_eq!(parse(&[0xb0, 7, 0xf8, 5]), vec![MidiMessage::ControlChange { channel: 0, control: 7, value: 5 }]);
+        assert_eq!(parse(&[0xc0, 5, 0xb0, 1, 2]), vec![MidiMessage::ControlChange { channel: 0, control: 1, value: 2 }]);
+        assert_eq!(parse(&[0xf0, 0x7e, 1, 2, 0xf7, 3, 4]), vec![]);
+    }
+
+    #[test]
+    fn encodes_what_it_parses() {
+        for message in [
+            MidiMessage::ControlChange { channel: 9, control: 74, value: 127 },
+            MidiMessage::NoteOn { channel: 2, note: 36, velocity: 1 },
+            MidiMessage::NoteOff { channel: 0, note: 127 },
+        ] {
+            assert_eq!(parse(&message.encode()), vec![message]);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// Maps MIDI controls to parameters. We talk to the ALSA raw MIDI devices (/dev/snd/midiC*D*)
+// directly, these include virtual ports created by the snd-virmidi module, which can be connected
+// to software with aconnect.
+//
+// Control changes are scaled into the parameter's range. Notes toggle booleans, step through enums,
+// and otherwise use the velocity. Changes are sent back to the controller, for motorised faders
+// and button lights.
+
+mod mapping;
+mod message;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info, warn};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::BeacnParameter;
+use beacn_lib::messages::mic::MicParameter;
+use beacn_lib::messages::schema::{ParameterValue, ValueKind};
+use crate::config::MidiConfig;
+
+pub use mapping::{Mapping, MidiSource};
+use message::{MidiMessage, MidiParser};
+
+#[derive(Default)]
+struct MidiState {
+    mappings: Vec<Mapping>,
+
+    // When set, the next control or note is mapped to this, rather than doing anything
+    learning: Option<BeacnParameter>,
+    device: Option<String>,
+}
+
+// Shared between the service and the UI, so mappings can be learned and removed from there
+#[derive(Clone, Default)]
+pub struct MidiHandle {
+    state: Arc<Mutex<MidiState>>,
+}
+
+impl MidiHandle {
+    fn new() -> Self {
+        let mappings = Mapping::load_all().unwrap_or_else(|e| {
+            warn!("Unable to load MIDI mappings: {:#}", e);
+            vec![]
+        });
+
+        let handle = Self::default();
+        handle.lock().mappings = mappings;
+        handle
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MidiState> {
+        self.state.lock().expect("MIDI state lock poisoned")
+    }
+
+    pub fn mappings(&self) -> Vec<Mapping> {
+        self.lock().mappings.clone()
+    }
+
+    pub fn device(&self) -> Option<String> {
+        self.lock().device.clone()
+    }
+
+    pub fn learning(&self) -> Option<BeacnParameter> {
+        self.lock().learning
+    }
+
+    pub fn learn(&self, parameter: Option<BeacnParameter>) {
+        self.lock().learning = parameter;
+    }
+
+    pub fn remove(&self, mapping: &Mapping) -> Result<()> {
+        let mut state = self.lock();
+        state.mappings.retain(|existing| existing != mapping);
+        Mapping::save_all(&state.mappings)
+    }
+}
+
+pub fn start(client: BeacnClient, config: MidiConfig) -> MidiHandle {
+    let handle = MidiHandle::new();
+    super::spawn("MIDI", run(client, config, handle.clone()));
+    handle
+}
+
+async fn run(client: BeacnClient, config: MidiConfig, handle: MidiHandle) -> Result<()> {
+    let path = match config.device {
+        Some(path) => path,
+        None => find_device()?,
+    };
+    let input = OpenOptions::new().read(true).write(true).open(&path).with_context(|| format!("Unable to open {}", path))?;
+    let mut output = input.try_clone()?;
+    info!("Using MIDI device {}", path);
+    handle.lock().device = Some(path);
+
+    // Reads block, so they get a thread of their own
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    thread::spawn(move || read_messages(input, sender));
+
+    // A fader which has just been moved shouldn't be sent its own value back, or it'll fight
+    let mut last_control = None;
+
+    let mut events = client.subscribe();
+    loop {
+        select! {
+            message = receiver.recv() => {
+                let message = message.ok_or_else(|| anyhow!("MIDI device was closed"))?;
+                match handle_message(&client, &handle, message).await {
+                    Ok(Some(mapping)) => last_control = Some(mapping),
+                    Ok(None) => {}
+                    Err(e) => warn!("Unable to handle MIDI message {:?}: {:#}", message, e),
+                }
+            }
+            event = events.recv() => {
+                let (param, value) = match event {
+                    Ok(DeviceEvent::ParameterChanged(param, value)) => (param, param.schema().decode(value)),
+                    Ok(DeviceEvent::MuteChanged(muted)) => (BeacnParameter::Mic(MicParameter::Mute), ParameterValue::Bool(muted)),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                let source = last_control.take_if(|mapping: &mut Mapping| mapping.parameter == param);
+                // A controller which won't take feedback can still be used for input
+                for mapping in handle.mappings().iter().filter(|mapping| mapping.parameter == param && Some(**mapping) != source) {
+                    if let Err(e) = output.write_all(&mapping.feedback(value).encode()) {
+                        warn!("Unable to send {} to the MIDI device: {}", mapping, e);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_device() -> Result<String> {
+    let mut devices: Vec<String> = fs::read_dir("/dev/snd").into_iter().flatten().filter_map(|entry| {
+        let path = entry.ok()?.path();
+        let name = path.file_name()?.to_str()?;
+        name.starts_with("midiC").then(|| path.to_string_lossy().to_string())
+    }).collect();
+    devices.sort();
+
+    devices.into_iter().next().ok_or_else(|| anyhow!("No MIDI devices found in /dev/snd (virtual ports can be created with 'modprobe snd-virmidi')"))
+}
+
+fn read_messages(mut input: File, sender: mpsc::UnboundedSender<MidiMessage>) {
+    let mut parser = MidiParser::default();
+    let mut buffer = [0; 256];
+    loop {
+        let length = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(length) => length,
+            Err(e) => {
+                warn!("Unable to read from MIDI device: {}", e);
+                break;
+            }
+        };
+
+        for message in buffer[..length].iter().filter_map(|byte| parser.push(*byte)) {
+            if sender.send(message).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// Returns the mapping if a control change was applied, so it can be left out of the feedback
+async fn handle_message(client: &BeacnClient, handle: &MidiHandle, message: MidiMessage) -> Result<Option<Mapping>> {
+    if let Some(parameter) = handle.learning() {
+        let (channel, source) = match message {
+            MidiMessage::ControlChange { channel, control, .. } => (channel, MidiSource::Control(control)),
+            MidiMessage::NoteOn { channel, note, .. } => (channel, MidiSource::Note(note)),
+            MidiMessage::NoteOff { .. } => return Ok(None),
+        };
+        let mapping = Mapping { channel, source, parameter };
+
+        // Each control only does one thing, so replace whatever it did before
+        let mut state = handle.lock();
+        state.learning = None;
+        state.mappings.retain(|existing| existing.channel != channel || existing.source != source);
+        state.mappings.push(mapping);
+        Mapping::save_all(&state.mappings)?;
+
+        info!("Mapped {} to {}", mapping, parameter.schema().name);
+        return Ok(None);
+    }
+
+    let mut applied = None;
+    for mapping in handle.mappings().into_iter().filter(|mapping| mapping.matches(&message)) {
+        let schema = mapping.parameter.schema();
+        let value = match message {
+            MidiMessage::ControlChange { value, .. } => {
+                applied = Some(mapping);
+                mapping::to_parameter(&schema, value)
+            }
+            MidiMessage::NoteOn { velocity, .. } => match (schema.kind, client.get_value(mapping.parameter).await?) {
+                (ValueKind::Bool, ParameterValue::Bool(value)) => ParameterValue::Bool(!value),
+                (ValueKind::Enum(options), ParameterValue::UInt(value)) => {
+                    let index = options.iter().position(|(option, _)| *option == value).map_or(0, |index| index + 1);
+                    ParameterValue::UInt(options[index % options.len()].0)
+                }
+                _ => mapping::to_parameter(&schema, velocity),
+            },
+            MidiMessage::NoteOff { .. } => continue,
+        };
+
+        debug!("MIDI {} setting {} to {}", mapping, schema.name, schema.format(value));
+        if let Err(e) = client.set_value(mapping.parameter, value).await {
+            bail!("Unable to set {}: {:#}", schema.name, e);
+        }
+    }
+    Ok(applied)
+}
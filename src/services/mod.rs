@@ -0,0 +1,90 @@
+mod apps;
+pub mod animation;
+mod dbus;
+mod hooks;
+mod hotkeys;
+mod http;
+mod json;
+pub mod midi;
+mod mqtt;
+mod obs;
+mod openrgb;
+mod osc;
+mod privacy;
+pub mod schedule;
+mod scripts;
+mod status;
+
+use std::future::Future;
+use anyhow::Result;
+use log::warn;
+use beacn_lib::client::BeacnClient;
+use crate::config::Config;
+
+// Handles to the running services which the UI can interact with
+#[derive(Default)]
+pub struct Services {
+    pub midi: Option<midi::MidiHandle>,
+    pub schedule: Option<schedule::ScheduleHandle>,
+}
+
+// Starts every enabled service on the current runtime, they keep running until the process exits.
+// A service failing is logged, but doesn't affect the others.
+pub fn start(client: &BeacnClient, config: &Config) -> Services {
+    let mut services = Services::default();
+
+    // Nothing plays until it's asked to, so this is always available to the other services
+    let animations = animation::start(client.clone());
+
+    if config.dbus.enabled {
+        spawn("D-Bus", dbus::run(client.clone(), animations.clone()));
+    }
+    if config.http.enabled {
+        spawn("HTTP", http::run(client.clone(), animations.clone(), config.http.clone()));
+    }
+    if config.osc.enabled {
+        spawn("OSC", osc::run(client.clone(), config.osc.clone()));
+    }
+    if config.midi.enabled {
+        services.midi = Some(midi::start(client.clone(), config.midi.clone()));
+    }
+    if config.openrgb.enabled {
+        spawn("OpenRGB", openrgb::run(client.clone(), config.openrgb.clone()));
+    }
+    if config.mqtt.enabled {
+        spawn("MQTT", mqtt::run(client.clone(), config.mqtt.clone()));
+    }
+    if config.obs.enabled {
+        spawn("OBS", obs::run(animations.clone(), config.obs.clone()));
+    }
+    if config.scripts.enabled {
+        spawn("Scripts", scripts::run(client.clone(), animations.clone()));
+    }
+    if config.schedule.enabled {
+        services.schedule = Some(schedule::start(client.clone(), animations.clone()));
+    }
+    if config.apps.enabled {
+        spawn("Apps", apps::run(client.clone(), animations.clone(), config.apps.clone()));
+    }
+    if config.hooks.enabled {
+        spawn("Hooks", hooks::run(client.clone(), config.hooks.clone()));
+    }
+    if config.hotkeys.enabled {
+        spawn("Hotkeys", hotkeys::run(client.clone(), config.hotkeys.clone()));
+    }
+    if config.privacy.enabled {
+        spawn("Privacy", privacy::run(client.clone(), animations.clone(), config.privacy.clone()));
+    }
+    if config.status.enabled {
+        spawn("Status", status::run(animations.clone(), config.status.clone()));
+    }
+    services
+}
+
+fn spawn(name: &'static str, service: impl Future<Output = Result<()>> + Send + 'static) {
+    tokio::spawn(async move {
+        if let Err(e) = service.await {
+            warn!("{} service stopped: {:#}", name, e);
+        }
+    });
+}
@@ -0,0 +1,521 @@
+// Bridges the Mic to an MQTT broker, announcing it to Home Assistant through MQTT discovery. It
+// turns up as a device with:
+//
+//   A light for the ring    Colour is the primary colour, and the effect is the LED mode
+//   A switch for mute
+//   A sensor for the level  The microphone's peak in dBFS, every meter_interval seconds
+//
+// Our topics are under <topic>/<id> (eg. beacn/ABC123/ring/set), where the id comes from the serial.
+
+use std::time::Duration;
+use anyhow::Result;
+use log::{debug, info, warn};
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::time::{self, MissedTickBehavior};
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::{ParameterValue, ValueKind};
+use beacn_lib::messages::{BeacnParameter, RGB};
+use beacn_lib::meter::METER_FLOOR_DB;
+use beacn_lib::state::DeviceState;
+use crate::config::MqttConfig;
+
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+// rumqttc reconnects on the next poll after an error, this stops it spinning while the broker is down
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Home Assistant announces itself here when it starts, and needs telling about us again
+const HOME_ASSISTANT_STATUS: &str = "homeassistant/status";
+
+enum Incoming {
+    Connected,
+    Message(String, Vec<u8>),
+}
+
+// The JSON schema for MQTT lights, every field is optional
+#[derive(Deserialize)]
+struct LightCommand {
+    state: Option<String>,
+    brightness: Option<i32>,
+    color: Option<Colour>,
+    effect: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Colour {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+pub async fn run(client: BeacnClient, config: MqttConfig) -> Result<()> {
+    let id = node_id(client.info().serial.as_deref());
+    let base = format!("{}/{}", config.topic, id);
+
+    let mut options = MqttOptions::new(format!("beacn-mic-{}", id), &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(format!("{}/status", base), OFFLINE, QoS::AtLeastOnce, true));
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.clone().unwrap_or_default());
+    }
+    let (mqtt, mut connection) = AsyncClient::new(options, 64);
+
+    // The connection only does anything while it's being polled, including sending what we publish,
+    // so it gets a task of its own and we're told about anything interesting.
+    let (sender, mut incoming) = mpsc::unbounded_channel();
+    let address = format!("{}:{}", config.host, config.port);
+    tokio::spawn(async move {
+        loop {
+            let message = match connection.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => Incoming::Connected,
+                Ok(Event::Incoming(Packet::Publish(publish))) => Incoming::Message(publish.topic, publish.payload.to_vec()),
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("MQTT connection to {} failed: {}", address, e);
+                    time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut events = client.subscribe();
+    let state = client.fetch_state().await?;
+    let mut bridge = Bridge { client, mqtt, config, id, base, state, last_brightness: None };
+
+    // Only subscribe to the meters if they're wanted, so the device isn't polled for nothing
+    let mut meters = (bridge.config.meter_interval > 0).then(|| bridge.client.subscribe_meters());
+    let mut publish_level = time::interval(Duration::from_secs(bridge.config.meter_interval.max(1)));
+    publish_level.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut peak = METER_FLOOR_DB;
+
+    loop {
+        select! {
+            message = incoming.recv() => {
+                let Some(message) = message else {
+                    break;
+                };
+                match message {
+                    Incoming::Connected => {
+                        info!("Connected to MQTT broker {}:{}", bridge.config.host, bridge.config.port);
+                        bridge.announce();
+                    }
+                    Incoming::Message(topic, payload) => {
+                        if let Err(e) = bridge.handle(&topic, &payload).await {
+                            warn!("Unable to handle MQTT message on {}: {:#}", topic, e);
+                        }
+                    }
+                }
+            }
+            event = events.recv() => {
+                match event {
+                    Ok(DeviceEvent::ParameterChanged(param, value)) => {
+                        bridge.state.set_param(param, value);
+                        if let BeacnParameter::LED(_) = param {
+                            bridge.publish_light();
+                        }
+                    }
+                    Ok(DeviceEvent::MuteChanged(muted)) => {
+                        bridge.state.mic.muted = muted;
+                        bridge.publish_mute();
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            levels = async { Some(meters.as_mut()?.recv().await) }, if meters.is_some() => {
+                match levels {
+                    Some(Ok(levels)) => peak = peak.max(levels.mic.peak),
+                    Some(Err(RecvError::Closed)) => meters = None,
+                    _ => {}
+                }
+            }
+            _ = publish_level.tick(), if meters.is_some() => {
+                bridge.publish(&format!("{}/level/state", bridge.base), format!("{:.1}", peak), false);
+                peak = METER_FLOOR_DB;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Topics and entity ids can't contain just anything, so the serial is cleaned up a bit
+fn node_id(serial: Option<&str>) -> String {
+    let serial = serial.unwrap_or_default();
+    let id: String = serial.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    if id.is_empty() {
+        String::from("beacn_mic")
+    } else {
+        id.to_ascii_lowercase()
+    }
+}
+
+fn effects() -> Vec<&'static str> {
+    match LEDParameter::Mode.schema().kind {
+        ValueKind::Enum(options) => options.iter().map(|(_, name)| *name).collect(),
+        _ => vec![],
+    }
+}
+
+struct Bridge {
+    client: BeacnClient,
+    mqtt: AsyncClient,
+    config: MqttConfig,
+    id: String,
+    base: String,
+    state: DeviceState,
+
+    // Turning the light off sets the brightness to 0, this is what turning it back on restores
+    last_brightness: Option<i32>,
+}
+
+impl Bridge {
+    // Nothing waits for the broker, so the device keeps being handled while it's away. Anything
+    // which doesn't fit in the queue is dropped, the state is sent again when we reconnect anyway.
+    fn publish(&self, topic: &str, payload: impl Into<Vec<u8>>, retain: bool) {
+        if let Err(e) = self.mqtt.try_publish(topic, QoS::AtLeastOnce, retain, payload) {
+            debug!("Unable to publish to {}: {}", topic, e);
+        }
+    }
+
+    // Sent on every (re)connect, as well as when Home Assistant restarts. A full queue isn't worth
+    // stopping for, it's all tried again on the next connect.
+    fn announce(&self) {
+        for topic in [format!("{}/+/set", self.base), String::from(HOME_ASSISTANT_STATUS)] {
+            if let Err(e) = self.mqtt.try_subscribe(&topic, QoS::AtLeastOnce) {
+                warn!("Unable to subscribe to {}: {}", topic, e);
+            }
+        }
+
+        let info = self.client.info();
+        let device = json!({
+            "identifiers": [format!("beacn_mic_{}", self.id)],
+            "name": "Beacn Mic",
+            "manufacturer": info.manufacturer.clone().unwrap_or_else(|| String::from("Beacn")),
+            "model": info.product.clone().unwrap_or_else(|| String::from("Beacn Mic")),
+            "sw_version": info.version,
+            "serial_number": info.serial,
+        });
+        let entity = |name: &str, key: &str| json!({
+            "name": name,
+            "unique_id": format!("beacn_mic_{}_{}", self.id, key),
+            "availability_topic": format!("{}/status", self.base),
+            "state_topic": format!("{}/{}/state", self.base, key),
+            "device": device,
+        });
+
+        let mut light = entity("Ring", "ring");
+        light["schema"] = json!("json");
+        light["command_topic"] = json!(format!("{}/ring/set", self.base));
+        light["brightness"] = json!(true);
+        light["brightness_scale"] = json!(100);
+        light["supported_color_modes"] = json!(["rgb"]);
+        light["effect"] = json!(true);
+        light["effect_list"] = json!(effects());
+        self.discover("light", "ring", Some(light));
+
+        let mut mute = entity("Mute", "mute");
+        mute["command_topic"] = json!(format!("{}/mute/set", self.base));
+        mute["icon"] = json!("mdi:microphone-off");
+        self.discover("switch", "mute", Some(mute));
+
+        // An empty config removes the sensor, in case it was turned off since last time
+        let level = (self.config.meter_interval > 0).then(|| {
+            let mut level = entity("Level", "level");
+            level["unit_of_measurement"] = json!("dBFS");
+            level["state_class"] = json!("measurement");
+            level["icon"] = json!("mdi:microphone");
+            level
+        });
+        self.discover("sensor", "level", level);
+
+        self.publish(&format!("{}/status", self.base), ONLINE, true);
+        self.publish_light();
+        self.publish_mute();
+    }
+
+    fn discover(&self, component: &str, key: &str, config: Option<Value>) {
+        let topic = format!("{}/{}/beacn_mic_{}/{}/config", self.config.discovery_prefix, component, self.id, key);
+        let payload = config.map(|config| config.to_string()).unwrap_or_default();
+        self.publish(&topic, payload, true);
+    }
+
+    fn publish_light(&self) {
+        let led = &self.state.led;
+        let state = json!({
+            "state": if led.brightness > 0 { "ON" } else { "OFF" },
+            "brightness": led.brightness,
+            "color_mode": "rgb",
+            "color": { "r": led.colour1.red, "g": led.colour1.green, "b": led.colour1.blue },
+            "effect": LEDParameter::Mode.schema().format(ParameterValue::UInt(led.mode)),
+        });
+        self.publish(&format!("{}/ring/state", self.base), state.to_string(), true);
+    }
+
+    fn publish_mute(&self) {
+        let state = if self.state.mic.muted { "ON" } else { "OFF" };
+        self.publish(&format!("{}/mute/state", self.base), state, true);
+    }
+
+    async fn handle(&mut self, topic: &str, payload: &[u8]) -> Result<()> {
+        let payload = String::from_utf8_lossy(payload);
+        debug!("MQTT {}: {}", topic, payload);
+
+        if topic == HOME_ASSISTANT_STATUS {
+            if payload == ONLINE {
+                self.announce();
+            }
+            return Ok(());
+        }
+
+        match topic.strip_prefix(&self.base) {
+            Some("/ring/set") => self.set_light(serde_json::from_str(&payload)?).await,
+            Some("/mute/set") => {
+                self.client.set_muted(payload.eq_ignore_ascii_case("ON")).await?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_light(&mut self, command: LightCommand) -> Result<()> {
+        let brightness = self.state.led.brightness;
+        let brightness = match (command.state.as_deref(), command.brightness) {
+            (Some("OFF"), _) => {
+                if brightness > 0 {
+                    self.last_brightness = Some(brightness);
+                }
+                Some(0)
+            }
+            (_, Some(brightness)) => Some(brightness.clamp(0, 100)),
+            (Some("ON"), None) if brightness == 0 => Some(self.last_brightness.unwrap_or(100)),
+            _ => None,
+        };
+
+        if let Some(effect) = command.effect {
+            let mode = LEDParameter::Mode.schema().parse(&effect)?;
+            self.client.set_value(BeacnParameter::LED(LEDParameter::Mode), mode).await?;
+        }
+        if let Some(colour) = command.color {
+            let colour = ParameterValue::Colour(RGB::new(colour.r, colour.g, colour.b));
+            self.client.set_value(BeacnParameter::LED(LEDParameter::Colour1), colour).await?;
+        }
+        if let Some(brightness) = brightness {
+            self.client.set_value(BeacnParameter::LED(LEDParameter::Brightness), ParameterValue::Int(brightness)).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use rumqttc::{ConnAck, ConnectReturnCode, PubAck, Publish, SubAck, SubscribeReasonCode};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use super::*;
+
+    const MAX_PACKET: usize = 1 << 20;
+
+    // Just enough of a broker for one client, which acknowledges everything and keeps what it's told
+    struct Broker {
+        stream: TcpStream,
+        buffer: BytesMut,
+        subscriptions: Vec<String>,
+    }
+
+    impl Broker {
+        // Starts the bridge against a broker of our own, returning it once the bridge has connected
+        async fn start(config: MqttConfig) -> (Broker, BeacnClient) {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let config = MqttConfig { host: String::from("127.0.0.1"), port: listener.local_addr().unwrap().port(), ..config };
+            let client = BeacnClient::connect_simulated();
+            tokio::spawn(run(client.clone(), config));
+
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut broker = Broker { stream, buffer: BytesMut::new(), subscriptions: vec![] };
+            let Packet::Connect(connect) = broker.read().await else {
+                panic!("Expected a connect");
+            };
+            let will = connect.last_will.unwrap();
+            assert_eq!((will.topic.as_str(), will.message.as_ref(), will.retain), ("beacn/simulated/status", OFFLINE.as_bytes(), true));
+            broker.write(Packet::ConnAck(ConnAck::new(ConnectReturnCode::Success, false))).await;
+            (broker, client)
+        }
+
+        async fn read(&mut self) -> Packet {
+            loop {
+                match Packet::read(&mut self.buffer, MAX_PACKET) {
+                    Ok(packet) => return packet,
+                    Err(rumqttc::Error::InsufficientBytes(_)) => {}
+                    Err(e) => panic!("Bad packet: {:?}", e),
+                }
+                let read = time::timeout(Duration::from_secs(5), self.stream.read_buf(&mut self.buffer)).await;
+                assert!(read.expect("Nothing from the bridge").unwrap() > 0, "The bridge disconnected");
+            }
+        }
+
+        async fn write(&mut self, packet: Packet) {
+            let mut buffer = BytesMut::new();
+            packet.write(&mut buffer, MAX_PACKET).unwrap();
+            self.stream.write_all(&buffer).await.unwrap();
+        }
+
+        // Skips everything else until something is published to the topic
+        async fn published(&mut self, topic: &str) -> Publish {
+            loop {
+                match self.read().await {
+                    Packet::Publish(publish) => {
+                        self.write(Packet::PubAck(PubAck::new(publish.pkid))).await;
+                        if publish.topic == topic {
+                            return publish;
+                        }
+                    }
+                    Packet::Subscribe(subscribe) => {
+                        let codes = subscribe.filters.iter().map(|filter| SubscribeReasonCode::Success(filter.qos)).collect();
+                        self.subscriptions.extend(subscribe.filters.into_iter().map(|filter| filter.path));
+                        self.write(Packet::SubAck(SubAck::new(subscribe.pkid, codes))).await;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        async fn json(&mut self, topic: &str) -> Value {
+            serde_json::from_slice(&self.published(topic).await.payload).unwrap()
+        }
+
+        async fn send(&mut self, topic: &str, payload: &str) {
+            self.write(Packet::Publish(Publish::new(topic, QoS::AtMostOnce, payload))).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn announces_itself_to_home_assistant() {
+        let (mut broker, _) = Broker::start(MqttConfig { meter_interval: 0, ..Default::default() }).await;
+
+        let light = broker.published("homeassistant/light/beacn_mic_simulated/ring/config").await;
+        assert!(light.retain);
+        let light: Value = serde_json::from_slice(&light.payload).unwrap();
+        assert_eq!(light["command_topic"], "beacn/simulated/ring/set");
+        assert_eq!(light["state_topic"], "beacn/simulated/ring/state");
+        assert_eq!(light["availability_topic"], "beacn/simulated/status");
+        assert_eq!(light["device"]["serial_number"], "SIMULATED");
+        assert!(light["effect_list"].as_array().unwrap().contains(&json!("solid")));
+
+        let mute = broker.json("homeassistant/switch/beacn_mic_simulated/mute/config").await;
+        assert_eq!(mute["command_topic"], "beacn/simulated/mute/set");
+
+        // The level is turned off, so its sensor is removed
+        assert!(broker.published("homeassistant/sensor/beacn_mic_simulated/level/config").await.payload.is_empty());
+
+        assert_eq!(broker.published("beacn/simulated/status").await.payload.as_ref(), ONLINE.as_bytes());
+        assert_eq!(broker.json("beacn/simulated/ring/state").await["state"], "ON");
+        assert_eq!(broker.published("beacn/simulated/mute/state").await.payload.as_ref(), b"OFF");
+        assert_eq!(broker.subscriptions, ["beacn/simulated/+/set", HOME_ASSISTANT_STATUS]);
+
+        // And again when Home Assistant restarts
+        broker.send(HOME_ASSISTANT_STATUS, ONLINE).await;
+        broker.published("homeassistant/light/beacn_mic_simulated/ring/config").await;
+    }
+
+    #[tokio::test]
+    async fn follows_commands_and_publishes_the_state() {
+        let (mut broker, client) = Broker::start(MqttConfig { meter_interval: 0, ..Default::default() }).await;
+        broker.published("beacn/simulated/mute/state").await;
+
+        broker.send("beacn/simulated/ring/set", r#"{"brightness": 40, "color": {"r": 255, "g": 0, "b": 0}}"#).await;
+        let mut state = broker.json("beacn/simulated/ring/state").await;
+        while state["brightness"] != 40 {
+            state = broker.json("beacn/simulated/ring/state").await;
+        }
+        assert_eq!(state["color"], json!({ "r": 255, "g": 0, "b": 0 }));
+        assert_eq!(client.get_value(BeacnParameter::LED(LEDParameter::Brightness)).await.unwrap(), ParameterValue::Int(40));
+
+        broker.send("beacn/simulated/mute/set", "ON").await;
+        assert_eq!(broker.published("beacn/simulated/mute/state").await.payload.as_ref(), b"ON");
+        assert!(client.get_muted().await.unwrap());
+
+        // Changes from elsewhere are published too
+        client.set_muted(false).await.unwrap();
+        assert_eq!(broker.published("beacn/simulated/mute/state").await.payload.as_ref(), b"OFF");
+    }
+
+    #[tokio::test]
+    async fn publishes_the_level() {
+        let (mut broker, _) = Broker::start(MqttConfig { meter_interval: 1, ..Default::default() }).await;
+        let sensor = broker.json("homeassistant/sensor/beacn_mic_simulated/level/config").await;
+        assert_eq!(sensor["unit_of_measurement"], "dBFS");
+        let level = broker.published("beacn/simulated/level/state").await;
+        assert!(String::from_utf8_lossy(&level.payload).parse::<f32>().is_ok());
+    }
+
+    // The broker is never polled, so nothing is sent anywhere
+    async fn bridge() -> Bridge {
+        let client = BeacnClient::connect_simulated();
+        let state = client.fetch_state().await.unwrap();
+        let (mqtt, _) = AsyncClient::new(MqttOptions::new("test", "localhost", 1883), 10);
+        let config = MqttConfig::default();
+        let base = format!("{}/test", config.topic);
+        Bridge { client, mqtt, config, id: String::from("test"), base, state, last_brightness: None }
+    }
+
+    // What the run loop does with the change events
+    async fn command(bridge: &mut Bridge, json: &str) -> i32 {
+        bridge.set_light(serde_json::from_str(json).unwrap()).await.unwrap();
+        bridge.state = bridge.client.fetch_state().await.unwrap();
+        bridge.state.led.brightness
+    }
+
+    #[tokio::test]
+    async fn off_and_on_restores_the_brightness() {
+        let mut bridge = bridge().await;
+        assert_eq!(command(&mut bridge, r#"{"brightness": 40}"#).await, 40);
+        assert_eq!(command(&mut bridge, r#"{"state": "OFF"}"#).await, 0);
+        assert_eq!(bridge.last_brightness, Some(40));
+
+        // Turning it off again doesn't forget what it was
+        assert_eq!(command(&mut bridge, r#"{"state": "OFF"}"#).await, 0);
+        assert_eq!(command(&mut bridge, r#"{"state": "ON"}"#).await, 40);
+    }
+
+    #[tokio::test]
+    async fn on_without_a_brightness_leaves_it_alone() {
+        let mut bridge = bridge().await;
+        assert_eq!(command(&mut bridge, r#"{"brightness": 30}"#).await, 30);
+        assert_eq!(command(&mut bridge, r#"{"state": "ON"}"#).await, 30);
+
+        // Nothing to go back to, so it's turned up all the way
+        assert_eq!(command(&mut bridge, r#"{"brightness": 0}"#).await, 0);
+        assert_eq!(command(&mut bridge, r#"{"state": "ON"}"#).await, 100);
+    }
+
+    #[tokio::test]
+    async fn sets_colour_effect_and_clamps_brightness() {
+        let mut bridge = bridge().await;
+        let json = r#"{"state": "ON", "brightness": 250, "color": {"r": 255, "g": 16, "b": 0}, "effect": "solid"}"#;
+        assert_eq!(command(&mut bridge, json).await, 100);
+        assert_eq!(bridge.state.led.colour1, RGB::new(255, 16, 0));
+        assert_eq!(bridge.state.led.mode, 0);
+        assert!(bridge.set_light(serde_json::from_str(r#"{"effect": "nonsense"}"#).unwrap()).await.is_err());
+    }
+
+    #[test]
+    fn node_ids_are_safe_for_topics() {
+        assert_eq!(node_id(Some("BM-00A1/2b 3")), "bm00a12b3");
+        assert_eq!(node_id(Some("-/ #+")), "beacn_mic");
+        assert_eq!(node_id(None), "beacn_mic");
+    }
+}
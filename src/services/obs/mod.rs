@@ -0,0 +1,301 @@
+// Follows OBS over obs-websocket (v5, built into OBS 28 onwards), changing the ring while streaming,
+// recording or on particular scenes, and putting it back afterwards. The changes are configured
+// as overlays in config.toml, eg.
+//
+//   [obs.streaming]
+//   mode = "solid"
+//   colour1 = "#ff0000"
+//
+//   [obs.scenes."Be Right Back"]
+//   brightness = 10
+
+mod overlay;
+
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use base64::prelude::*;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+use tokio::time;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use crate::config::ObsConfig;
+use crate::services::animation::AnimationHandle;
+use crate::services::obs::overlay::Overlays;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// OBS is often not running, so we just keep trying quietly
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const RPC_VERSION: u64 = 1;
+
+const OP_HELLO: u64 = 0;
+const OP_IDENTIFY: u64 = 1;
+const OP_IDENTIFIED: u64 = 2;
+const OP_EVENT: u64 = 5;
+const OP_REQUEST: u64 = 6;
+const OP_REQUEST_RESPONSE: u64 = 7;
+
+const EVENTS_SCENES: u64 = 1 << 2;
+const EVENTS_OUTPUTS: u64 = 1 << 6;
+
+pub async fn run(animations: AnimationHandle, config: ObsConfig) -> Result<()> {
+    let mut overlays = Overlays::new(animations, &config)?;
+
+    loop {
+        match connect_async(&config.address).await {
+            Ok((socket, _)) => {
+                if let Err(e) = session(socket, &config, &mut overlays).await {
+                    warn!("Lost connection to OBS: {:#}", e);
+                }
+                if let Err(e) = overlays.clear().await {
+                    warn!("Unable to restore the ring after OBS: {:#}", e);
+                }
+            }
+            Err(e) => debug!("Unable to connect to OBS at {}: {}", config.address, e),
+        }
+        time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn session(mut socket: Socket, config: &ObsConfig, overlays: &mut Overlays) -> Result<()> {
+    let hello = receive(&mut socket).await?;
+    if hello["op"] != OP_HELLO {
+        bail!("Expected Hello from OBS, got {}", hello);
+    }
+
+    let mut identify = json!({ "rpcVersion": RPC_VERSION, "eventSubscriptions": EVENTS_SCENES | EVENTS_OUTPUTS });
+    if let Some(auth) = hello["d"].get("authentication") {
+        let password = config.password.as_deref().ok_or_else(|| anyhow!("OBS needs a password, set obs.password"))?;
+        let salt = auth["salt"].as_str().unwrap_or_default();
+        let challenge = auth["challenge"].as_str().unwrap_or_default();
+        identify["authentication"] = json!(authenticate(password, salt, challenge));
+    }
+    send(&mut socket, OP_IDENTIFY, identify).await?;
+
+    // A wrong password gets the connection closed, which receive() reports
+    let identified = receive(&mut socket).await?;
+    if identified["op"] != OP_IDENTIFIED {
+        bail!("Expected Identified from OBS, got {}", identified);
+    }
+    info!("Connected to OBS {}", hello["d"]["obsWebSocketVersion"].as_str().unwrap_or("(unknown version)"));
+
+    // Events only tell us about changes, so start by asking what's happening now
+    for request in ["GetStreamStatus", "GetRecordStatus", "GetCurrentProgramScene"] {
+        send(&mut socket, OP_REQUEST, json!({ "requestType": request, "requestId": request })).await?;
+    }
+
+    loop {
+        let message = receive(&mut socket).await?;
+        let data = &message["d"];
+        let (kind, data) = match message["op"].as_u64() {
+            Some(OP_EVENT) => (&data["eventType"], &data["eventData"]),
+            Some(OP_REQUEST_RESPONSE) if data["requestStatus"]["result"] == true => (&data["requestType"], &data["responseData"]),
+            Some(OP_REQUEST_RESPONSE) => {
+                warn!("OBS request {} failed: {}", data["requestType"], data["requestStatus"]);
+                continue;
+            }
+            _ => continue,
+        };
+
+        // The responses to our requests look enough like the events to be handled together
+        let kind = kind.as_str().unwrap_or_default();
+        let active = data["outputActive"].as_bool().unwrap_or_default();
+        match kind {
+            "StreamStateChanged" | "GetStreamStatus" => overlays.is_streaming = active,
+            "RecordStateChanged" | "GetRecordStatus" => overlays.is_recording = active,
+            "CurrentProgramSceneChanged" | "GetCurrentProgramScene" => {
+                // Older versions only have currentProgramSceneName in the response
+                let scene = data.get("sceneName").or_else(|| data.get("currentProgramSceneName"));
+                overlays.scene = scene.and_then(Value::as_str).map(String::from);
+            }
+            _ => continue,
+        }
+        debug!("OBS {}: {}", kind, data);
+
+        if let Err(e) = overlays.update().await {
+            warn!("Unable to update the ring for OBS: {:#}", e);
+        }
+    }
+}
+
+// See 'Creating an authentication string' in the obs-websocket protocol docs
+fn authenticate(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = BASE64_STANDARD.encode(Sha256::digest(format!("{}{}", password, salt)));
+    BASE64_STANDARD.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+async fn send(socket: &mut Socket, op: u64, data: Value) -> Result<()> {
+    let message = json!({ "op": op, "d": data });
+    socket.send(Message::Text(message.to_string().into())).await?;
+    Ok(())
+}
+
+async fn receive(socket: &mut Socket) -> Result<Value> {
+    loop {
+        match socket.next().await.ok_or_else(|| anyhow!("Connection closed"))?? {
+            Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+            Message::Close(Some(frame)) => bail!("Connection closed ({}: {})", frame.code, frame.reason),
+            Message::Close(None) => bail!("Connection closed"),
+            _ => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use beacn_lib::client::BeacnClient;
+    use beacn_lib::messages::BeacnParameter;
+    use beacn_lib::messages::led::LEDParameter;
+    use beacn_lib::messages::schema::ParameterValue;
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+    use tokio_tungstenite::accept_async;
+    use crate::config::LightingOverlay;
+    use crate::services::animation;
+    use super::*;
+
+    const PASSWORD: &str = "supersecretpassword";
+    const SALT: &str = "lM1GncleQOaCu9lT1yeUZhFYnqhsLLP1G5lAGo3ixaI=";
+    const CHALLENGE: &str = "+IxH4CnCiqpX1rM9scsNynZzbOe4KhDeYcTNS3PDaeY=";
+
+    // Our end of a session with the stand-in OBS, which gives the overlays back when it's over
+    struct Ours {
+        client: BeacnClient,
+        session: JoinHandle<(Result<()>, Overlays)>,
+    }
+
+    fn brightness(value: i32) -> LightingOverlay {
+        LightingOverlay { brightness: Some(value), ..Default::default() }
+    }
+
+    async fn connect(password: Option<&str>) -> (WebSocketStream<TcpStream>, Ours) {
+        let mut config = ObsConfig {
+            password: password.map(String::from),
+            streaming: Some(brightness(90)),
+            recording: Some(brightness(50)),
+            ..Default::default()
+        };
+        config.scenes.insert(String::from("Starting Soon"), brightness(20));
+
+        let client = BeacnClient::connect_simulated();
+        let mut overlays = Overlays::new(animation::start(client.clone()), &config).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("ws://{}", listener.local_addr().unwrap());
+        let session = tokio::spawn(async move {
+            let (socket, _) = connect_async(address).await.unwrap();
+            (session(socket, &config, &mut overlays).await, overlays)
+        });
+        let (stream, _) = listener.accept().await.unwrap();
+        (accept_async(stream).await.unwrap(), Ours { client, session })
+    }
+
+    async fn send(socket: &mut WebSocketStream<TcpStream>, op: u64, data: Value) {
+        let message = json!({ "op": op, "d": data });
+        socket.send(Message::Text(message.to_string().into())).await.unwrap();
+    }
+
+    async fn receive(socket: &mut WebSocketStream<TcpStream>) -> Value {
+        match socket.next().await.unwrap().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            message => panic!("Unexpected message {:?}", message),
+        }
+    }
+
+    async fn event(socket: &mut WebSocketStream<TcpStream>, kind: &str, data: Value) {
+        send(socket, OP_EVENT, json!({ "eventType": kind, "eventIntent": EVENTS_OUTPUTS, "eventData": data })).await;
+    }
+
+    // Changes are made in the background, so give them a moment to show up
+    async fn wait_for_brightness(client: &BeacnClient, expected: i32) {
+        let param = BeacnParameter::LED(LEDParameter::Brightness);
+        for _ in 0..100 {
+            if client.get_value(param).await.unwrap() == ParameterValue::Int(expected) {
+                return;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Brightness never became {}, it's {:?}", expected, client.get_value(param).await.unwrap());
+    }
+
+    // The example from the obs-websocket protocol docs
+    #[test]
+    fn authentication_matches_obs() {
+        assert_eq!(authenticate(PASSWORD, SALT, CHALLENGE), "1Ct943GAT+6YQUUX47Ia/ncufilbe6+oD6lY+5kaCu4=");
+    }
+
+    #[tokio::test]
+    async fn follows_obs() {
+        let (mut obs, ours) = connect(Some(PASSWORD)).await;
+        let initial = ours.client.get_value(BeacnParameter::LED(LEDParameter::Brightness)).await.unwrap();
+
+        let authentication = json!({ "challenge": CHALLENGE, "salt": SALT });
+        send(&mut obs, OP_HELLO, json!({ "obsWebSocketVersion": "5.5.0", "rpcVersion": 1, "authentication": authentication })).await;
+        let identify = receive(&mut obs).await;
+        assert_eq!(identify["op"], OP_IDENTIFY);
+        assert_eq!(identify["d"]["eventSubscriptions"], EVENTS_SCENES | EVENTS_OUTPUTS);
+        assert_eq!(identify["d"]["authentication"], "1Ct943GAT+6YQUUX47Ia/ncufilbe6+oD6lY+5kaCu4=");
+        send(&mut obs, OP_IDENTIFIED, json!({ "negotiatedRpcVersion": 1 })).await;
+
+        // It starts by asking what's going on, we're recording on the Starting Soon scene
+        let mut requests = vec![];
+        for _ in 0..3 {
+            let request = receive(&mut obs).await;
+            assert_eq!(request["op"], OP_REQUEST);
+            requests.push(request["d"]["requestType"].as_str().unwrap().to_string());
+        }
+        assert_eq!(requests, ["GetStreamStatus", "GetRecordStatus", "GetCurrentProgramScene"]);
+        for (request, data) in [
+            ("GetStreamStatus", json!({ "outputActive": false })),
+            ("GetRecordStatus", json!({ "outputActive": true })),
+            ("GetCurrentProgramScene", json!({ "currentProgramSceneName": "Starting Soon" })),
+        ] {
+            let status = json!({ "result": true, "code": 100 });
+            send(&mut obs, OP_REQUEST_RESPONSE, json!({ "requestType": request, "requestId": request, "requestStatus": status, "responseData": data })).await;
+        }
+        wait_for_brightness(&ours.client, 50).await;
+
+        event(&mut obs, "StreamStateChanged", json!({ "outputActive": true, "outputState": "OBS_WEBSOCKET_OUTPUT_STARTED" })).await;
+        wait_for_brightness(&ours.client, 90).await;
+        event(&mut obs, "StreamStateChanged", json!({ "outputActive": false, "outputState": "OBS_WEBSOCKET_OUTPUT_STOPPED" })).await;
+        event(&mut obs, "RecordStateChanged", json!({ "outputActive": false, "outputState": "OBS_WEBSOCKET_OUTPUT_STOPPED" })).await;
+        wait_for_brightness(&ours.client, 20).await;
+
+        // OBS going away ends the session, and run() then clears the overlays
+        obs.close(None).await.unwrap();
+        let (result, mut overlays) = ours.session.await.unwrap();
+        assert!(result.is_err());
+        overlays.clear().await.unwrap();
+        assert_eq!(ours.client.get_value(BeacnParameter::LED(LEDParameter::Brightness)).await.unwrap(), initial);
+    }
+
+    #[tokio::test]
+    async fn needs_a_password_when_obs_asks() {
+        let (mut obs, ours) = connect(None).await;
+        send(&mut obs, OP_HELLO, json!({ "rpcVersion": 1, "authentication": { "challenge": CHALLENGE, "salt": SALT } })).await;
+        let (result, _) = ours.session.await.unwrap();
+        assert!(format!("{:#}", result.unwrap_err()).contains("obs.password"));
+    }
+
+    #[tokio::test]
+    async fn failed_requests_are_skipped() {
+        let (mut obs, ours) = connect(None).await;
+        send(&mut obs, OP_HELLO, json!({ "rpcVersion": 1 })).await;
+        assert!(receive(&mut obs).await["d"].get("authentication").is_none());
+        send(&mut obs, OP_IDENTIFIED, json!({ "negotiatedRpcVersion": 1 })).await;
+        for _ in 0..3 {
+            receive(&mut obs).await;
+        }
+
+        let status = json!({ "result": false, "code": 207, "comment": "Not ready" });
+        let data = json!({ "outputActive": true });
+        send(&mut obs, OP_REQUEST_RESPONSE, json!({ "requestType": "GetStreamStatus", "requestStatus": status, "responseData": data })).await;
+        event(&mut obs, "RecordStateChanged", json!({ "outputActive": true })).await;
+        wait_for_brightness(&ours.client, 50).await;
+    }
+}
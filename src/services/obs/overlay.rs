@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use anyhow::{Context, Result};
+use crate::config::{LightingOverlay, ObsConfig};
+use crate::services::animation::{AnimationHandle, Layer, Values};
+
+// Tracks what OBS is doing, and which overlays that means the ring should be showing. When several
+// are active the streaming one wins, then recording, then the scene's. They're shown as a layer on
+// the animation engine, which puts the ring back afterwards.
+pub struct Overlays {
+    animations: AnimationHandle,
+
+    streaming: Values,
+    recording: Values,
+    scenes: BTreeMap<String, Values>,
+
+    pub is_streaming: bool,
+    pub is_recording: bool,
+    pub scene: Option<String>,
+}
+
+impl Overlays {
+    pub fn new(animations: AnimationHandle, config: &ObsConfig) -> Result<Self> {
+        let optional = |overlay: &Option<LightingOverlay>, name: &str| {
+            overlay.as_ref().map(LightingOverlay::values).transpose().with_context(|| format!("Invalid obs.{} overlay", name))
+        };
+
+        let mut scenes = BTreeMap::new();
+        for (scene, overlay) in &config.scenes {
+            let values = overlay.values().with_context(|| format!("Invalid overlay for scene '{}'", scene))?;
+            scenes.insert(scene.clone(), values);
+        }
+
+        Ok(Self {
+            animations,
+            streaming: optional(&config.streaming, "streaming")?.unwrap_or_default(),
+            recording: optional(&config.recording, "recording")?.unwrap_or_default(),
+            scenes,
+            is_streaming: false,
+            is_recording: false,
+            scene: None,
+        })
+    }
+
+    fn wanted(&self) -> Values {
+        let scene = self.scene.as_ref().and_then(|scene| self.scenes.get(scene));
+        let active = [
+            scene,
+            self.is_recording.then_some(&self.recording),
+            self.is_streaming.then_some(&self.streaming),
+        ];
+
+        let mut wanted: Values = vec![];
+        for (param, value) in active.into_iter().flatten().flatten() {
+            wanted.retain(|(existing, _)| existing != param);
+            wanted.push((*param, *value));
+        }
+        wanted
+    }
+
+    // Called whenever OBS changes state, puts the ring into whatever it should now look like
+    pub async fn update(&mut self) -> Result<()> {
+        self.animations.layer(Layer::Obs, self.wanted()).await
+    }
+
+    // When OBS goes away we can't know what it's doing, so everything is put back
+    pub async fn clear(&mut self) -> Result<()> {
+        self.is_streaming = false;
+        self.is_recording = false;
+        self.scene = None;
+        self.update().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use beacn_lib::client::BeacnClient;
+    use beacn_lib::messages::led::LEDParameter;
+    use beacn_lib::messages::schema::ParameterValue;
+    use crate::services::animation;
+    use crate::services::animation::parse_colour;
+    use super::*;
+
+    fn overlay(colour1: &str, brightness: Option<i32>) -> LightingOverlay {
+        LightingOverlay { colour1: Some(colour1.to_string()), brightness, ..Default::default() }
+    }
+
+    fn colour(value: &str) -> ParameterValue {
+        ParameterValue::Colour(parse_colour(value).unwrap())
+    }
+
+    async fn overlays() -> Overlays {
+        let mut config = ObsConfig {
+            streaming: Some(overlay("#ff0000", None)),
+            recording: Some(overlay("#0000ff", Some(50))),
+            ..Default::default()
+        };
+        config.scenes.insert(String::from("Starting Soon"), overlay("#00ff00", Some(20)));
+        Overlays::new(animation::start(BeacnClient::connect_simulated()), &config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn scenes_have_their_own_overlay() {
+        let mut overlays = overlays().await;
+        assert_eq!(overlays.wanted(), vec![]);
+
+        overlays.scene = Some(String::from("Starting Soon"));
+        assert_eq!(overlays.wanted(), vec![(LEDParameter::Colour1, colour("#00ff00")), (LEDParameter::Brightness, ParameterValue::Int(20))]);
+
+        overlays.scene = Some(String::from("Gameplay"));
+        assert_eq!(overlays.wanted(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn streaming_wins_over_recording_and_scenes() {
+        let mut overlays = overlays().await;
+        overlays.scene = Some(String::from("Starting Soon"));
+        overlays.is_recording = true;
+        assert_eq!(overlays.wanted(), vec![(LEDParameter::Colour1, colour("#0000ff")), (LEDParameter::Brightness, ParameterValue::Int(50))]);
+
+        // Only the values streaming sets win, the rest still come from underneath
+        overlays.is_streaming = true;
+        assert_eq!(overlays.wanted(), vec![(LEDParameter::Brightness, ParameterValue::Int(50)), (LEDParameter::Colour1, colour("#ff0000"))]);
+    }
+
+    #[tokio::test]
+    async fn invalid_scene_overlays_are_reported() {
+        let mut config = ObsConfig::default();
+        config.scenes.insert(String::from("Broken"), overlay("not a colour", None));
+        let error = Overlays::new(animation::start(BeacnClient::connect_simulated()), &config).err().unwrap();
+        assert!(format!("{:#}", error).contains("scene 'Broken'"));
+    }
+}
@@ -0,0 +1,339 @@
+// An OpenRGB SDK server, so the ring can be synced with everything else from OpenRGB (add it on the
+// 'SDK Client' tab). The ring shows up as a microphone with a mode for each lighting style, and two
+// zones holding the primary and secondary colours. OpenRGB's profiles are our saved profiles.
+//
+// Changes made elsewhere are announced with a 'device list updated' packet, which has clients
+// fetch the device again.
+
+mod protocol;
+
+use anyhow::{anyhow, bail, Result};
+use log::{debug, info, warn};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::mpsc;
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::{BeacnParameter, RGB};
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::ParameterValue;
+use beacn_lib::profile::Profile;
+use beacn_lib::state::DeviceState;
+use crate::config::OpenRgbConfig;
+use crate::services::openrgb::protocol::*;
+
+// In the order OpenRGB lists them, the values are the same as led.mode
+const MODES: &[(u32, &str)] = &[
+    (MODE_SOLID, "Solid"),
+    (0x03, "Gradient"),
+    (MODE_SPECTRUM, "Spectrum"),
+    (0x05, "Whole Ring Meter"),
+    (0x06, "Bar Meter Up"),
+    (0x07, "Bar Meter Down"),
+    (0x0a, "Sparkle Random"),
+    (0x0b, "Sparkle Meter"),
+];
+const MODE_SOLID: u32 = 0x00;
+const MODE_SPECTRUM: u32 = 0x01;
+
+// The ring's speed runs from -10 to 10, OpenRGB gets the size and the sign becomes the direction
+const MAX_SPEED: u32 = 10;
+const MAX_BRIGHTNESS: u32 = 100;
+
+// Nothing we understand comes close to this, it just stops a bad client making us allocate lots
+const MAX_PACKET: usize = 64 * 1024;
+
+pub async fn run(client: BeacnClient, config: OpenRgbConfig) -> Result<()> {
+    let listener = TcpListener::bind(&config.address).await?;
+    info!("OpenRGB server listening on {}", listener.local_addr()?);
+
+    loop {
+        let (stream, address) = listener.accept().await?;
+        debug!("OpenRGB client {} connected", address);
+
+        let client = client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve(client, stream).await {
+                debug!("OpenRGB client {} disconnected: {:#}", address, e);
+            }
+        });
+    }
+}
+
+async fn serve(client: BeacnClient, stream: TcpStream) -> Result<()> {
+    let (reader, writer) = stream.into_split();
+
+    // Subscribe before fetching the state, so nothing is missed in between
+    let events = client.subscribe();
+    let state = client.fetch_state().await?;
+
+    // Reads aren't safe to cancel part way through, so they get a task of their own
+    let (sender, receiver) = mpsc::channel(16);
+    let reader = tokio::spawn(async move {
+        if let Err(e) = read_packets(reader, sender).await {
+            debug!("Stopped reading from OpenRGB client: {:#}", e);
+        }
+    });
+
+    let mut connection = Connection { client, writer, state, version: 0 };
+    let result = connection.run(receiver, events).await;
+    reader.abort();
+    result
+}
+
+async fn read_packets(mut reader: OwnedReadHalf, sender: mpsc::Sender<(Header, Vec<u8>)>) -> Result<()> {
+    loop {
+        let mut header = [0; HEADER_LENGTH];
+        reader.read_exact(&mut header).await?;
+        let header = Header::decode(&header)?;
+        if header.length as usize > MAX_PACKET {
+            bail!("Packet {} is too large ({} bytes)", header.id, header.length);
+        }
+
+        let mut data = vec![0; header.length as usize];
+        reader.read_exact(&mut data).await?;
+        if sender.send((header, data)).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+struct Connection {
+    client: BeacnClient,
+    writer: OwnedWriteHalf,
+    state: DeviceState,
+
+    // Negotiated with REQUEST_PROTOCOL_VERSION, clients which never ask only speak version 0
+    version: u32,
+}
+
+impl Connection {
+    async fn run(&mut self, mut packets: mpsc::Receiver<(Header, Vec<u8>)>, mut events: broadcast::Receiver<DeviceEvent>) -> Result<()> {
+        loop {
+            select! {
+                packet = packets.recv() => {
+                    let Some((header, data)) = packet else {
+                        return Ok(());
+                    };
+                    if let Err(e) = self.handle(header, &data).await {
+                        warn!("Unable to handle OpenRGB packet {}: {:#}", header.id, e);
+                    }
+                }
+                event = events.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return Ok(()),
+                    };
+
+                    // Our own changes are already in the state, so only tell the client about the
+                    // device changing if it now looks different. A profile being applied changes
+                    // lots at once, so everything queued up is handled together.
+                    let before = self.controller().encode(PROTOCOL_VERSION);
+                    self.apply(event);
+                    loop {
+                        match events.try_recv() {
+                            Ok(event) => self.apply(event),
+                            Err(TryRecvError::Lagged(_)) => continue,
+                            Err(_) => break,
+                        }
+                    }
+                    if self.controller().encode(PROTOCOL_VERSION) != before {
+                        self.send(0, DEVICE_LIST_UPDATED, &[]).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, event: DeviceEvent) {
+        if let DeviceEvent::ParameterChanged(param, value) = event {
+            self.state.set_param(param, value);
+        }
+    }
+
+    async fn send(&mut self, device: u32, id: u32, data: &[u8]) -> Result<()> {
+        self.writer.write_all(&packet(device, id, data)).await?;
+        Ok(())
+    }
+
+    async fn handle(&mut self, header: Header, data: &[u8]) -> Result<()> {
+        let mut reader = Reader::new(data);
+
+        match header.id {
+            REQUEST_CONTROLLER_COUNT => self.send(0, header.id, &1_u32.to_le_bytes()).await?,
+            REQUEST_PROTOCOL_VERSION => {
+                self.version = reader.u32().unwrap_or(0).min(PROTOCOL_VERSION);
+                self.send(0, header.id, &PROTOCOL_VERSION.to_le_bytes()).await?;
+            }
+            REQUEST_CONTROLLER_DATA => {
+                check_device(header)?;
+                let version = reader.u32().unwrap_or(0).min(PROTOCOL_VERSION);
+                let data = self.controller().encode(version);
+                self.send(header.device, header.id, &data).await?;
+            }
+            SET_CLIENT_NAME => debug!("OpenRGB client is {}", c_string(data)),
+            REQUEST_PROFILE_LIST => {
+                let data = profile_list(&Profile::list_named()?);
+                self.send(0, header.id, &data).await?;
+            }
+            REQUEST_LOAD_PROFILE => self.client.apply_profile(&Profile::load_named(&c_string(data))?).await?,
+            REQUEST_SAVE_PROFILE => self.client.capture_profile().await?.save_named(&c_string(data))?,
+            REQUEST_DELETE_PROFILE => bail!("Profiles can't be deleted over OpenRGB"),
+
+            // The zones are a single LED each, so there's nothing to resize
+            RESIZE_ZONE => check_device(header)?,
+            UPDATE_LEDS => {
+                check_device(header)?;
+                let _length = reader.u32()?;
+                for (zone, colour) in reader.colours()?.into_iter().enumerate() {
+                    self.set_colour(zone, colour).await?;
+                }
+            }
+            UPDATE_ZONE_LEDS => {
+                check_device(header)?;
+                let _length = reader.u32()?;
+                let zone = reader.u32()? as usize;
+                if let Some(colour) = reader.colours()?.first() {
+                    self.set_colour(zone, *colour).await?;
+                }
+            }
+            UPDATE_SINGLE_LED => {
+                check_device(header)?;
+                let led = reader.u32()? as usize;
+                let colour = reader.colour()?;
+                self.set_colour(led, colour).await?;
+            }
+
+            // This is how effects take direct control of the LEDs, solid shows the primary colour
+            SET_CUSTOM_MODE => {
+                check_device(header)?;
+                self.set_if_changed(LEDParameter::Mode, ParameterValue::UInt(MODE_SOLID)).await?;
+            }
+
+            // The device keeps its settings anyway, so saving is the same as updating
+            UPDATE_MODE | SAVE_MODE => {
+                check_device(header)?;
+                let _length = reader.u32()?;
+                let index = reader.u32()? as usize;
+                let mode = Mode::decode(&mut reader, self.version)?;
+                self.update_mode(index, mode).await?;
+            }
+            id => debug!("Ignoring OpenRGB packet {}", id),
+        }
+        Ok(())
+    }
+
+    async fn set_colour(&mut self, zone: usize, colour: RGB) -> Result<()> {
+        let param = match zone {
+            0 => LEDParameter::Colour1,
+            1 => LEDParameter::Colour2,
+            _ => bail!("Unknown zone {}", zone),
+        };
+        self.set_if_changed(param, ParameterValue::Colour(colour)).await
+    }
+
+    async fn update_mode(&mut self, index: usize, mode: Mode) -> Result<()> {
+        // Our description of the mode is trusted over the client's, only the settings are taken
+        let current = self.modes().into_iter().nth(index).ok_or_else(|| anyhow!("Unknown mode {}", index))?;
+        self.set_if_changed(LEDParameter::Mode, ParameterValue::UInt(current.value)).await?;
+
+        if current.flags & MODE_FLAG_HAS_SPEED != 0 {
+            let speed = mode.speed.min(MAX_SPEED) as i32;
+            let speed = if mode.direction == DIRECTION_LEFT { -speed } else { speed };
+            self.set_if_changed(LEDParameter::Speed, ParameterValue::Int(speed)).await?;
+        }
+        if current.flags & MODE_FLAG_HAS_BRIGHTNESS != 0 && self.version >= 3 {
+            let brightness = mode.brightness.min(MAX_BRIGHTNESS) as i32;
+            self.set_if_changed(LEDParameter::Brightness, ParameterValue::Int(brightness)).await?;
+        }
+        Ok(())
+    }
+
+    // Clients running effects send every colour many times a second, whether it changed or not
+    async fn set_if_changed(&mut self, param: LEDParameter, value: ParameterValue) -> Result<()> {
+        let schema = param.schema();
+        let value = schema.encode(value)?;
+        if self.current(param).and_then(|current| schema.encode(current).ok()) == Some(value) {
+            return Ok(());
+        }
+
+        let param = BeacnParameter::LED(param);
+        let value = self.client.set(param, value).await?;
+        self.state.set_param(param, value);
+        Ok(())
+    }
+
+    fn current(&self, param: LEDParameter) -> Option<ParameterValue> {
+        let led = &self.state.led;
+        match param {
+            LEDParameter::Mode => Some(ParameterValue::UInt(led.mode)),
+            LEDParameter::Colour1 => Some(ParameterValue::Colour(led.colour1)),
+            LEDParameter::Colour2 => Some(ParameterValue::Colour(led.colour2)),
+            LEDParameter::Speed => Some(ParameterValue::Int(led.speed)),
+            LEDParameter::Brightness => Some(ParameterValue::Int(led.brightness)),
+            _ => None,
+        }
+    }
+
+    fn modes(&self) -> Vec<Mode> {
+        let led = &self.state.led;
+        MODES.iter().map(|(value, name)| {
+            let mut flags = MODE_FLAG_HAS_BRIGHTNESS;
+            if *value != MODE_SOLID {
+                flags |= MODE_FLAG_HAS_SPEED | MODE_FLAG_HAS_DIRECTION_LR;
+            }
+
+            // Spectrum brings its own colours, everything else uses the zones
+            let colour_mode = if *value == MODE_SPECTRUM {
+                MODE_COLORS_NONE
+            } else {
+                flags |= MODE_FLAG_HAS_PER_LED_COLOR;
+                MODE_COLORS_PER_LED
+            };
+
+            Mode {
+                name: name.to_string(),
+                value: *value,
+                flags,
+                speed_min: 0,
+                speed_max: MAX_SPEED,
+                brightness_min: 0,
+                brightness_max: MAX_BRIGHTNESS,
+                speed: led.speed.unsigned_abs().min(MAX_SPEED),
+                brightness: led.brightness.clamp(0, MAX_BRIGHTNESS as i32) as u32,
+                direction: if led.speed < 0 { DIRECTION_LEFT } else { DIRECTION_RIGHT },
+                colour_mode,
+            }
+        }).collect()
+    }
+
+    fn controller(&self) -> Controller {
+        let info = self.client.info();
+        let led = &self.state.led;
+
+        Controller {
+            name: info.product.clone().unwrap_or_else(|| String::from("Beacn Mic")),
+            vendor: info.manufacturer.clone().unwrap_or_else(|| String::from("Beacn")),
+            description: String::from("Beacn Mic LED ring"),
+            version: info.version.clone(),
+            serial: info.serial.clone().unwrap_or_default(),
+            location: format!("USB: Bus {} Address {}", info.bus_number, info.address),
+            modes: self.modes(),
+            active_mode: MODES.iter().position(|(value, _)| *value == led.mode).unwrap_or_default(),
+            zones: vec![(String::from("Primary"), led.colour1), (String::from("Secondary"), led.colour2)],
+        }
+    }
+}
+
+// We only have the one device
+fn check_device(header: Header) -> Result<()> {
+    if header.device != 0 {
+        bail!("Unknown device {}", header.device);
+    }
+    Ok(())
+}
@@ -0,0 +1,421 @@
+// The parts of the OpenRGB network SDK protocol which a server with a single device needs. Every
+// packet is a 16 byte header ("ORGB", device index, packet id and data length) followed by its
+// data. Everything is little endian, and strings are prefixed with their length (including a NUL).
+
+use anyhow::{anyhow, bail, Result};
+use beacn_lib::messages::RGB;
+
+pub const HEADER_LENGTH: usize = 16;
+const MAGIC: &[u8; 4] = b"ORGB";
+
+// The newest version we speak, 3 added brightness to modes. Later versions add segments and plugins,
+// which don't mean anything for a single ring.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+pub const REQUEST_CONTROLLER_COUNT: u32 = 0;
+pub const REQUEST_CONTROLLER_DATA: u32 = 1;
+pub const REQUEST_PROTOCOL_VERSION: u32 = 40;
+pub const SET_CLIENT_NAME: u32 = 50;
+pub const DEVICE_LIST_UPDATED: u32 = 100;
+pub const REQUEST_PROFILE_LIST: u32 = 150;
+pub const REQUEST_SAVE_PROFILE: u32 = 151;
+pub const REQUEST_LOAD_PROFILE: u32 = 152;
+pub const REQUEST_DELETE_PROFILE: u32 = 153;
+pub const RESIZE_ZONE: u32 = 1000;
+pub const UPDATE_LEDS: u32 = 1050;
+pub const UPDATE_ZONE_LEDS: u32 = 1051;
+pub const UPDATE_SINGLE_LED: u32 = 1052;
+pub const SET_CUSTOM_MODE: u32 = 1100;
+pub const UPDATE_MODE: u32 = 1101;
+pub const SAVE_MODE: u32 = 1102;
+
+pub const DEVICE_TYPE_MICROPHONE: i32 = 16;
+const ZONE_TYPE_SINGLE: i32 = 0;
+
+pub const MODE_FLAG_HAS_SPEED: u32 = 1 << 0;
+pub const MODE_FLAG_HAS_DIRECTION_LR: u32 = 1 << 1;
+pub const MODE_FLAG_HAS_BRIGHTNESS: u32 = 1 << 4;
+pub const MODE_FLAG_HAS_PER_LED_COLOR: u32 = 1 << 5;
+
+pub const MODE_COLORS_NONE: u32 = 0;
+pub const MODE_COLORS_PER_LED: u32 = 1;
+
+pub const DIRECTION_LEFT: u32 = 0;
+pub const DIRECTION_RIGHT: u32 = 1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub device: u32,
+    pub id: u32,
+    pub length: u32,
+}
+
+impl Header {
+    pub fn decode(data: &[u8; HEADER_LENGTH]) -> Result<Self> {
+        if !data.starts_with(MAGIC) {
+            bail!("Not an OpenRGB packet");
+        }
+        let field = |index: usize| u32::from_le_bytes(data[index * 4..index * 4 + 4].try_into().unwrap());
+        Ok(Self { device: field(1), id: field(2), length: field(3) })
+    }
+}
+
+pub fn packet(device: u32, id: u32, data: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(HEADER_LENGTH + data.len());
+    packet.extend(MAGIC);
+    packet.extend(device.to_le_bytes());
+    packet.extend(id.to_le_bytes());
+    packet.extend((data.len() as u32).to_le_bytes());
+    packet.extend(data);
+    packet
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mode {
+    pub name: String,
+    pub value: u32,
+    pub flags: u32,
+    pub speed_min: u32,
+    pub speed_max: u32,
+    pub brightness_min: u32,
+    pub brightness_max: u32,
+    pub speed: u32,
+    pub brightness: u32,
+    pub direction: u32,
+    pub colour_mode: u32,
+}
+
+impl Mode {
+    fn encode(&self, writer: &mut Writer, version: u32) {
+        writer.string(&self.name);
+        writer.u32(self.value);
+        writer.u32(self.flags);
+        writer.u32(self.speed_min);
+        writer.u32(self.speed_max);
+        if version >= 3 {
+            writer.u32(self.brightness_min);
+            writer.u32(self.brightness_max);
+        }
+
+        // The number of mode specific colours allowed, we only use per LED colours
+        writer.u32(0);
+        writer.u32(0);
+        writer.u32(self.speed);
+        if version >= 3 {
+            writer.u32(self.brightness);
+        }
+        writer.u32(self.direction);
+        writer.u32(self.colour_mode);
+        writer.u16(0);
+    }
+
+    pub fn decode(reader: &mut Reader, version: u32) -> Result<Self> {
+        let name = reader.string()?;
+        let value = reader.u32()?;
+        let flags = reader.u32()?;
+        let speed_min = reader.u32()?;
+        let speed_max = reader.u32()?;
+        let (brightness_min, brightness_max) = if version >= 3 { (reader.u32()?, reader.u32()?) } else { (0, 0) };
+        let _colours_min = reader.u32()?;
+        let _colours_max = reader.u32()?;
+        let speed = reader.u32()?;
+        let brightness = if version >= 3 { reader.u32()? } else { 0 };
+        let direction = reader.u32()?;
+        let colour_mode = reader.u32()?;
+
+        // Mode specific colours, which we don't use
+        reader.colours()?;
+
+        Ok(Self { name, value, flags, speed_min, speed_max, brightness_min, brightness_max, speed, brightness, direction, colour_mode })
+    }
+}
+
+// Each zone has a single LED of the same name
+pub struct Controller {
+    pub name: String,
+    pub vendor: String,
+    pub description: String,
+    pub version: String,
+    pub serial: String,
+    pub location: String,
+    pub modes: Vec<Mode>,
+    pub active_mode: usize,
+    pub zones: Vec<(String, RGB)>,
+}
+
+impl Controller {
+    pub fn encode(&self, version: u32) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.i32(DEVICE_TYPE_MICROPHONE);
+        writer.string(&self.name);
+        if version >= 1 {
+            writer.string(&self.vendor);
+        }
+        writer.string(&self.description);
+        writer.string(&self.version);
+        writer.string(&self.serial);
+        writer.string(&self.location);
+
+        writer.u16(self.modes.len() as u16);
+        writer.i32(self.active_mode as i32);
+        for mode in &self.modes {
+            mode.encode(&mut writer, version);
+        }
+
+        writer.u16(self.zones.len() as u16);
+        for (name, _) in &self.zones {
+            writer.string(name);
+            writer.i32(ZONE_TYPE_SINGLE);
+            writer.u32(1);
+            writer.u32(1);
+            writer.u32(1);
+            writer.u16(0); // No matrix map
+        }
+
+        writer.u16(self.zones.len() as u16);
+        for (index, (name, _)) in self.zones.iter().enumerate() {
+            writer.string(name);
+            writer.u32(index as u32);
+        }
+
+        writer.u16(self.zones.len() as u16);
+        for (_, colour) in &self.zones {
+            writer.colour(*colour);
+        }
+
+        // The length at the start includes itself
+        let mut data = ((writer.0.len() + 4) as u32).to_le_bytes().to_vec();
+        data.extend(writer.0);
+        data
+    }
+}
+
+pub fn profile_list(names: &[String]) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.u16(names.len() as u16);
+    for name in names {
+        writer.string(name);
+    }
+
+    let mut data = ((writer.0.len() + 4) as u32).to_le_bytes().to_vec();
+    data.extend(writer.0);
+    data
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u16(&mut self, value: u16) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u16(value.len() as u16 + 1);
+        self.0.extend(value.as_bytes());
+        self.0.push(0);
+    }
+
+    fn colour(&mut self, colour: RGB) {
+        self.0.extend([colour.red, colour.green, colour.blue, 0]);
+    }
+}
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(length).filter(|end| *end <= self.data.len());
+        let end = end.ok_or_else(|| anyhow!("OpenRGB packet is truncated"))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into()?)
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let length = self.u16()? as usize;
+        let bytes = self.bytes(length)?;
+        Ok(String::from_utf8_lossy(bytes.strip_suffix(&[0]).unwrap_or(bytes)).to_string())
+    }
+
+    pub fn colour(&mut self) -> Result<RGB> {
+        let [red, green, blue, _] = self.array()?;
+        Ok(RGB::new(red, green, blue))
+    }
+
+    pub fn colours(&mut self) -> Result<Vec<RGB>> {
+        let count = self.u16()?;
+        (0..count).map(|_| self.colour()).collect()
+    }
+}
+
+// A few packets (profile names, the client name) are just a NUL terminated string, with no length
+pub fn c_string(data: &[u8]) -> String {
+    let end = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(name: &str) -> Mode {
+        Mode {
+            name: name.to_string(),
+            value: 4,
+            flags: MODE_FLAG_HAS_SPEED | MODE_FLAG_HAS_DIRECTION_LR | MODE_FLAG_HAS_BRIGHTNESS,
+            speed_min: 0,
+            speed_max: 10,
+            brightness_min: 0,
+            brightness_max: 100,
+            speed: 7,
+            brightness: 60,
+            direction: DIRECTION_RIGHT,
+            colour_mode: MODE_COLORS_NONE,
+        }
+    }
+
+    fn encode_mode(mode: &Mode, version: u32) -> Vec<u8> {
+        let mut writer = Writer::default();
+        mode.encode(&mut writer, version);
+        writer.0
+    }
+
+    #[test]
+    fn modes_survive_the_round_trip() {
+        for version in 0..=PROTOCOL_VERSION {
+            let mut expected = mode("Rainbow");
+            if version < 3 {
+                (expected.brightness_min, expected.brightness_max, expected.brightness) = (0, 0, 0);
+            }
+
+            let data = encode_mode(&mode("Rainbow"), version);
+            let mut reader = Reader::new(&data);
+            assert_eq!(Mode::decode(&mut reader, version).unwrap(), expected, "version {}", version);
+            assert_eq!(reader.position, data.len(), "version {}", version);
+        }
+    }
+
+    #[test]
+    fn truncated_modes_are_errors() {
+        let data = encode_mode(&mode("Rainbow"), PROTOCOL_VERSION);
+        for length in 0..data.len() {
+            assert!(Mode::decode(&mut Reader::new(&data[..length]), PROTOCOL_VERSION).is_err(), "{} bytes decoded", length);
+        }
+    }
+
+    #[test]
+    fn controllers_can_be_read_back() {
+        let controller = Controller {
+            name: String::from("Beacn Mic"),
+            vendor: String::from("Beacn"),
+            description: String::from("Ring"),
+            version: String::from("1.2.3"),
+            serial: String::from("ABC"),
+            location: String::from("USB"),
+            modes: vec![mode("Solid"), mode("Rainbow")],
+            active_mode: 1,
+            zones: vec![(String::from("Primary"), RGB::new(255, 0, 0)), (String::from("Secondary"), RGB::new(0, 0, 255))],
+        };
+        let data = controller.encode(PROTOCOL_VERSION);
+
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.u32().unwrap() as usize, data.len());
+        assert_eq!(reader.u32().unwrap() as i32, DEVICE_TYPE_MICROPHONE);
+        for expected in ["Beacn Mic", "Beacn", "Ring", "1.2.3", "ABC", "USB"] {
+            assert_eq!(reader.string().unwrap(), expected);
+        }
+
+        assert_eq!(reader.u16().unwrap(), 2);
+        assert_eq!(reader.u32().unwrap(), 1);
+        for expected in &controller.modes {
+            assert_eq!(&Mode::decode(&mut reader, PROTOCOL_VERSION).unwrap(), expected);
+        }
+
+        assert_eq!(reader.u16().unwrap(), 2);
+        for expected in ["Primary", "Secondary"] {
+            assert_eq!(reader.string().unwrap(), expected);
+            reader.bytes(4 * 4 + 2).unwrap(); // Type, LED counts and no matrix
+        }
+        assert_eq!(reader.u16().unwrap(), 2);
+        for (index, expected) in ["Primary", "Secondary"].into_iter().enumerate() {
+            assert_eq!(reader.string().unwrap(), expected);
+            assert_eq!(reader.u32().unwrap() as usize, index);
+        }
+        assert_eq!(reader.colours().unwrap(), vec![RGB::new(255, 0, 0), RGB::new(0, 0, 255)]);
+        assert_eq!(reader.position, data.len());
+    }
+
+    #[test]
+    fn older_clients_get_less() {
+        let controller = Controller {
+            name: String::new(),
+            vendor: String::from("Beacn"),
+            description: String::new(),
+            version: String::new(),
+            serial: String::new(),
+            location: String::new(),
+            modes: vec![mode("Solid")],
+            active_mode: 0,
+            zones: vec![],
+        };
+
+        // Version 0 has no vendor, and only 3 has mode brightness
+        let lengths: Vec<_> = (0..=PROTOCOL_VERSION).map(|version| controller.encode(version).len()).collect();
+        assert_eq!(lengths[1] - lengths[0], 2 + "Beacn".len() + 1);
+        assert_eq!(lengths[2], lengths[1]);
+        assert_eq!(lengths[3] - lengths[2], 3 * 4);
+    }
+
+    #[test]
+    fn headers_survive_the_round_trip() {
+        let data = packet(2, UPDATE_MODE, &[1, 2, 3]);
+        let header = Header::decode(data[..HEADER_LENGTH].try_into().unwrap()).unwrap();
+        assert_eq!((header.device, header.id, header.length), (2, UPDATE_MODE, 3));
+        assert_eq!(&data[HEADER_LENGTH..], &[1, 2, 3]);
+
+        let mut wrong = data.clone();
+        wrong[0] = b'X';
+        assert!(Header::decode(wrong[..HEADER_LENGTH].try_into().unwrap()).is_err());
+    }
+
+    #[test]
+    fn reader_checks_lengths() {
+        // A string claiming to be longer than the packet, and a colour list which stops short
+        assert!(Reader::new(&[0xff, 0xff, b'a', 0]).string().is_err());
+        assert!(Reader::new(&[2, 0, 1, 2, 3, 0]).colours().is_err());
+
+        // Strings don't have to have the NUL
+        assert_eq!(Reader::new(&[3, 0, b'a', b'b', 0]).string().unwrap(), "ab");
+        assert_eq!(Reader::new(&[2, 0, b'a', b'b']).string().unwrap(), "ab");
+        assert_eq!(c_string(b"Default\0junk"), "Default");
+        assert_eq!(c_string(b"Default"), "Default");
+    }
+}
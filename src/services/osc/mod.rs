@@ -0,0 +1,188 @@
+// An OSC server for show control software, parameters are addressed by their names with the dots
+// turned into slashes, under /beacn:
+//
+//   /beacn/led/mode "spectrum"        Enums take their name, or the number
+//   /beacn/led/colour1 255 136 0      Colours take three ints, three floats (0 to 1), an OSC
+//                                     colour, or "#rrggbb"
+//   /beacn/led/brightness 50          Numbers take an int or a float
+//   /beacn/led/brightness             With no arguments, the current value is sent back
+//   /beacn/profile "evening"          Applies a saved profile
+//   /beacn/subscribe [port]           Sends changes to the sender (optionally on another port)
+//   /beacn/unsubscribe [port]
+//
+// Changes (from anywhere) are sent to subscribed clients, and any listed in the config.
+
+mod packet;
+
+use std::net::SocketAddr;
+use anyhow::{anyhow, bail, Result};
+use log::{debug, info, warn};
+use tokio::net::{lookup_host, UdpSocket};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::{BeacnParameter, RGB};
+use beacn_lib::messages::mic::MicParameter;
+use beacn_lib::messages::schema::{ParameterSchema, ParameterValue, ValueKind};
+use beacn_lib::profile::Profile;
+use crate::config::OscConfig;
+use crate::services::osc::packet::{OscArg, OscMessage};
+
+const PREFIX: &str = "/beacn/";
+
+// The biggest packet we'll accept, anything we understand is far smaller than this
+const MAX_PACKET: usize = 4096;
+
+pub async fn run(client: BeacnClient, config: OscConfig) -> Result<()> {
+    let mut clients = vec![];
+    for address in &config.clients {
+        let address = lookup_host(address).await?.next().ok_or_else(|| anyhow!("Unable to resolve {}", address))?;
+        clients.push(address);
+    }
+
+    let socket = UdpSocket::bind(&config.address).await?;
+    info!("OSC server listening on {}", socket.local_addr()?);
+
+    let mut events = client.subscribe();
+    let mut buffer = [0; MAX_PACKET];
+    loop {
+        select! {
+            received = socket.recv_from(&mut buffer) => {
+                let (length, sender) = received?;
+                let messages = match packet::decode(&buffer[..length]) {
+                    Ok(messages) => messages,
+                    Err(e) => {
+                        warn!("Invalid OSC packet from {}: {}", sender, e);
+                        continue;
+                    }
+                };
+
+                for message in messages {
+                    if let Err(e) = handle(&client, &socket, &mut clients, sender, &message).await {
+                        warn!("Unable to handle OSC message {}: {:#}", message.address, e);
+                    }
+                }
+            }
+            event = events.recv() => {
+                let (param, value) = match event {
+                    Ok(DeviceEvent::ParameterChanged(param, value)) => (param, param.schema().decode(value)),
+                    Ok(DeviceEvent::MuteChanged(muted)) => (BeacnParameter::Mic(MicParameter::Mute), ParameterValue::Bool(muted)),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                let data = feedback(param, value).encode();
+                for client in &clients {
+                    if let Err(e) = socket.send_to(&data, client).await {
+                        warn!("Unable to send OSC feedback to {}: {}", client, e);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle(client: &BeacnClient, socket: &UdpSocket, clients: &mut Vec<SocketAddr>, sender: SocketAddr, message: &OscMessage) -> Result<()> {
+    let path = message.address.strip_prefix(PREFIX).ok_or_else(|| anyhow!("Unknown address"))?;
+
+    match (path, message.args.as_slice()) {
+        ("subscribe", args) => {
+            let address = reply_address(sender, args)?;
+            if !clients.contains(&address) {
+                debug!("OSC client {} subscribed", address);
+                clients.push(address);
+            }
+        }
+        ("unsubscribe", args) => {
+            let address = reply_address(sender, args)?;
+            clients.retain(|client| *client != address);
+        }
+        ("profile", [OscArg::String(name)]) => client.apply_profile(&Profile::load_named(name)?).await?,
+        ("profile", _) => bail!("Expected the name of a profile"),
+        (path, args) => {
+            let param = BeacnParameter::from_name(&path.replace('/', ".")).ok_or_else(|| anyhow!("Unknown address"))?;
+            let schema = param.schema();
+
+            if args.is_empty() {
+                let value = client.get_value(param).await?;
+                socket.send_to(&feedback(param, value).encode(), sender).await?;
+                return Ok(());
+            }
+
+            if schema.read_only {
+                bail!("{} is read only", schema.name);
+            }
+            client.set_value(param, from_osc(&schema, args)?).await?;
+        }
+    }
+    Ok(())
+}
+
+// Clients often listen on a different port to the one they send from, so they can tell us
+fn reply_address(sender: SocketAddr, args: &[OscArg]) -> Result<SocketAddr> {
+    match args {
+        [] => Ok(sender),
+        [OscArg::Int(port)] => {
+            let port = u16::try_from(*port).map_err(|_| anyhow!("{} is not a valid port", port))?;
+            Ok(SocketAddr::new(sender.ip(), port))
+        }
+        _ => bail!("Expected an optional port number"),
+    }
+}
+
+fn from_osc(schema: &ParameterSchema, args: &[OscArg]) -> Result<ParameterValue> {
+    let invalid = || anyhow!("{:?} is not a valid value for {} ({})", args, schema.name, schema.kind);
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    let value = match (schema.kind, args) {
+        (_, [OscArg::String(value)]) => return schema.parse(value),
+        (ValueKind::Bool, [OscArg::Bool(value)]) => ParameterValue::Bool(*value),
+        (ValueKind::Bool, [OscArg::Int(value)]) => ParameterValue::Bool(*value != 0),
+
+        // Buttons on control surfaces tend to send 1.0 and 0.0
+        (ValueKind::Bool, [OscArg::Float(value)]) => ParameterValue::Bool(*value >= 0.5),
+        (ValueKind::Enum(_) | ValueKind::UInt(..), [OscArg::Int(value)]) => ParameterValue::UInt(u32::try_from(*value).map_err(|_| invalid())?),
+        (ValueKind::Enum(_) | ValueKind::UInt(..), [OscArg::Float(value)]) if *value >= 0.0 => ParameterValue::UInt(value.round() as u32),
+        (ValueKind::Int(..), [OscArg::Int(value)]) => ParameterValue::Int(*value),
+        (ValueKind::Int(..), [OscArg::Float(value)]) => ParameterValue::Int(value.round() as i32),
+        (ValueKind::Float(..), [OscArg::Float(value)]) => ParameterValue::Float(*value),
+        (ValueKind::Float(..), [OscArg::Int(value)]) => ParameterValue::Float(*value as f32),
+        (ValueKind::Colour, [OscArg::Colour([red, green, blue, _])]) => colour(*red, *green, *blue),
+        (ValueKind::Colour, [OscArg::Int(red), OscArg::Int(green), OscArg::Int(blue)]) => {
+            let channel = |value: &i32| u8::try_from(*value).map_err(|_| invalid());
+            colour(channel(red)?, channel(green)?, channel(blue)?)
+        }
+        (ValueKind::Colour, [OscArg::Float(red), OscArg::Float(green), OscArg::Float(blue)]) => {
+            colour(channel(*red), channel(*green), channel(*blue))
+        }
+        _ => return Err(invalid()),
+    };
+
+    schema.encode(value)?;
+    Ok(value)
+}
+
+fn colour(red: u8, green: u8, blue: u8) -> ParameterValue {
+    ParameterValue::Colour(RGB { red, green, blue, alpha: 0 })
+}
+
+// Booleans go out as 0 or 1 rather than T or F, as not everything understands those
+fn feedback(param: BeacnParameter, value: ParameterValue) -> OscMessage {
+    let schema = param.schema();
+    let address = format!("{}{}", PREFIX, schema.name.replace('.', "/"));
+
+    let args = match (schema.kind, value) {
+        (ValueKind::Enum(_), _) => vec![OscArg::String(schema.format(value))],
+        (_, ParameterValue::UInt(value)) => vec![OscArg::Int(value as i32)],
+        (_, ParameterValue::Int(value)) => vec![OscArg::Int(value)],
+        (_, ParameterValue::Float(value)) => vec![OscArg::Float(value)],
+        (_, ParameterValue::Bool(value)) => vec![OscArg::Int(value as i32)],
+        (_, ParameterValue::Colour(value)) => {
+            vec![OscArg::Int(value.red as i32), OscArg::Int(value.green as i32), OscArg::Int(value.blue as i32)]
+        }
+    };
+    OscMessage::new(address, args)
+}
@@ -0,0 +1,272 @@
+// Just enough of OSC 1.0 to talk to lighting desks and control surfaces. Everything is big endian,
+// and strings and blobs are NUL padded out to a multiple of 4 bytes.
+
+use anyhow::{anyhow, bail, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Bool(bool),
+    // RGBA, from the 'r' type
+    Colour([u8; 4]),
+    Long(i64),
+    Double(f64),
+    Blob(Vec<u8>),
+    Nil,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    pub fn new(address: impl Into<String>, args: Vec<OscArg>) -> Self {
+        Self { address: address.into(), args }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = vec![];
+        write_string(&mut data, &self.address);
+
+        let mut tags = String::from(",");
+        for arg in &self.args {
+            tags.push(match arg {
+                OscArg::Int(_) => 'i',
+                OscArg::Float(_) => 'f',
+                OscArg::String(_) => 's',
+                OscArg::Bool(true) => 'T',
+                OscArg::Bool(false) => 'F',
+                OscArg::Colour(_) => 'r',
+                OscArg::Long(_) => 'h',
+                OscArg::Double(_) => 'd',
+                OscArg::Blob(_) => 'b',
+                OscArg::Nil => 'N',
+            });
+        }
+        write_string(&mut data, &tags);
+
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(value) => data.extend(value.to_be_bytes()),
+                OscArg::Float(value) => data.extend(value.to_be_bytes()),
+                OscArg::String(value) => write_string(&mut data, value),
+                OscArg::Colour(value) => data.extend(value),
+                OscArg::Long(value) => data.extend(value.to_be_bytes()),
+                OscArg::Double(value) => data.extend(value.to_be_bytes()),
+                OscArg::Blob(value) => {
+                    data.extend((value.len() as i32).to_be_bytes());
+                    data.extend(value);
+                    pad(&mut data);
+                }
+                OscArg::Bool(_) | OscArg::Nil => {}
+            }
+        }
+        data
+    }
+}
+
+// Bundles are flattened, we don't support scheduling so everything in them happens immediately
+pub fn decode(data: &[u8]) -> Result<Vec<OscMessage>> {
+    let mut reader = Reader { data, position: 0 };
+
+    if data.starts_with(b"#bundle\0") {
+        reader.position = 16; // Skip the tag and time tag
+        let mut messages = vec![];
+        while !reader.is_empty() {
+            let length = reader.int()? as usize;
+            messages.extend(decode(reader.bytes(length)?)?);
+        }
+        return Ok(messages);
+    }
+
+    let address = reader.string()?;
+    if !address.starts_with('/') {
+        bail!("'{}' is not a valid OSC address", address);
+    }
+
+    // Some older senders leave out the type tags if there are no arguments
+    if reader.is_empty() {
+        return Ok(vec![OscMessage::new(address, vec![])]);
+    }
+
+    let tags = reader.string()?;
+    let tags = tags.strip_prefix(',').ok_or_else(|| anyhow!("Missing OSC type tags"))?;
+
+    let mut args = vec![];
+    for tag in tags.chars() {
+        args.push(match tag {
+            'i' => OscArg::Int(reader.int()?),
+            'f' => OscArg::Float(f32::from_be_bytes(reader.array()?)),
+            's' | 'S' => OscArg::String(reader.string()?),
+            'T' => OscArg::Bool(true),
+            'F' => OscArg::Bool(false),
+            'r' => OscArg::Colour(reader.array()?),
+            'h' => OscArg::Long(i64::from_be_bytes(reader.array()?)),
+            'd' => OscArg::Double(f64::from_be_bytes(reader.array()?)),
+            'b' => {
+                let length = reader.int()? as usize;
+                let blob = reader.bytes(length)?.to_vec();
+                reader.align();
+                OscArg::Blob(blob)
+            }
+            'N' | 'I' => OscArg::Nil,
+            _ => bail!("Unsupported OSC type '{}'", tag),
+        });
+    }
+    Ok(vec![OscMessage::new(address, args)])
+}
+
+fn write_string(data: &mut Vec<u8>, value: &str) {
+    data.extend(value.as_bytes());
+    data.push(0);
+    pad(data);
+}
+
+fn pad(data: &mut Vec<u8>) {
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(length).filter(|end| *end <= self.data.len());
+        let end = end.ok_or_else(|| anyhow!("OSC packet is truncated"))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into()?)
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let remaining = &self.data[self.position.min(self.data.len())..];
+        let length = remaining.iter().position(|byte| *byte == 0).ok_or_else(|| anyhow!("Unterminated OSC string"))?;
+        let value = String::from_utf8(self.bytes(length)?.to_vec())?;
+        self.position += 1;
+        self.align();
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.next_multiple_of(4);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_arg() -> OscMessage {
+        OscMessage::new("/beacn/led/colour1", vec![
+            OscArg::Int(-7),
+            OscArg::Float(0.5),
+            OscArg::String(String::from("solid")),
+            OscArg::Bool(true),
+            OscArg::Bool(false),
+            OscArg::Colour([255, 128, 0, 255]),
+            OscArg::Long(1 << 40),
+            OscArg::Double(-2.25),
+            OscArg::Blob(vec![1, 2, 3, 4, 5]),
+            OscArg::Nil,
+        ])
+    }
+
+    fn bundle(messages: &[OscMessage]) -> Vec<u8> {
+        let mut data = b"#bundle\0".to_vec();
+        data.extend(1u64.to_be_bytes());
+        for message in messages {
+            let encoded = message.encode();
+            data.extend((encoded.len() as i32).to_be_bytes());
+            data.extend(encoded);
+        }
+        data
+    }
+
+    #[test]
+    fn messages_survive_the_round_trip() {
+        let message = every_arg();
+        let encoded = message.encode();
+        assert!(encoded.len().is_multiple_of(4));
+        assert_eq!(decode(&encoded).unwrap(), vec![message]);
+
+        let empty = OscMessage::new("/beacn/ping", vec![]);
+        assert_eq!(decode(&empty.encode()).unwrap(), vec![empty]);
+    }
+
+    #[test]
+    fn reads_a_known_packet() {
+        let data = b"/beacn/led/brightness\0\0\0,i\0\0\0\0\0\x32";
+        assert_eq!(decode(data).unwrap(), vec![OscMessage::new("/beacn/led/brightness", vec![OscArg::Int(50)])]);
+    }
+
+    #[test]
+    fn accepts_a_missing_type_tag() {
+        assert_eq!(decode(b"/beacn/state\0\0\0\0").unwrap(), vec![OscMessage::new("/beacn/state", vec![])]);
+    }
+
+    #[test]
+    fn flattens_bundles() {
+        let first = OscMessage::new("/beacn/mic/mute", vec![OscArg::Bool(true)]);
+        let second = every_arg();
+        assert_eq!(decode(&bundle(&[first.clone(), second.clone()])).unwrap(), vec![first, second]);
+    }
+
+    #[test]
+    fn truncated_packets_are_errors() {
+        let encoded = every_arg().encode();
+
+        // Anything cut after the type tags is missing some of the arguments. Only the padding at
+        // the end of the blob can be left off, as everything's there without it.
+        let arguments = encoded.len() - (4 + 4 + 8 + 4 + 8 + 8 + 12);
+        let padding = 3;
+        for length in 0..encoded.len() {
+            let result = decode(&encoded[..length]);
+            if length >= arguments && length < encoded.len() - padding {
+                assert!(result.is_err(), "{} bytes decoded as {:?}", length, result);
+            }
+        }
+
+        let bundled = bundle(&[every_arg()]);
+        for length in 17..bundled.len() {
+            assert!(decode(&bundled[..length]).is_err(), "{} bytes of the bundle decoded", length);
+        }
+    }
+
+    #[test]
+    fn rejects_nonsense() {
+        assert!(decode(b"beacn\0\0\0").is_err());
+        assert!(decode(b"/beacn\0\0i\0\0\0").is_err());
+        assert!(decode(b"/beacn\0\0,x\0\0").is_err());
+        assert!(decode(b"/beacn").is_err());
+
+        // A blob claiming to be far bigger than the packet
+        let mut data = OscMessage::new("/beacn", vec![]).encode();
+        data.truncate(8);
+        data.extend(b",b\0\0");
+        data.extend(i32::MAX.to_be_bytes());
+        assert!(decode(&data).is_err());
+        data.truncate(12);
+        data.extend((-1i32).to_be_bytes());
+        assert!(decode(&data).is_err());
+    }
+}
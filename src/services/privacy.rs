@@ -0,0 +1,283 @@
+// Mutes the Mic (and changes the ring) while the session is locked or the system is asleep, and
+// puts both back afterwards, eg.
+//
+//   [privacy]
+//   enabled = true
+//   lighting = { mode = "solid", colour1 = "#200000" }
+//
+// This follows systemd-logind on the system bus. Locking is picked up from the Lock / Unlock
+// signals as well as LockedHint, as desktops differ in which they use. A delay inhibitor is held
+// so the Mic is muted before the system actually goes to sleep.
+//
+// If the Mic was already muted it's left muted afterwards.
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use anyhow::{bail, Context, Result};
+use futures_util::StreamExt;
+use log::{debug, info, warn};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use zbus::zvariant::{OwnedFd, OwnedObjectPath};
+use zbus::{proxy, Connection};
+use beacn_lib::client::BeacnClient;
+use beacn_lib::events::DeviceEvent;
+use crate::config::PrivacyConfig;
+use crate::services::animation::{AnimationHandle, Layer, Values};
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Manager {
+    fn get_session(&self, session_id: &str) -> zbus::Result<OwnedObjectPath>;
+    fn get_user(&self, uid: u32) -> zbus::Result<OwnedObjectPath>;
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.freedesktop.login1.Session", default_service = "org.freedesktop.login1")]
+trait Session {
+    #[zbus(signal)]
+    fn lock(&self) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn unlock(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn idle_hint(&self) -> zbus::Result<bool>;
+}
+
+#[proxy(interface = "org.freedesktop.login1.User", default_service = "org.freedesktop.login1")]
+trait User {
+    #[zbus(property)]
+    fn display(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+}
+
+// Why the Mic should be muted, it stays muted until none of these apply
+#[derive(Default)]
+struct Reasons {
+    locked: bool,
+    asleep: bool,
+    idle: bool,
+}
+
+impl Reasons {
+    fn any(&self) -> bool {
+        self.locked || self.asleep || self.idle
+    }
+}
+
+// Whether we muted the Mic, so it can be put back. The ring is a layer on the animation engine,
+// which puts it back itself.
+struct Private {
+    client: BeacnClient,
+    animations: AnimationHandle,
+    lighting: Values,
+
+    active: bool,
+    muted: bool,
+}
+
+impl Private {
+    // Brings the Mic into line with whether it should be private. Only marked as done if everything
+    // worked, so a failure (such as the Mic still waking up) is tried again on the next change.
+    async fn update(&mut self, private: bool) {
+        if private == self.active {
+            return;
+        }
+        let result = match private {
+            true => self.start().await,
+            false => self.stop().await,
+        };
+        match result {
+            Ok(()) => self.active = private,
+            Err(e) => warn!("Unable to {} the Mic: {:#}", if private { "mute" } else { "restore" }, e),
+        }
+    }
+
+    async fn start(&mut self) -> Result<()> {
+        info!("Muting the Mic for privacy");
+        if !self.client.get_muted().await? {
+            self.client.set_muted(true).await?;
+            self.muted = true;
+        }
+
+        self.animations.layer(Layer::Privacy, self.lighting.clone()).await
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        info!("Restoring the Mic");
+        self.animations.layer(Layer::Privacy, vec![]).await?;
+        if self.muted {
+            self.client.set_muted(false).await?;
+            self.muted = false;
+        }
+        Ok(())
+    }
+}
+
+pub async fn run(client: BeacnClient, animations: AnimationHandle, config: PrivacyConfig) -> Result<()> {
+    let lighting = config.lighting.values().context("Invalid privacy.lighting")?;
+    let mut events = client.subscribe();
+    let mut private = Private { client, animations, lighting, active: false, muted: false };
+
+    let connection = Connection::system().await.context("Unable to connect to the system bus")?;
+    let manager = ManagerProxy::new(&connection).await?;
+    let path = find_session(&connection, &manager).await?;
+    debug!("Following logind session {}", path.as_str());
+    let session = SessionProxy::builder(&connection).path(path)?.build().await?;
+
+    let mut sleeping = manager.receive_prepare_for_sleep().await?;
+    let mut locks = session.receive_lock().await?;
+    let mut unlocks = session.receive_unlock().await?;
+    let mut locked_hint = session.receive_locked_hint_changed().await;
+    let mut idle_hint = session.receive_idle_hint_changed().await;
+
+    let mut reasons = Reasons {
+        locked: session.locked_hint().await.unwrap_or(false),
+        idle: config.idle && session.idle_hint().await.unwrap_or(false),
+        ..Default::default()
+    };
+    let mut inhibitor = inhibit(&manager).await;
+
+    loop {
+        private.update(reasons.any()).await;
+
+        select! {
+            // A message we can't make sense of is skipped, rather than giving up on privacy
+            Some(signal) = sleeping.next() => {
+                reasons.asleep = match signal.args() {
+                    Ok(args) => args.start,
+                    Err(e) => {
+                        warn!("Unable to read PrepareForSleep: {}", e);
+                        continue;
+                    }
+                };
+                if reasons.asleep {
+                    // Done with it until we're back
+                    private.update(true).await;
+                    inhibitor = None;
+                } else if inhibitor.is_none() {
+                    inhibitor = inhibit(&manager).await;
+                }
+            }
+            Some(_) = locks.next() => reasons.locked = true,
+            Some(_) = unlocks.next() => reasons.locked = false,
+            Some(change) = locked_hint.next() => match change.get().await {
+                Ok(locked) => reasons.locked = locked,
+                Err(e) => warn!("Unable to read LockedHint: {}", e),
+            },
+            Some(change) = idle_hint.next() => match change.get().await {
+                Ok(idle) => reasons.idle = config.idle && idle,
+                Err(e) => warn!("Unable to read IdleHint: {}", e),
+            },
+            event = events.recv() => match event {
+                // The Mic may have come back after sleeping, so anything that failed can be done now
+                Ok(DeviceEvent::Connected) => {
+                    let wanted = private.active;
+                    private.active = !wanted;
+                    private.update(wanted).await;
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+            else => break,
+        }
+    }
+    Ok(())
+}
+
+// Our own session, or the user's graphical one when we're not running inside a session (such as
+// from a systemd user service)
+async fn find_session(connection: &Connection, manager: &ManagerProxy<'_>) -> Result<OwnedObjectPath> {
+    if let Ok(path) = manager.get_session("auto").await {
+        return Ok(path);
+    }
+
+    let uid = fs::metadata("/proc/self")?.uid();
+    let user = UserProxy::builder(connection).path(manager.get_user(uid).await?)?.build().await?;
+    let (id, path) = user.display().await?;
+    if id.is_empty() {
+        bail!("Unable to find a session to follow, is anyone logged in?");
+    }
+    Ok(path)
+}
+
+// Asks logind to wait for us before sleeping, which it does until the returned fd is closed
+async fn inhibit(manager: &ManagerProxy<'_>) -> Option<OwnedFd> {
+    match manager.inhibit("sleep", env!("CARGO_PKG_NAME"), "Muting the Mic", "delay").await {
+        Ok(fd) => Some(fd),
+        Err(e) => {
+            debug!("Unable to delay sleep, the Mic may not be muted in time: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use beacn_lib::messages::BeacnParameter;
+    use beacn_lib::messages::led::LEDParameter;
+    use beacn_lib::messages::schema::ParameterValue;
+    use crate::services::animation;
+    use super::*;
+
+    const BRIGHTNESS: BeacnParameter = BeacnParameter::LED(LEDParameter::Brightness);
+
+    fn private(client: &BeacnClient) -> Private {
+        let lighting = vec![(LEDParameter::Brightness, ParameterValue::Int(5))];
+        Private { client: client.clone(), animations: animation::start(client.clone()), lighting, active: false, muted: false }
+    }
+
+    #[test]
+    fn any_reason_is_enough() {
+        assert!(!Reasons::default().any());
+        assert!(Reasons { locked: true, ..Default::default() }.any());
+        assert!(Reasons { asleep: true, ..Default::default() }.any());
+        assert!(Reasons { idle: true, ..Default::default() }.any());
+        assert!(Reasons { locked: true, asleep: true, idle: true }.any());
+    }
+
+    #[tokio::test]
+    async fn mic_is_muted_and_put_back() {
+        let client = BeacnClient::connect_simulated();
+        client.set_value(BRIGHTNESS, ParameterValue::Int(40)).await.unwrap();
+        let mut private = private(&client);
+
+        private.update(true).await;
+        assert!(private.active);
+        assert!(client.get_muted().await.unwrap());
+        assert_eq!(client.get_value(BRIGHTNESS).await.unwrap(), ParameterValue::Int(5));
+
+        // Only changes are acted on, so unmuting by hand while locked isn't undone
+        client.set_muted(false).await.unwrap();
+        private.update(true).await;
+        assert!(!client.get_muted().await.unwrap());
+
+        client.set_muted(true).await.unwrap();
+        private.update(false).await;
+        assert!(!private.active);
+        assert!(!client.get_muted().await.unwrap());
+        assert_eq!(client.get_value(BRIGHTNESS).await.unwrap(), ParameterValue::Int(40));
+    }
+
+    #[tokio::test]
+    async fn already_muted_mic_stays_muted() {
+        let client = BeacnClient::connect_simulated();
+        client.set_muted(true).await.unwrap();
+        let mut private = private(&client);
+
+        private.update(true).await;
+        private.update(false).await;
+        assert!(!private.active);
+        assert!(client.get_muted().await.unwrap());
+    }
+}
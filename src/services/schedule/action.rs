@@ -0,0 +1,257 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone, Weekday};
+use serde::{Deserialize, Serialize};
+use beacn_lib::profile::Profile;
+use crate::config::LightingOverlay;
+
+pub const WEEK: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+const TIME_FORMAT: &str = "%H:%M";
+
+// Every action comes round at least once a week, so there's never any need to look further
+const WEEK_DAYS: u64 = 7;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Action {
+    pub time: NaiveTime,
+
+    // Every day if this is empty
+    pub days: Vec<Weekday>,
+
+    // The profile is applied first, so the lighting can adjust it
+    pub profile: Option<String>,
+    pub lighting: LightingOverlay,
+}
+
+pub fn parse_time(text: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(text.trim(), TIME_FORMAT).map_err(|_| anyhow!("'{}' is not a time, expected HH:MM", text))
+}
+
+impl Action {
+    pub fn validate(&self) -> Result<()> {
+        if self.profile.is_none() && self.lighting.is_empty() {
+            bail!("Scheduled actions need a profile or some lighting to apply");
+        }
+        if let Some(profile) = &self.profile {
+            Profile::path_for(profile)?;
+        }
+        self.lighting.values()?;
+        Ok(())
+    }
+
+    // When the action happens on the given date, if it does. A time which is skipped by the clocks
+    // going forward doesn't happen that day. This is always Local outside of the tests.
+    fn on<Tz: TimeZone>(&self, date: NaiveDate, zone: &Tz) -> Option<DateTime<Tz>> {
+        if !self.days.is_empty() && !self.days.contains(&date.weekday()) {
+            return None;
+        }
+        date.and_time(self.time).and_local_timezone(zone.clone()).earliest()
+    }
+
+    pub fn next_after<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let today = now.date_naive();
+        (0..=WEEK_DAYS).filter_map(|days| self.on(today + Days::new(days), &now.timezone())).find(|time| *time > now)
+    }
+
+    pub fn last_before<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let today = now.date_naive();
+        (0..=WEEK_DAYS).filter_map(|days| self.on(today - Days::new(days), &now.timezone())).find(|time| *time <= now)
+    }
+
+    pub fn days_label(&self) -> String {
+        if self.days.is_empty() || WEEK.iter().all(|day| self.days.contains(day)) {
+            return String::from("Every day");
+        }
+        let days: Vec<String> = WEEK.iter().filter(|day| self.days.contains(day)).map(ToString::to_string).collect();
+        days.join(", ")
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let lighting = &self.lighting;
+        let parts = [
+            self.profile.as_ref().map(|profile| format!("profile '{}'", profile)),
+            lighting.mode.as_ref().map(|mode| format!("mode {}", mode)),
+            lighting.colour1.as_ref().map(|colour| format!("colour1 {}", colour)),
+            lighting.colour2.as_ref().map(|colour| format!("colour2 {}", colour)),
+            lighting.brightness.map(|brightness| format!("brightness {}", brightness)),
+        ];
+        let parts: Vec<String> = parts.into_iter().flatten().collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+// The actions, and when we last checked for any being due. That's kept so that after a restart
+// only what was actually missed is caught up, rather than undoing changes made since.
+#[derive(Debug, Default)]
+pub struct Schedule {
+    pub actions: Vec<Action>,
+    pub last_run: Option<DateTime<Local>>,
+}
+
+// How the schedule is stored in schedule.toml, eg.
+//
+//   [[action]]
+//   time = "19:00"
+//   days = ["mon", "tue", "wed", "thu", "fri"]
+//   profile = "evening"
+//
+//   [[action]]
+//   time = "09:00"
+//   lighting = { colour1 = "#ffffff", brightness = 100 }
+#[derive(Serialize, Deserialize)]
+struct StoredAction {
+    time: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    days: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    #[serde(default, skip_serializing_if = "LightingOverlay::is_empty")]
+    lighting: LightingOverlay,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ScheduleFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_run: Option<DateTime<Local>>,
+    #[serde(default)]
+    action: Vec<StoredAction>,
+}
+
+impl Schedule {
+    // Kept apart from config.toml, as changing the schedule from the UI rewrites it
+    pub fn path() -> Result<PathBuf> {
+        Ok(beacn_lib::config_directory()?.join("schedule.toml"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = fs::read_to_string(&path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let file: ScheduleFile = toml::from_str(&text).with_context(|| format!("Unable to parse {}", path.display()))?;
+        let actions = file.action.into_iter().map(|stored| {
+            let days = stored.days.iter().map(|day| day.parse().map_err(|_| anyhow!("'{}' is not a day", day))).collect::<Result<_>>()?;
+            let action = Action { time: parse_time(&stored.time)?, days, profile: stored.profile, lighting: stored.lighting };
+            action.validate()?;
+            Ok(action)
+        }).collect::<Result<_>>().with_context(|| format!("Invalid action in {}", path.display()))?;
+
+        Ok(Self { actions, last_run: file.last_run })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let action = self.actions.iter().map(|action| StoredAction {
+            time: action.time.format(TIME_FORMAT).to_string(),
+            days: action.days.iter().map(|day| day.to_string().to_lowercase()).collect(),
+            profile: action.profile.clone(),
+            lighting: action.lighting.clone(),
+        }).collect();
+        let file = ScheduleFile { last_run: self.last_run, action };
+        fs::write(&path, toml::to_string(&file)?).with_context(|| format!("Unable to write {}", path.display()))
+    }
+
+    // Everything which should have happened since we last looked, in the order it would have. After
+    // a long suspend an action may have been missed several times, but running it once gives the
+    // same result. If we've never looked, the last week is caught up so the Mic starts off as the
+    // schedule says it should be.
+    pub fn take_due<Tz: TimeZone>(&mut self, now: DateTime<Tz>) -> Vec<Action> {
+        let week_ago = now.clone() - TimeDelta::days(WEEK_DAYS as i64);
+        let since = self.last_run.map_or(week_ago.to_utc(), |last_run| last_run.to_utc().max(week_ago.to_utc()));
+
+        let mut due: Vec<(DateTime<Tz>, &Action)> = self.actions.iter().filter_map(|action| {
+            let time = action.last_before(now.clone())?;
+            (time > since).then_some((time, action))
+        }).collect();
+        due.sort_by_key(|(time, _)| time.to_utc());
+
+        let due = due.into_iter().map(|(_, action)| action.clone()).collect();
+        self.last_run = Some(now.with_timezone(&Local));
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Europe::London;
+    use chrono_tz::Tz;
+    use super::*;
+
+    // Actions are told apart by their brightness
+    fn action(time: &str, days: &[Weekday], brightness: i32) -> Action {
+        let lighting = LightingOverlay { brightness: Some(brightness), ..Default::default() };
+        Action { time: parse_time(time).unwrap(), days: days.to_vec(), profile: None, lighting }
+    }
+
+    fn at(day: u32, month: u32, time: &str) -> DateTime<Tz> {
+        let time = parse_time(time).unwrap();
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap().and_time(time).and_local_timezone(London).earliest().unwrap()
+    }
+
+    fn due(schedule: &mut Schedule, now: DateTime<Tz>) -> Vec<i32> {
+        schedule.take_due(now).iter().map(|action| action.lighting.brightness.unwrap()).collect()
+    }
+
+    fn schedule(actions: Vec<Action>, last_run: Option<DateTime<Tz>>) -> Schedule {
+        Schedule { actions, last_run: last_run.map(|time| time.with_timezone(&Local)) }
+    }
+
+    #[test]
+    fn catches_up_once_in_order() {
+        // Asleep from Monday morning to Wednesday morning (12th to 14th)
+        let actions = vec![action("08:00", &[], 1), action("20:00", &[], 2), action("12:00", &[Weekday::Mon], 3)];
+        let mut schedule = schedule(actions, Some(at(12, 10, "07:00")));
+        assert_eq!(due(&mut schedule, at(14, 10, "09:00")), vec![3, 2, 1]);
+
+        assert_eq!(due(&mut schedule, at(14, 10, "09:00")), Vec::<i32>::new());
+        assert_eq!(due(&mut schedule, at(14, 10, "19:59")), Vec::<i32>::new());
+        assert_eq!(due(&mut schedule, at(14, 10, "20:00")), vec![2]);
+    }
+
+    #[test]
+    fn looks_back_a_week_at_most() {
+        let actions = || vec![action("08:00", &[], 1), action("09:30", &[Weekday::Wed], 2)];
+
+        // Never run, or not for a month, gives the same as a week (so last Wednesday's 09:30)
+        for last_run in [None, Some(at(14, 9, "09:00"))] {
+            let mut schedule = schedule(actions(), last_run);
+            assert_eq!(due(&mut schedule, at(14, 10, "09:00")), vec![2, 1]);
+        }
+    }
+
+    #[test]
+    fn skips_times_the_clocks_jump_over() {
+        // 01:30 doesn't happen on the 29th of March
+        let mut schedule = schedule(vec![action("01:30", &[], 1)], Some(at(28, 3, "23:00")));
+        assert_eq!(due(&mut schedule, at(29, 3, "09:00")), Vec::<i32>::new());
+        assert_eq!(schedule.actions[0].next_after(at(28, 3, "23:00")), Some(at(30, 3, "01:30")));
+        assert_eq!(due(&mut schedule, at(30, 3, "01:30")), vec![1]);
+    }
+
+    #[test]
+    fn runs_once_when_the_clocks_go_back() {
+        // 01:30 happens twice on the 25th of October, the first one counts
+        let second = at(25, 10, "01:45") + TimeDelta::hours(1);
+        assert_eq!(second.format("%H:%M").to_string(), "01:45");
+
+        let mut first = schedule(vec![action("01:30", &[], 1)], Some(at(25, 10, "00:00")));
+        assert_eq!(due(&mut first, at(25, 10, "01:45")), vec![1]);
+        assert_eq!(due(&mut first, second), Vec::<i32>::new());
+
+        // Catching up over the second 01:30 still only has it once
+        let mut both = schedule(vec![action("01:30", &[], 1)], Some(at(25, 10, "00:00")));
+        assert_eq!(due(&mut both, second), vec![1]);
+        assert_eq!(both.actions[0].last_before(second), Some(at(25, 10, "01:30")));
+    }
+}
@@ -0,0 +1,101 @@
+// Applies profiles and lighting changes at set times of day, such as dimming the ring in the
+// evening. Anything missed while the machine was off or asleep is caught up when we're back.
+
+mod action;
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use tokio::time::{self, MissedTickBehavior};
+use beacn_lib::client::BeacnClient;
+use beacn_lib::profile::Profile;
+use crate::services::animation::AnimationHandle;
+
+pub use action::{parse_time, Action, Schedule, WEEK};
+
+// Timers don't count time spent suspended, so rather than sleeping until the next action we keep
+// checking the clock. This also copes with the clock being changed.
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+// Shared between the service and the UI, so actions can be added and removed from there
+#[derive(Clone, Default)]
+pub struct ScheduleHandle {
+    schedule: Arc<Mutex<Schedule>>,
+}
+
+impl ScheduleHandle {
+    fn new() -> Self {
+        let schedule = Schedule::load().unwrap_or_else(|e| {
+            warn!("Unable to load the schedule: {:#}", e);
+            Schedule::default()
+        });
+        Self { schedule: Arc::new(Mutex::new(schedule)) }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Schedule> {
+        self.schedule.lock().expect("Schedule lock poisoned")
+    }
+
+    // Every action with when it next happens, soonest first
+    pub fn upcoming(&self, now: DateTime<Local>) -> Vec<(DateTime<Local>, Action)> {
+        let mut upcoming: Vec<_> = self.lock().actions.iter().filter_map(|action| Some((action.next_after(now)?, action.clone()))).collect();
+        upcoming.sort_by_key(|(time, _)| *time);
+        upcoming
+    }
+
+    pub fn add(&self, action: Action) -> Result<()> {
+        action.validate()?;
+        let mut schedule = self.lock();
+        schedule.actions.push(action);
+        schedule.save()
+    }
+
+    pub fn remove(&self, action: &Action) -> Result<()> {
+        let mut schedule = self.lock();
+        schedule.actions.retain(|existing| existing != action);
+        schedule.save()
+    }
+
+    // Only saved when something runs, the time we last checked doesn't matter otherwise
+    fn take_due(&self) -> Vec<Action> {
+        let mut schedule = self.lock();
+        let due = schedule.take_due(Local::now());
+        if !due.is_empty() {
+            if let Err(e) = schedule.save() {
+                warn!("Unable to save the schedule: {:#}", e);
+            }
+        }
+        due
+    }
+}
+
+pub fn start(client: BeacnClient, animations: AnimationHandle) -> ScheduleHandle {
+    let handle = ScheduleHandle::new();
+    super::spawn("Schedule", run(client, animations, handle.clone()));
+    handle
+}
+
+async fn run(client: BeacnClient, animations: AnimationHandle, handle: ScheduleHandle) -> Result<()> {
+    let mut check = time::interval(CHECK_INTERVAL);
+    check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        check.tick().await;
+        for action in handle.take_due() {
+            info!("Running scheduled action: {}", action);
+            if let Err(e) = apply(&client, &animations, &action).await {
+                warn!("Unable to run scheduled action '{}': {:#}", action, e);
+            }
+        }
+    }
+}
+
+// Lighting goes underneath anything else on the ring (such as OBS), so it shows once that's done
+async fn apply(client: &BeacnClient, animations: &AnimationHandle, action: &Action) -> Result<()> {
+    if let Some(profile) = &action.profile {
+        client.apply_profile(&Profile::load_named(profile)?).await?;
+    }
+    animations.set(action.lighting.values()?).await
+}
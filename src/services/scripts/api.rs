@@ -0,0 +1,216 @@
+// The functions scripts can call. Values are the same as the HTTP API uses: numbers and booleans
+// where possible, with enums as their names and colours as "#rrggbb".
+//
+//   get("led.mode")                    set("led.colour1", "#ff8800")
+//   state()                            A map of everything, eg. state().led.brightness
+//   muted()   set_muted(true)   toggle_mute()
+//   apply_profile("evening")
+//   flash("#ff0000", 3)                animate(#{ type: "breathe", colour: "#0000ff" })
+//   stop_animation()
+//   on("mute", |muted| ...)            Also "change" (name, value, for all but the mute), "meter"
+//                                      (levels), "profile" (name, or () if it wasn't loaded by
+//                                      name), "connect" (at the start, and when the Mic is plugged
+//                                      back in) and "disconnect"
+//   every(1000, || ...)  after(500, || ...)  cancel(timer)
+//   log("text")                        print() works too
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use log::info;
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Map, INT};
+use serde_json::Value;
+use beacn_lib::blocking::BlockingClient;
+use beacn_lib::messages::BeacnParameter;
+use beacn_lib::messages::schema::ParameterValue;
+use beacn_lib::profile::Profile;
+use crate::services::animation::{Animation, AnimationHandle};
+use crate::services::json;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// Stops a runaway loop in one script from freezing all of them
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// The shortest interval for every(), anything faster would keep the scripts thread permanently busy
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+// Far longer than the daemon will be running, and keeps deadlines well clear of overflowing
+const MAX_DELAY: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+pub const HOOKS: &[&str] = &["mute", "change", "meter", "profile", "connect", "disconnect"];
+
+pub struct Timer {
+    pub id: INT,
+    pub due: Instant,
+    pub repeat: Option<Duration>,
+    pub callback: FnPtr,
+}
+
+// What a script has asked to be called for, shared between its functions and the runtime
+#[derive(Default)]
+pub struct Handlers {
+    pub hooks: Vec<(String, FnPtr)>,
+    pub timers: Vec<Timer>,
+    next_timer: INT,
+}
+
+#[derive(Clone, Default)]
+pub struct SharedHandlers(Arc<Mutex<Handlers>>);
+
+impl SharedHandlers {
+    pub fn lock(&self) -> MutexGuard<'_, Handlers> {
+        self.0.lock().expect("Script handlers lock poisoned")
+    }
+
+    pub fn hooks(&self, hook: &str) -> Vec<FnPtr> {
+        self.lock().hooks.iter().filter(|(name, _)| name == hook).map(|(_, callback)| callback.clone()).collect()
+    }
+
+    fn add_timer(&self, delay: INT, repeat: bool, callback: FnPtr) -> ScriptResult<INT> {
+        let delay = Duration::from_millis(u64::try_from(delay).map_err(|_| "Timer delay can't be negative")?);
+        if repeat && delay < MIN_INTERVAL {
+            return Err(format!("Timers can't repeat more often than every {}ms", MIN_INTERVAL.as_millis()).into());
+        }
+
+        let due = Instant::now().checked_add(delay).filter(|_| delay <= MAX_DELAY);
+        let due = due.ok_or_else(|| format!("Timers can't be more than {}ms away", MAX_DELAY.as_millis()))?;
+
+        let mut handlers = self.lock();
+        handlers.next_timer += 1;
+        let id = handlers.next_timer;
+        handlers.timers.push(Timer { id, due, repeat: repeat.then_some(delay), callback });
+        Ok(id)
+    }
+}
+
+fn error(error: anyhow::Error) -> Box<EvalAltResult> {
+    format!("{:#}", error).into()
+}
+
+fn find_parameter(name: &str) -> ScriptResult<BeacnParameter> {
+    BeacnParameter::from_name(name).ok_or_else(|| format!("Unknown parameter '{}'", name).into())
+}
+
+pub fn to_dynamic(value: Value) -> Dynamic {
+    rhai::serde::to_dynamic(value).unwrap_or(Dynamic::UNIT)
+}
+
+pub fn value_to_dynamic(param: BeacnParameter, value: ParameterValue) -> Dynamic {
+    to_dynamic(json::to_json(&param.schema(), value))
+}
+
+pub fn engine(name: &str, client: Arc<BlockingClient>, animations: AnimationHandle, handlers: SharedHandlers) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let script = name.to_string();
+    engine.on_print(move |text| info!("[{}] {}", script, text));
+    let script = name.to_string();
+    engine.register_fn("log", move |text: &str| info!("[{}] {}", script, text));
+
+    let device = client.clone();
+    engine.register_fn("get", move |name: &str| -> ScriptResult<Dynamic> {
+        let param = find_parameter(name)?;
+        Ok(value_to_dynamic(param, device.get_value(param).map_err(error)?))
+    });
+
+    let device = client.clone();
+    engine.register_fn("set", move |name: &str, value: Dynamic| -> ScriptResult<Dynamic> {
+        let param = find_parameter(name)?;
+        let schema = param.schema();
+        if schema.read_only {
+            return Err(format!("{} is read only", schema.name).into());
+        }
+
+        let value: Value = rhai::serde::from_dynamic(&value)?;
+        let value = json::from_json(&schema, &value).map_err(error)?;
+        Ok(value_to_dynamic(param, device.set_value(param, value).map_err(error)?))
+    });
+
+    let device = client.clone();
+    engine.register_fn("state", move || -> ScriptResult<Map> {
+        let mut state = Map::new();
+        for param in BeacnParameter::all() {
+            let schema = param.schema();
+            if schema.read_only {
+                continue;
+            }
+
+            let value = value_to_dynamic(param, device.get_value(param).map_err(error)?);
+            let (group, name) = schema.name.split_once('.').unwrap_or(("", schema.name));
+            let group = state.entry(group.into()).or_insert_with(|| Dynamic::from_map(Map::new()));
+            if let Some(mut group) = group.write_lock::<Map>() {
+                group.insert(name.into(), value);
+            }
+        }
+        Ok(state)
+    });
+
+    let device = client.clone();
+    engine.register_fn("muted", move || device.get_muted().map_err(error));
+    let device = client.clone();
+    engine.register_fn("set_muted", move |muted: bool| device.set_muted(muted).map_err(error));
+    let device = client.clone();
+    engine.register_fn("toggle_mute", move || device.toggle_mute().map_err(error));
+
+    let device = client;
+    engine.register_fn("apply_profile", move |name: &str| -> ScriptResult<()> {
+        let profile = Profile::load_named(name).map_err(error)?;
+        device.apply_profile(&profile).map_err(error)
+    });
+
+    let player = animations.clone();
+    engine.register_fn("flash", move |colour: &str, count: INT| -> ScriptResult<()> {
+        let count = u32::try_from(count).map_err(|_| "Flash count can't be negative")?;
+        player.flash(colour, count).map_err(error)
+    });
+    let player = animations.clone();
+    engine.register_fn("animate", move |animation: Map| -> ScriptResult<()> {
+        let animation: Animation = rhai::serde::from_dynamic(&Dynamic::from_map(animation))?;
+        player.play(animation).map_err(error)
+    });
+    engine.register_fn("stop_animation", move || animations.stop().map_err(error));
+
+    let shared = handlers.clone();
+    engine.register_fn("on", move |hook: &str, callback: FnPtr| -> ScriptResult<()> {
+        if !HOOKS.contains(&hook) {
+            return Err(format!("Unknown event '{}', expected one of {}", hook, HOOKS.join(", ")).into());
+        }
+        shared.lock().hooks.push((hook.to_string(), callback));
+        Ok(())
+    });
+
+    let shared = handlers.clone();
+    engine.register_fn("every", move |interval: INT, callback: FnPtr| shared.add_timer(interval, true, callback));
+    let shared = handlers.clone();
+    engine.register_fn("after", move |delay: INT, callback: FnPtr| shared.add_timer(delay, false, callback));
+    engine.register_fn("cancel", move |id: INT| handlers.lock().timers.retain(|timer| timer.id != id));
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn callback() -> FnPtr {
+        FnPtr::new("tick").unwrap()
+    }
+
+    #[test]
+    fn timers_reject_delays_too_long_to_schedule() {
+        let handlers = SharedHandlers::default();
+        assert!(handlers.add_timer(INT::MAX, false, callback()).is_err());
+        assert!(handlers.add_timer(INT::MAX, true, callback()).is_err());
+        assert!(handlers.lock().timers.is_empty());
+    }
+
+    #[test]
+    fn timers_reject_negative_and_fast_repeats() {
+        let handlers = SharedHandlers::default();
+        assert!(handlers.add_timer(-1, false, callback()).is_err());
+        assert!(handlers.add_timer(1, true, callback()).is_err());
+        assert_eq!(handlers.add_timer(0, false, callback()).unwrap(), 1);
+        assert_eq!(handlers.add_timer(1000, true, callback()).unwrap(), 2);
+    }
+}
@@ -0,0 +1,251 @@
+// Runs the user's Rhai scripts (*.rhai in the scripts directory, next to the profiles), for
+// behaviours which don't belong in the app itself. For example, dimming the ring while muted:
+//
+//   let brightness = get("led.brightness");
+//   on("mute", |muted| set("led.brightness", if muted { 10 } else { brightness }));
+//
+// See api.rs for everything scripts can call. Handlers should be registered when the script first
+// runs, as that's when we work out whether anything wants meter levels. The scripts share a thread,
+// so a slow handler holds up all of them.
+
+mod api;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Instant;
+use anyhow::{anyhow, bail, Result};
+use log::{debug, info, warn};
+use rhai::{Dynamic, Engine, FnPtr, AST};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::oneshot;
+use beacn_lib::blocking::BlockingClient;
+use beacn_lib::client::BeacnClient;
+use beacn_lib::device::DEFAULT_TIMEOUT;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::meter::MeterLevels;
+use crate::services::animation::AnimationHandle;
+use crate::services::json;
+use crate::services::scripts::api::SharedHandlers;
+
+const SCRIPT_EXTENSION: &str = "rhai";
+
+enum ScriptEvent {
+    Device(DeviceEvent),
+    Meters(MeterLevels),
+}
+
+pub fn directory() -> Result<PathBuf> {
+    Ok(beacn_lib::config_directory()?.join("scripts"))
+}
+
+pub async fn run(client: BeacnClient, animations: AnimationHandle) -> Result<()> {
+    let directory = directory()?;
+    let mut events = client.subscribe();
+
+    // Scripts are synchronous, so they get their own thread and talk to the device through a
+    // blocking client. It reports back once they're loaded.
+    let (sender, receiver) = mpsc::channel();
+    let (loaded_sender, loaded) = oneshot::channel();
+    let blocking = Arc::new(client.blocking(DEFAULT_TIMEOUT));
+    let meter_pending = Arc::new(AtomicBool::new(false));
+    let pending = meter_pending.clone();
+    let path = directory.clone();
+    thread::spawn(move || {
+        let scripts = load_all(&path, blocking, &animations);
+        let wants_meters = scripts.iter().any(|script| !script.handlers.hooks("meter").is_empty());
+        let _ = loaded_sender.send((scripts.len(), wants_meters));
+        run_scripts(scripts, receiver, pending);
+    });
+
+    let (count, wants_meters) = loaded.await?;
+    if count == 0 {
+        debug!("No scripts found in {}", directory.display());
+        return Ok(());
+    }
+
+    let mut meters = wants_meters.then(|| client.subscribe_meters());
+    loop {
+        let event = select! {
+            event = events.recv() => match event {
+                Ok(event) => ScriptEvent::Device(event),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+            levels = async { Some(meters.as_mut()?.recv().await) }, if meters.is_some() => match levels {
+                // Levels arrive faster than most scripts want them, so if the last lot hasn't been
+                // handled yet these are dropped rather than queued up behind it
+                Some(Ok(_)) if meter_pending.load(Ordering::Acquire) => continue,
+                Some(Ok(levels)) => {
+                    meter_pending.store(true, Ordering::Release);
+                    ScriptEvent::Meters(levels)
+                }
+                Some(Err(RecvError::Closed)) => {
+                    meters = None;
+                    continue;
+                }
+                _ => continue,
+            },
+        };
+
+        if sender.send(event).is_err() {
+            bail!("Scripts have stopped");
+        }
+    }
+    Ok(())
+}
+
+fn load_all(directory: &Path, client: Arc<BlockingClient>, animations: &AnimationHandle) -> Vec<Script> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory).into_iter().flatten().filter_map(|entry| {
+        let path = entry.ok()?.path();
+        (path.extension()? == SCRIPT_EXTENSION).then_some(path)
+    }).collect();
+    paths.sort();
+
+    paths.iter().filter_map(|path| match Script::load(path, client.clone(), animations.clone()) {
+        Ok(script) => {
+            info!("Loaded script {}", script.name);
+            Some(script)
+        }
+        Err(e) => {
+            warn!("Unable to load script {}: {:#}", path.display(), e);
+            None
+        }
+    }).collect()
+}
+
+fn run_scripts(scripts: Vec<Script>, events: mpsc::Receiver<ScriptEvent>, meter_pending: Arc<AtomicBool>) {
+    if scripts.is_empty() {
+        return;
+    }
+
+    for script in &scripts {
+        script.hook("connect", vec![]);
+    }
+
+    loop {
+        let next_timer = scripts.iter().filter_map(Script::next_timer).min();
+        let event = match next_timer {
+            Some(due) => match events.recv_timeout(due.saturating_duration_since(Instant::now())) {
+                Ok(event) => Some(event),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            },
+            None => match events.recv() {
+                Ok(event) => Some(event),
+                Err(_) => break,
+            },
+        };
+
+        match event {
+            Some(ScriptEvent::Device(DeviceEvent::ParameterChanged(param, value))) => {
+                let value = param.schema().decode(value);
+                let args = vec![Dynamic::from(param.schema().name.to_string()), api::value_to_dynamic(param, value)];
+                for script in &scripts {
+                    script.hook("change", args.clone());
+                }
+            }
+            Some(ScriptEvent::Device(DeviceEvent::MuteChanged(value))) => {
+                for script in &scripts {
+                    script.hook("mute", vec![Dynamic::from(value)]);
+                }
+            }
+            Some(ScriptEvent::Device(DeviceEvent::ProfileApplied(name))) => {
+                let name = name.map_or(Dynamic::UNIT, Dynamic::from);
+                for script in &scripts {
+                    script.hook("profile", vec![name.clone()]);
+                }
+            }
+            Some(ScriptEvent::Device(DeviceEvent::Connected)) => {
+                for script in &scripts {
+                    script.hook("connect", vec![]);
+                }
+            }
+            Some(ScriptEvent::Device(DeviceEvent::Disconnected)) => {
+                for script in &scripts {
+                    script.hook("disconnect", vec![]);
+                }
+            }
+            Some(ScriptEvent::Meters(levels)) => {
+                meter_pending.store(false, Ordering::Release);
+                let levels = api::to_dynamic(json::meters(&levels));
+                for script in &scripts {
+                    script.hook("meter", vec![levels.clone()]);
+                }
+            }
+            None => {}
+        }
+
+        let now = Instant::now();
+        for script in &scripts {
+            script.run_timers(now);
+        }
+    }
+}
+
+struct Script {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    handlers: SharedHandlers,
+}
+
+impl Script {
+    // Runs the script's top level, which is where it registers its handlers
+    fn load(path: &Path, client: Arc<BlockingClient>, animations: AnimationHandle) -> Result<Self> {
+        let name = path.file_stem().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let handlers = SharedHandlers::default();
+        let engine = api::engine(&name, client, animations, handlers.clone());
+
+        let ast = engine.compile_file(path.to_path_buf()).map_err(|e| anyhow!("{}", e))?;
+        engine.run_ast(&ast).map_err(|e| anyhow!("{}", e))?;
+        Ok(Self { name, engine, ast, handlers })
+    }
+
+    fn call(&self, callback: &FnPtr, args: Vec<Dynamic>) {
+        if let Err(e) = callback.call::<Dynamic>(&self.engine, &self.ast, args) {
+            warn!("Error in script {}: {}", self.name, e);
+        }
+    }
+
+    fn hook(&self, hook: &str, args: Vec<Dynamic>) {
+        for callback in self.handlers.hooks(hook) {
+            self.call(&callback, args.clone());
+        }
+    }
+
+    fn next_timer(&self) -> Option<Instant> {
+        self.handlers.lock().timers.iter().map(|timer| timer.due).min()
+    }
+
+    fn run_timers(&self, now: Instant) {
+        // The lock is let go before calling anything, as the callbacks can add and cancel timers
+        let mut due = vec![];
+        self.handlers.lock().timers.retain_mut(|timer| {
+            if timer.due > now {
+                return true;
+            }
+            due.push(timer.callback.clone());
+
+            match timer.repeat {
+                // If we've fallen behind, skip the missed runs rather than trying to catch up. One
+                // too far off to work out would never come round again anyway.
+                Some(interval) => match timer.due.checked_add(interval) {
+                    Some(next) => {
+                        timer.due = next.max(now);
+                        true
+                    }
+                    None => false,
+                },
+                None => false,
+            }
+        });
+
+        for callback in due {
+            self.call(&callback, vec![]);
+        }
+    }
+}
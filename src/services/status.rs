@@ -0,0 +1,312 @@
+// Uses the ring as a status light, by reading a number every so often and picking the primary
+// colour (and optionally brightness) for it from a gradient, eg.
+//
+//   [status]
+//   enabled = true
+//   source = "temperature"
+//   hysteresis = 2
+//
+//   [[status.stop]]
+//   value = 45
+//   colour = "#00ff00"
+//   brightness = 30
+//
+//   [[status.stop]]
+//   value = 85
+//   colour = "#ff0000"
+//   brightness = 100
+//
+// The sources are:
+//
+//   temperature   A thermal zone from /sys/class/thermal, in °C
+//   load          The one minute load average
+//   file          The first number in a file (path), eg. something from /sys
+//   command       The first number a command (run with 'sh -c') prints
+//
+// Only the colour is changed, so this works best with the ring in solid mode.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, warn};
+use tokio::process::Command;
+use tokio::time::{self, MissedTickBehavior};
+use beacn_lib::messages::RGB;
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::ParameterValue;
+use crate::config::{StatusConfig, StatusSource};
+use crate::services::animation::{blend, parse_colour, AnimationHandle};
+
+const THERMAL_ZONES: &str = "/sys/class/thermal";
+const LOAD_AVERAGE: &str = "/proc/loadavg";
+
+enum Source {
+    Temperature(Option<String>),
+    Load,
+    File(PathBuf),
+    Command(String),
+}
+
+impl Source {
+    fn from_config(config: &StatusConfig) -> Result<Self> {
+        let source = match config.source {
+            StatusSource::Temperature => Source::Temperature(config.zone.clone()),
+            StatusSource::Load => Source::Load,
+            StatusSource::File => Source::File(config.path.clone().ok_or_else(|| anyhow!("status.path must be set for the file source"))?),
+            StatusSource::Command => Source::Command(config.command.clone().ok_or_else(|| anyhow!("status.command must be set for the command source"))?),
+        };
+        Ok(source)
+    }
+
+    async fn read(&self, timeout: Duration) -> Result<f64> {
+        match self {
+            Source::Temperature(zone) => temperature(zone.as_deref()),
+            Source::Load => first_number(&fs::read_to_string(LOAD_AVERAGE)?),
+            Source::File(path) => {
+                let text = fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+                first_number(&text)
+            }
+            Source::Command(command) => {
+                // Giving up drops the child, which kills it
+                let output = Command::new("sh").arg("-c").arg(command).kill_on_drop(true).output();
+                let output = time::timeout(timeout, output).await.map_err(|_| anyhow!("Timed out after {}s", timeout.as_secs()))??;
+                if !output.status.success() {
+                    bail!("{}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim());
+                }
+                first_number(&String::from_utf8_lossy(&output.stdout))
+            }
+        }
+    }
+}
+
+fn first_number(text: &str) -> Result<f64> {
+    let word = text.split_whitespace().next().ok_or_else(|| anyhow!("Nothing to read"))?;
+    let number: f64 = word.parse().map_err(|_| anyhow!("'{}' is not a number", word))?;
+    if !number.is_finite() {
+        bail!("'{}' is not a usable reading", word);
+    }
+    Ok(number)
+}
+
+// Zones report in millidegrees, the hottest one is used unless a type was asked for
+fn temperature(zone: Option<&str>) -> Result<f64> {
+    let mut hottest: Option<f64> = None;
+    for entry in fs::read_dir(THERMAL_ZONES)?.flatten() {
+        let path = entry.path();
+        if !entry.file_name().to_string_lossy().starts_with("thermal_zone") {
+            continue;
+        }
+        if zone.is_none_or(|zone| read_trimmed(&path.join("type")).is_some_and(|kind| kind == zone)) {
+            let Some(reading) = read_trimmed(&path.join("temp")).and_then(|temp| temp.parse::<f64>().ok()).filter(|temp| temp.is_finite()) else {
+                continue;
+            };
+            let reading = reading / 1000.;
+            hottest = Some(hottest.map_or(reading, |hottest| hottest.max(reading)));
+        }
+    }
+
+    match zone {
+        Some(zone) => hottest.ok_or_else(|| anyhow!("No thermal zone of type '{}' in {}", zone, THERMAL_ZONES)),
+        None => hottest.ok_or_else(|| anyhow!("No thermal zones in {}", THERMAL_ZONES)),
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|text| text.trim().to_string())
+}
+
+struct Stop {
+    value: f64,
+    colour: RGB,
+    brightness: Option<i32>,
+}
+
+// The stops, in order of value
+struct Gradient(Vec<Stop>);
+
+impl Gradient {
+    fn from_config(config: &StatusConfig) -> Result<Self> {
+        if config.stops.is_empty() {
+            bail!("At least one [[status.stop]] is needed");
+        }
+
+        let mut stops = vec![];
+        for stop in &config.stops {
+            if let Some(brightness) = stop.brightness {
+                LEDParameter::Brightness.schema().encode(ParameterValue::Int(brightness))?;
+            }
+            stops.push(Stop { value: stop.value, colour: parse_colour(&stop.colour)?, brightness: stop.brightness });
+        }
+        stops.sort_by(|a, b| a.value.total_cmp(&b.value));
+        Ok(Self(stops))
+    }
+
+    fn colour(&self, value: f64) -> RGB {
+        let stops: Vec<_> = self.0.iter().map(|stop| (stop.value, stop.colour)).collect();
+        // Readings are checked before they get here, but the first stop is better than nothing
+        between(&stops, value).map_or(self.0[0].colour, |(from, to, fraction)| blend(from, to, fraction))
+    }
+
+    // Only stops with a brightness count, so it can be left out of some of them
+    fn brightness(&self, value: f64) -> Option<i32> {
+        let stops: Vec<_> = self.0.iter().filter_map(|stop| Some((stop.value, stop.brightness?))).collect();
+        let (from, to, fraction) = between(&stops, value)?;
+        Some((from as f32 + (to - from) as f32 * fraction).round() as i32)
+    }
+}
+
+// The stops either side of a value and how far it is between them, values beyond the ends get the
+// first or last stop
+fn between<T: Copy>(stops: &[(f64, T)], value: f64) -> Option<(T, T, f32)> {
+    let first = *stops.first()?;
+    let last = *stops.last()?;
+    if value <= first.0 {
+        return Some((first.1, first.1, 0.));
+    }
+    if value >= last.0 {
+        return Some((last.1, last.1, 0.));
+    }
+
+    let index = stops.iter().position(|stop| stop.0 > value)?;
+    let (low, high) = (stops[index - 1], stops[index]);
+    Some((low.1, high.1, ((value - low.0) / (high.0 - low.0)) as f32))
+}
+
+pub async fn run(animations: AnimationHandle, config: StatusConfig) -> Result<()> {
+    let source = Source::from_config(&config)?;
+    let gradient = Gradient::from_config(&config)?;
+
+    let interval = Duration::from_secs(config.interval.max(1));
+    let mut ticker = time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    // The reading the ring is showing, which only moves once a new one is far enough away
+    let mut shown: Option<f64> = None;
+    let mut failing = false;
+
+    loop {
+        ticker.tick().await;
+        let reading = match source.read(interval).await {
+            Ok(reading) => reading,
+            Err(e) => {
+                // Only worth saying once, until it's working again
+                if !failing {
+                    warn!("Unable to read status: {:#}", e);
+                }
+                failing = true;
+                continue;
+            }
+        };
+        failing = false;
+
+        if !has_moved(shown, reading, config.hysteresis) {
+            continue;
+        }
+        shown = Some(reading);
+        debug!("Status is now {}", reading);
+
+        if let Err(e) = show(&animations, &gradient, reading).await {
+            warn!("Unable to update the ring: {:#}", e);
+        }
+    }
+}
+
+fn has_moved(shown: Option<f64>, reading: f64, hysteresis: f64) -> bool {
+    shown.is_none_or(|shown| (reading - shown).abs() >= hysteresis)
+}
+
+// These go underneath anything else on the ring (such as OBS), and values which are already right
+// are left alone, so nothing is sent while the reading is steady
+async fn show(animations: &AnimationHandle, gradient: &Gradient, reading: f64) -> Result<()> {
+    let mut values = vec![(LEDParameter::Colour1, ParameterValue::Colour(gradient.colour(reading)))];
+    if let Some(brightness) = gradient.brightness(reading) {
+        values.push((LEDParameter::Brightness, ParameterValue::Int(brightness)));
+    }
+    animations.set(values).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::StatusStop;
+    use super::*;
+
+    fn gradient(stops: &[(f64, &str, Option<i32>)]) -> Gradient {
+        let stops = stops.iter().map(|(value, colour, brightness)| StatusStop { value: *value, colour: colour.to_string(), brightness: *brightness }).collect();
+        Gradient::from_config(&StatusConfig { stops, ..Default::default() }).unwrap()
+    }
+
+    #[test]
+    fn readings_beyond_the_ends_get_the_end_stops() {
+        // Given out of order, which doesn't matter
+        let gradient = gradient(&[(85., "#ff0000", Some(100)), (45., "#00ff00", Some(30))]);
+        assert_eq!(gradient.colour(20.), RGB::new(0, 255, 0));
+        assert_eq!(gradient.brightness(20.), Some(30));
+        assert_eq!(gradient.colour(45.), RGB::new(0, 255, 0));
+        assert_eq!(gradient.colour(100.), RGB::new(255, 0, 0));
+        assert_eq!(gradient.brightness(100.), Some(100));
+    }
+
+    #[test]
+    fn readings_between_stops_are_mixed() {
+        let gradient = gradient(&[(45., "#00ff00", Some(30)), (85., "#ff0000", Some(100)), (95., "#ffffff", Some(100))]);
+        assert_eq!(gradient.colour(65.), RGB::new(128, 128, 0));
+        assert_eq!(gradient.brightness(65.), Some(65));
+        assert_eq!(gradient.colour(75.), RGB::new(191, 64, 0));
+        assert_eq!(gradient.colour(90.), RGB::new(255, 128, 128));
+    }
+
+    #[test]
+    fn brightness_only_comes_from_stops_which_have_it() {
+        let some = gradient(&[(0., "#0000ff", Some(10)), (50., "#00ff00", None), (100., "#ff0000", Some(90))]);
+        assert_eq!(some.brightness(50.), Some(50));
+        assert_eq!(some.colour(50.), RGB::new(0, 255, 0));
+
+        let without = gradient(&[(0., "#0000ff", None), (100., "#ff0000", None)]);
+        assert_eq!(without.brightness(50.), None);
+    }
+
+    #[test]
+    fn single_stops_and_bad_stops() {
+        let gradient = gradient(&[(50., "#123456", None)]);
+        assert_eq!(gradient.colour(0.), RGB::new(0x12, 0x34, 0x56));
+        assert_eq!(gradient.colour(100.), RGB::new(0x12, 0x34, 0x56));
+
+        assert!(Gradient::from_config(&StatusConfig::default()).is_err());
+        let stop = StatusStop { value: 0., colour: String::from("#123456"), brightness: Some(101) };
+        assert!(Gradient::from_config(&StatusConfig { stops: vec![stop], ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn small_movements_leave_the_ring_alone() {
+        assert!(has_moved(None, 60., 2.));
+        assert!(!has_moved(Some(60.), 61.5, 2.));
+        assert!(!has_moved(Some(60.), 58.5, 2.));
+        assert!(has_moved(Some(60.), 62., 2.));
+        assert!(has_moved(Some(60.), 57.9, 2.));
+
+        // Without any, every change shows
+        assert!(has_moved(Some(60.), 60.1, 0.));
+    }
+
+    #[test]
+    fn reads_the_first_number() {
+        assert_eq!(first_number("0.52 0.58 0.59 1/611 12345\n").unwrap(), 0.52);
+        assert_eq!(first_number("  42  ").unwrap(), 42.);
+        assert!(first_number("").is_err());
+        assert!(first_number("hot").is_err());
+        assert!(first_number("NaN").is_err());
+        assert!(first_number("inf").is_err());
+    }
+
+    #[test]
+    fn sources_are_checked_when_parsed() {
+        let config: StatusConfig = toml::from_str("source = \"command\"\ncommand = \"echo 1\"").unwrap();
+        assert!(matches!(Source::from_config(&config).unwrap(), Source::Command(command) if command == "echo 1"));
+
+        let config: StatusConfig = toml::from_str("source = \"file\"").unwrap();
+        assert!(Source::from_config(&config).is_err());
+
+        assert!(toml::from_str::<StatusConfig>("source = \"tempreature\"").is_err());
+    }
+}
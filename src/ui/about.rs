@@ -0,0 +1,25 @@
+use egui::Context;
+use crate::ui::BeacnApp;
+
+impl BeacnApp {
+    pub(crate) fn draw_about_page(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("About Device");
+            ui.add_space(4.);
+
+            egui::Grid::new("about_grid").num_columns(2).striped(true).show(ui, |ui| {
+                for (label, value) in self.client.info().fields() {
+                    ui.label(label);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+            ui.add_space(8.);
+
+            if ui.button("Copy Details for Bug Report").clicked() {
+                let report = format!("{}\n{:#?}", self.client.info(), self.state);
+                ctx.copy_text(report);
+            }
+        });
+    }
+}
@@ -0,0 +1,58 @@
+use egui::{Context, Ui};
+use strum::IntoEnumIterator;
+use beacn_lib::messages::{BeacnValue, BeacnParameter, MessageValue};
+use beacn_lib::messages::headphones::{HeadphoneParameter, HeadphoneType};
+use beacn_lib::messages::Message::SET;
+use crate::ui::BeacnApp;
+
+impl BeacnApp {
+    fn draw_headphone_level(&mut self, ui: &mut Ui) {
+        ui.label("Headphone Level");
+        let schema = HeadphoneParameter::Level.schema();
+        let slider = egui::Slider::new(&mut self.state.headphones.level, schema.float_range()).suffix(schema.unit.unwrap_or_default());
+        if ui.add(slider).changed() {
+            let message = MessageValue::<f32>(self.state.headphones.level);
+            let message = SET((BeacnParameter::Headphones(HeadphoneParameter::Level), BeacnValue::from(message)));
+            self.send_message(message);
+        }
+        ui.add_space(4.);
+    }
+
+    fn draw_mic_monitor(&mut self, ui: &mut Ui) {
+        ui.label("Mic Monitoring");
+        let schema = HeadphoneParameter::MicMonitor.schema();
+        let slider = egui::Slider::new(&mut self.state.headphones.mic_monitor, schema.float_range()).suffix(schema.unit.unwrap_or_default());
+        if ui.add(slider).changed() {
+            let message = MessageValue::<f32>(self.state.headphones.mic_monitor);
+            let message = SET((BeacnParameter::Headphones(HeadphoneParameter::MicMonitor), BeacnValue::from(message)));
+            self.send_message(message);
+        }
+        ui.add_space(4.);
+    }
+
+    fn draw_headphone_type(&mut self, ui: &mut Ui) {
+        ui.label("Headphone Type");
+        ui.vertical(|ui| {
+            for headphone_type in HeadphoneType::iter() {
+                let value = headphone_type.value();
+                if ui.radio_value(&mut self.state.headphones.headphone_type, value, headphone_type.label()).changed() {
+                    let message = MessageValue::<u32>(value);
+                    let message = SET((BeacnParameter::Headphones(HeadphoneParameter::HeadphoneType), BeacnValue::from(message)));
+                    self.send_message(message);
+                }
+            }
+        });
+        ui.add_space(4.);
+    }
+
+    pub(crate) fn draw_headphones_page(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Headphones");
+            ui.add_space(4.);
+
+            self.draw_headphone_level(ui);
+            self.draw_mic_monitor(ui);
+            self.draw_headphone_type(ui);
+        });
+    }
+}
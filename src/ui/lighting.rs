@@ -0,0 +1,250 @@
+use egui::{Context, Ui};
+use strum::IntoEnumIterator;
+use beacn_lib::messages::{BeacnValue, BeacnParameter, MessageValue, RGB};
+use beacn_lib::messages::led::{LEDMeterSource, LEDParameter};
+use beacn_lib::messages::Message::SET;
+use crate::ui::BeacnApp;
+
+impl BeacnApp {
+    // These are some common elements used in multiple pages..
+    fn draw_primary_colour(&mut self, ui: &mut Ui) {
+        ui.label("Primary Colour");
+        if ui.color_edit_button_srgb(&mut self.colour1).changed() {
+            let message = MessageValue::<RGB>(RGB {
+                red: self.colour1[0],
+                green: self.colour1[1],
+                blue: self.colour1[2],
+                alpha: 0,
+            });
+            let message = SET((BeacnParameter::LED(LEDParameter::Colour1), BeacnValue::from(message)));
+            self.send_message(message);
+        }
+        ui.add_space(4.);
+    }
+
+    fn draw_secondary_colour(&mut self, ui: &mut Ui) {
+        ui.label("Secondary Colour");
+        if ui.color_edit_button_srgb(&mut self.colour2).changed() {
+            let message = MessageValue::<RGB>(RGB {
+                red: self.colour2[0],
+                green: self.colour2[1],
+                blue: self.colour2[2],
+                alpha: 0,
+            });
+            let message = SET((BeacnParameter::LED(LEDParameter::Colour2), BeacnValue::from(message)));
+            self.send_message(message);
+        }
+        ui.add_space(4.);
+    }
+
+    fn draw_speed_direction(&mut self, ui: &mut Ui) {
+        ui.label("Speed and Direction");
+        let range = LEDParameter::Speed.schema().int_range();
+        if ui.add(egui::Slider::new(&mut self.state.led.speed, range)).changed() {
+            let message = MessageValue::<i32>(self.state.led.speed);
+            let message = SET((BeacnParameter::LED(LEDParameter::Speed), BeacnValue::from(message)));
+            self.send_message(message);
+        };
+        ui.add_space(4.);
+    }
+
+    fn draw_meter_sensitivity(&mut self, ui: &mut Ui) {
+        ui.label("Meter Sensitivity");
+        let range = LEDParameter::MeterSensitivity.schema().float_range();
+        if ui.add(egui::Slider::new(&mut self.state.led.meter_sensitivity, range)).changed() {
+            let message = MessageValue::<f32>(self.state.led.meter_sensitivity);
+            let message = SET((BeacnParameter::LED(LEDParameter::MeterSensitivity), BeacnValue::from(message)));
+            self.send_message(message);
+        }
+        ui.add_space(4.);
+    }
+
+    fn draw_meter_source(&mut self, ui: &mut Ui) {
+        ui.label("Meter Source");
+        egui::ComboBox::from_label("")
+            .selected_text(
+                match LEDMeterSource::from_value(self.state.led.meter_source) {
+                    Some(source) => source.label(),
+                    None => "Unknown?",
+                }
+            )
+            .show_ui(ui, |ui| {
+                for source in LEDMeterSource::iter() {
+                    if ui.selectable_value(&mut self.state.led.meter_source, source.value(), source.label()).changed() {
+                        let message = MessageValue::<u32>(self.state.led.meter_source);
+                        let message = SET((BeacnParameter::LED(LEDParameter::MeterSource), BeacnValue::from(message)));
+                        self.send_message(message);
+                    }
+                }
+            });
+        ui.add_space(4.);
+    }
+
+    fn draw_ring_brightness(&mut self, ui: &mut Ui) {
+        ui.label("Ring Brightness");
+        let schema = LEDParameter::Brightness.schema();
+        let slider = egui::Slider::new(&mut self.state.led.brightness, schema.int_range()).suffix(schema.unit.unwrap_or_default());
+        if ui.add(slider).changed() {
+            let message = MessageValue::<i32>(self.state.led.brightness);
+            let message = SET((BeacnParameter::LED(LEDParameter::Brightness), BeacnValue::from(message)));
+            self.send_message(message);
+        }
+        ui.add_space(4.);
+    }
+
+    // This can be done better, there's no reason to duplicate code here between pages..
+    fn draw_gradient_settings(&mut self, ui: &mut Ui) {
+        self.draw_primary_colour(ui);
+        self.draw_secondary_colour(ui);
+        self.draw_speed_direction(ui);
+        self.draw_ring_brightness(ui);
+    }
+
+    fn draw_solid_settings(&mut self, ui: &mut Ui) {
+        self.draw_primary_colour(ui);
+        self.draw_ring_brightness(ui);
+    }
+
+    fn draw_reactive_settings(&mut self, ui: &mut Ui) {
+        ui.label("Behaviour");
+
+        ui.vertical(|ui| {
+            if ui.radio_value(&mut self.state.led.mode, 0x05, "Whole Ring Meter").changed() {
+                self.set_mode(0x05);
+            }
+            if ui.radio_value(&mut self.state.led.mode, 0x06, "Bar Meter Up").changed() {
+                self.set_mode(0x06);
+            }
+            if ui.radio_value(&mut self.state.led.mode, 0x07, "Bar Meter Down").changed() {
+                self.set_mode(0x07);
+            }
+        });
+        ui.add_space(4.);
+
+        self.draw_primary_colour(ui);
+        self.draw_secondary_colour(ui);
+        self.draw_meter_sensitivity(ui);
+        self.draw_ring_brightness(ui);
+        self.draw_meter_source(ui);
+    }
+
+    fn draw_sparkle_settings(&mut self, ui: &mut Ui) {
+        ui.label("Behaviour");
+
+        ui.vertical(|ui| {
+            if ui.radio_value(&mut self.state.led.mode, 0x0a, "Sparkle Random").changed() {
+                self.set_mode(0x0a);
+            }
+            if ui.radio_value(&mut self.state.led.mode, 0x0b, "Sparkle Meter").changed() {
+                self.set_mode(0x0b)
+            }
+        });
+        ui.add_space(4.);
+
+        self.draw_primary_colour(ui);
+        self.draw_secondary_colour(ui);
+        self.draw_meter_sensitivity(ui);
+        self.draw_speed_direction(ui);
+        self.draw_ring_brightness(ui);
+        self.draw_meter_source(ui);
+    }
+
+    fn draw_spectrum_settings(&mut self, ui: &mut Ui) {
+        self.draw_speed_direction(ui);
+        self.draw_ring_brightness(ui);
+    }
+
+    fn set_mode(&mut self, mode: u32) {
+        let value = MessageValue::<u32>(mode);
+        let message = SET((BeacnParameter::LED(LEDParameter::Mode), BeacnValue::from(value)));
+        self.send_message(message);
+    }
+
+    pub(crate) fn draw_lighting_page(&mut self, ctx: &Context) {
+        // Ok, the panel order is important, as they define how they are 'stretched', because we want the
+        // global settings to span the entire bottom, we need to do that first..
+        egui::TopBottomPanel::bottom("global").exact_height(180.).resizable(false).show(ctx, |ui| {
+            ui.heading("Other Lighting Options");
+            egui::Grid::new("bottom_grid").num_columns(2).min_col_width(200.).show(ui, |ui| {
+                ui.vertical(|ui| {
+                    ui.label("When Muted");
+                    ui.vertical(|ui| {
+                        ui.radio_value(&mut self.state.led.mute_mode, 0, "Do Nothing");
+                        ui.radio_value(&mut self.state.led.mute_mode, 1, "Turn LED ring to a solid colour");
+                        ui.radio_value(&mut self.state.led.mute_mode, 2, "Turn off LED ring");
+                    });
+                    ui.add_space(4.);
+
+                    ui.label("Colour");
+                    ui.color_edit_button_srgb(&mut self.mute_colour);
+                });
+
+                ui.vertical(|ui| {
+                    ui.label("When USB Is Suspended");
+                    ui.vertical(|ui| {
+                        ui.radio_value(&mut self.state.led.suspend_mode, 0, "Do Nothing");
+                        ui.radio_value(&mut self.state.led.suspend_mode, 1, "Turn off LED ring");
+                        ui.radio_value(&mut self.state.led.suspend_mode, 2, "Change the brightness to:");
+                    });
+                    ui.add_space(4.);
+                    let schema = LEDParameter::SuspendBrightness.schema();
+                    ui.add(egui::Slider::new(&mut self.state.led.suspend_brightness, schema.uint_range()).suffix(schema.unit.unwrap_or_default()));
+                });
+                ui.end_row();
+            });
+        });
+
+        // For the others, left first, then right.
+        egui::SidePanel::left("mode").resizable(false).default_width(200.).show(ctx, |ui| {
+            ui.vertical(|ui| {
+                ui.heading("Lighting Style");
+
+                // Our Match for Reactive / Sparkle depend on the active selection, so we'll match
+                // directly if it's active, or have a 'default' if it's not.
+                let reactive_match = match self.state.led.mode {
+                    0x05..=0x07 => self.state.led.mode,
+                    _ => 0x05
+                };
+                let sparkle_match = match self.state.led.mode {
+                    0x0a..=0x0b => self.state.led.mode,
+                    _ => 0x0a
+                };
+
+                if ui.selectable_value(&mut self.state.led.mode, 0x00, "Solid Colour").clicked() {
+                    self.set_mode(0x00);
+                }
+                if ui.selectable_value(&mut self.state.led.mode, 0x03, "Gradient").clicked() {
+                    self.set_mode(0x03);
+                }
+                if ui.selectable_value(&mut self.state.led.mode, reactive_match, "Reactive Meter").clicked() {
+                    self.set_mode(reactive_match);
+                }
+                if ui.selectable_value(&mut self.state.led.mode, sparkle_match, "Solid Sparkle").clicked() {
+                    self.set_mode(sparkle_match);
+                }
+                if ui.selectable_value(&mut self.state.led.mode, 0x01, "Spectrum Cycle").clicked() {
+                    self.set_mode(0x01);
+                }
+
+                ui.add_space(8.);
+                if ui.button("From Image...").clicked() {
+                    self.palette_form.open = true;
+                }
+            });
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            match self.state.led.mode as u8 {
+                0x00 => self.draw_solid_settings(ui),
+                0x01 => self.draw_spectrum_settings(ui),
+                0x03 => self.draw_gradient_settings(ui),
+                0x05..=0x07 => self.draw_reactive_settings(ui),
+                0x0a..=0x0b => self.draw_sparkle_settings(ui),
+                _ => {}
+            }
+        });
+
+        if self.palette_form.open {
+            self.draw_palette_window(ctx);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+use egui::{Color32, Context, Rect, Sense, Ui, vec2};
+use tokio::sync::broadcast::error::TryRecvError;
+use beacn_lib::meter::{ChannelLevel, PeakHold};
+use crate::ui::BeacnApp;
+
+const METER_REFRESH: Duration = Duration::from_millis(50);
+const METER_HEIGHT: f32 = 24.;
+
+impl BeacnApp {
+    fn receive_meters(&mut self) {
+        let receiver = self.meter_receiver.get_or_insert_with(|| self.client.subscribe_meters());
+
+        // Drain everything that's arrived since the last frame, we only need the newest
+        loop {
+            match receiver.try_recv() {
+                Ok(levels) => {
+                    self.mic_peak_hold.update(levels.mic.peak);
+                    self.headphone_peak_hold.update(levels.headphones.peak);
+                    self.meter_levels = Some(levels);
+                }
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn draw_meter(ui: &mut Ui, label: &str, level: ChannelLevel, hold: &PeakHold) {
+        ui.label(format!("{} (RMS {:.1}dB, Peak {:.1}dB)", label, level.rms, hold.value()));
+
+        let (rect, _) = ui.allocate_exact_size(vec2(ui.available_width(), METER_HEIGHT), Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2., Color32::from_gray(40));
+
+        // The peak is drawn first and dimmer, so the RMS level sits inside of it
+        let peak = rect.width() * ChannelLevel::fraction(level.peak);
+        let peak_rect = Rect::from_min_size(rect.min, vec2(peak, rect.height()));
+        painter.rect_filled(peak_rect, 2., Color32::from_rgb(30, 110, 30));
+
+        let rms = rect.width() * ChannelLevel::fraction(level.rms);
+        let rms_rect = Rect::from_min_size(rect.min, vec2(rms, rect.height()));
+        painter.rect_filled(rms_rect, 2., Color32::from_rgb(60, 200, 60));
+
+        let hold_x = rect.min.x + rect.width() * ChannelLevel::fraction(hold.value());
+        painter.vline(hold_x, rect.y_range(), (2., Color32::from_rgb(230, 60, 60)));
+        ui.add_space(8.);
+    }
+
+    pub(crate) fn draw_meters_page(&mut self, ctx: &Context) {
+        self.receive_meters();
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Levels");
+            ui.add_space(4.);
+
+            let levels = self.meter_levels.unwrap_or_default();
+            Self::draw_meter(ui, "Microphone", levels.mic, &self.mic_peak_hold);
+            Self::draw_meter(ui, "Headphones", levels.headphones, &self.headphone_peak_hold);
+        });
+
+        // Meters need to keep moving even if nothing in the UI is being touched
+        ctx.request_repaint_after(METER_REFRESH);
+    }
+}
@@ -0,0 +1,65 @@
+use egui::Context;
+use log::warn;
+use beacn_lib::profile::Profile;
+use crate::ui::BeacnApp;
+
+impl BeacnApp {
+    pub(crate) fn draw_midi_page(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("MIDI");
+            ui.add_space(4.);
+
+            let Some(midi) = self.services.midi.clone() else {
+                ui.label("MIDI is disabled, set 'enabled = true' under [midi] in config.toml to use it.");
+                return;
+            };
+
+            match midi.device() {
+                Some(device) => ui.label(format!("Device: {}", device)),
+                None => ui.label("No MIDI device connected"),
+            };
+            ui.add_space(8.);
+
+            // Anything that could go in a profile can be controlled, which leaves out the meters
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("midi_parameter")
+                    .selected_text(self.midi_parameter.schema().name)
+                    .show_ui(ui, |ui| {
+                        for param in Profile::parameters() {
+                            ui.selectable_value(&mut self.midi_parameter, param, param.schema().name);
+                        }
+                    });
+
+                match midi.learning() {
+                    Some(_) => {
+                        if ui.button("Cancel").clicked() {
+                            midi.learn(None);
+                        }
+                    }
+                    None => {
+                        if ui.button("Learn").clicked() {
+                            midi.learn(Some(self.midi_parameter));
+                        }
+                    }
+                }
+            });
+            if let Some(param) = midi.learning() {
+                ui.label(format!("Move a control or press a button to map it to {}", param.schema().name));
+            }
+            ui.add_space(8.);
+
+            egui::Grid::new("midi_grid").num_columns(3).striped(true).show(ui, |ui| {
+                for mapping in midi.mappings() {
+                    ui.label(mapping.to_string());
+                    ui.label(mapping.parameter.schema().name);
+                    if ui.button("Remove").clicked() {
+                        if let Err(e) = midi.remove(&mapping) {
+                            warn!("Unable to remove MIDI mapping: {:#}", e);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,187 @@
+mod about;
+mod headphones;
+mod lighting;
+mod meters;
+mod midi;
+mod palette;
+mod schedule;
+
+use std::time::Duration;
+use eframe::Frame;
+use egui::Context;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::TryRecvError;
+use beacn_lib::blocking::BlockingClient;
+use beacn_lib::client::BeacnClient;
+use log::warn;
+use beacn_lib::events::DeviceEvent;
+use beacn_lib::messages::{BeacnParameter, BeacnValue, Message, MessageValue, RGB};
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::mic::MicParameter;
+use beacn_lib::messages::Message::SET;
+use beacn_lib::meter::{MeterLevels, PeakHold};
+use beacn_lib::state::DeviceState;
+use crate::services::Services;
+use crate::ui::palette::PaletteForm;
+use crate::ui::schedule::ScheduleForm;
+
+const EVENT_REFRESH: Duration = Duration::from_millis(250);
+
+// If the device takes longer than this, the UI will give up and carry on
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(PartialEq)]
+enum Page {
+    Lighting,
+    Headphones,
+    Meters,
+    Midi,
+    Schedule,
+    About,
+}
+
+pub struct BeacnApp {
+    client: BeacnClient,
+    device: BlockingClient,
+    state: DeviceState,
+    page: Page,
+    events: broadcast::Receiver<DeviceEvent>,
+
+    // We only subscribe to the meters while they're on screen, so the device isn't polled otherwise
+    meter_receiver: Option<broadcast::Receiver<MeterLevels>>,
+    meter_levels: Option<MeterLevels>,
+    mic_peak_hold: PeakHold,
+    headphone_peak_hold: PeakHold,
+
+    // We need to extract the colours to eGUI values.
+    colour1: [u8; 3],
+    colour2: [u8; 3],
+    mute_colour: [u8; 3],
+
+    services: Services,
+    midi_parameter: BeacnParameter,
+    schedule_form: ScheduleForm,
+    palette_form: PaletteForm,
+}
+
+impl BeacnApp {
+    pub fn new(state: DeviceState, client: BeacnClient, services: Services) -> Self {
+        let colour1 = rgb(state.led.colour1);
+        let colour2 = rgb(state.led.colour2);
+        let mute_colour = rgb(state.led.mute_colour);
+
+        Self {
+            events: client.subscribe(),
+            device: client.blocking(RESPONSE_TIMEOUT),
+            client,
+            state,
+            page: Page::Lighting,
+            meter_receiver: None,
+            meter_levels: None,
+            mic_peak_hold: PeakHold::default(),
+            headphone_peak_hold: PeakHold::default(),
+            colour1,
+            colour2,
+            mute_colour,
+            services,
+            midi_parameter: BeacnParameter::LED(LEDParameter::Brightness),
+            schedule_form: ScheduleForm::default(),
+            palette_form: PaletteForm::default(),
+        }
+    }
+
+    fn receive_events(&mut self) {
+        loop {
+            match self.events.try_recv() {
+                Ok(DeviceEvent::MuteChanged(muted)) => self.state.mic.muted = muted,
+
+                // Something else (such as a script or D-Bus) may have changed a value
+                Ok(DeviceEvent::ParameterChanged(param, value)) => {
+                    self.state.set_param(param, value);
+                    self.update_colours();
+                }
+
+                // It may have been changed elsewhere while it was unplugged
+                Ok(DeviceEvent::Connected) => match self.device.fetch_state() {
+                    Ok(state) => {
+                        self.state = state;
+                        self.update_colours();
+                    }
+                    Err(e) => warn!("Unable to reload the state: {}", e),
+                },
+                Ok(_) => continue,
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn update_colours(&mut self) {
+        self.colour1 = rgb(self.state.led.colour1);
+        self.colour2 = rgb(self.state.led.colour2);
+        self.mute_colour = rgb(self.state.led.mute_colour);
+    }
+
+    fn toggle_mute(&mut self) {
+        self.state.mic.muted = !self.state.mic.muted;
+
+        let message = MessageValue::<bool>(self.state.mic.muted);
+        let message = SET((BeacnParameter::Mic(MicParameter::Mute), BeacnValue::from(message)));
+        self.send_message(message);
+    }
+
+    fn send_message(&self, message: Message) -> Option<BeacnValue> {
+        match self.device.send(message) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Failed to Send Message: {}", e);
+                None
+            }
+        }
+    }
+}
+
+fn rgb(colour: RGB) -> [u8; 3] {
+    [colour.red, colour.green, colour.blue]
+}
+
+impl eframe::App for BeacnApp {
+    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        self.receive_events();
+
+        // The page selector sits above everything else, so it needs to be laid out first.
+        egui::TopBottomPanel::top("pages").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.page, Page::Lighting, "Lighting");
+                ui.selectable_value(&mut self.page, Page::Headphones, "Headphones");
+                ui.selectable_value(&mut self.page, Page::Meters, "Meters");
+                ui.selectable_value(&mut self.page, Page::Midi, "MIDI");
+                ui.selectable_value(&mut self.page, Page::Schedule, "Schedule");
+                ui.selectable_value(&mut self.page, Page::About, "About");
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let label = if self.state.mic.muted { "Unmute" } else { "Mute" };
+                    if ui.selectable_label(self.state.mic.muted, label).clicked() {
+                        self.toggle_mute();
+                    }
+                });
+            });
+        });
+
+        // The Mute button on the mic can be pressed at any time, so make sure we notice.
+        ctx.request_repaint_after(EVENT_REFRESH);
+
+        if self.page != Page::Meters {
+            self.meter_receiver = None;
+        }
+
+        match self.page {
+            Page::Lighting => self.draw_lighting_page(ctx),
+            Page::Headphones => self.draw_headphones_page(ctx),
+            Page::Meters => self.draw_meters_page(ctx),
+            Page::Midi => self.draw_midi_page(ctx),
+            Page::Schedule => self.draw_schedule_page(ctx),
+            Page::About => self.draw_about_page(ctx),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+use egui::{Color32, Context, Mesh, Pos2, Rect, Sense, Ui, Vec2};
+use beacn_lib::messages::{BeacnParameter, RGB};
+use crate::palette::{hex, Pairing, Palette};
+use crate::ui::BeacnApp;
+
+const PREVIEW_SIZE: Vec2 = Vec2::new(160., 18.);
+const SWATCH_SIZE: Vec2 = Vec2::new(24., 18.);
+
+// Spots of the secondary colour in the sparkle preview, as fractions of the width and height
+const SPARKLES: &[(f32, f32)] = &[(0.06, 0.3), (0.2, 0.75), (0.33, 0.2), (0.47, 0.6), (0.6, 0.35), (0.74, 0.8), (0.86, 0.25), (0.95, 0.6)];
+
+// The 'From Image' window on the Lighting page
+#[derive(Default)]
+pub struct PaletteForm {
+    pub open: bool,
+    path: String,
+    palette: Option<Palette>,
+    error: Option<String>,
+}
+
+fn colour32(colour: RGB) -> Color32 {
+    Color32::from_rgb(colour.red, colour.green, colour.blue)
+}
+
+fn draw_swatch(ui: &mut Ui, colour: RGB) {
+    let (rect, response) = ui.allocate_exact_size(SWATCH_SIZE, Sense::hover());
+    ui.painter().rect_filled(rect, 2., colour32(colour));
+    response.on_hover_text(hex(colour));
+}
+
+// Roughly how the ring will look with the pairing applied
+fn draw_preview(ui: &mut Ui, pairing: &Pairing) {
+    let (rect, _) = ui.allocate_exact_size(PREVIEW_SIZE, Sense::hover());
+    let (primary, secondary) = (colour32(pairing.colour1), colour32(pairing.colour2));
+
+    if pairing.mode == "gradient" {
+        let mut mesh = Mesh::default();
+        mesh.colored_vertex(rect.left_top(), primary);
+        mesh.colored_vertex(rect.right_top(), secondary);
+        mesh.colored_vertex(rect.right_bottom(), secondary);
+        mesh.colored_vertex(rect.left_bottom(), primary);
+        mesh.add_triangle(0, 1, 2);
+        mesh.add_triangle(0, 2, 3);
+        ui.painter().add(mesh);
+        return;
+    }
+
+    ui.painter().rect_filled(rect, 2., primary);
+    for (x, y) in SPARKLES {
+        let centre = Pos2::new(rect.left() + rect.width() * x, rect.top() + rect.height() * y);
+        ui.painter().rect_filled(Rect::from_center_size(centre, Vec2::splat(4.)), 1., secondary);
+    }
+}
+
+impl BeacnApp {
+    pub(crate) fn draw_palette_window(&mut self, ctx: &Context) {
+        let mut open = self.palette_form.open;
+
+        egui::Window::new("Colours From Image").open(&mut open).resizable(false).collapsible(false).show(ctx, |ui| {
+            // Dropping a picture on the window is quicker than typing its path
+            let dropped = ctx.input(|input| input.raw.dropped_files.iter().find_map(|file| file.path.clone()));
+            if let Some(path) = &dropped {
+                self.palette_form.path = path.display().to_string();
+            }
+
+            ui.label("A PNG or JPEG image (or drop one here)");
+            let load = ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.palette_form.path);
+                ui.button("Load").clicked()
+            }).inner;
+            if load || dropped.is_some() {
+                match Palette::from_image(&PathBuf::from(self.palette_form.path.trim())) {
+                    Ok(palette) => {
+                        self.palette_form.palette = Some(palette);
+                        self.palette_form.error = None;
+                    }
+                    Err(e) => {
+                        self.palette_form.palette = None;
+                        self.palette_form.error = Some(format!("{:#}", e));
+                    }
+                }
+            }
+            if let Some(error) = &self.palette_form.error {
+                ui.colored_label(Color32::RED, error);
+            }
+
+            let Some(palette) = &self.palette_form.palette else {
+                return;
+            };
+            ui.add_space(4.);
+            ui.horizontal(|ui| {
+                for swatch in &palette.swatches {
+                    draw_swatch(ui, swatch.colour);
+                }
+            });
+            ui.add_space(8.);
+
+            let mut chosen = None;
+            egui::Grid::new("palette_grid").num_columns(3).show(ui, |ui| {
+                for pairing in &palette.pairings {
+                    draw_preview(ui, pairing);
+                    ui.label(pairing.mode);
+                    if ui.button("Apply").clicked() {
+                        chosen = Some(*pairing);
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if let Some(pairing) = chosen {
+                self.apply_pairing(&pairing);
+            }
+        });
+        self.palette_form.open = open;
+    }
+
+    fn apply_pairing(&mut self, pairing: &Pairing) {
+        let values = match pairing.values() {
+            Ok(values) => values,
+            Err(e) => {
+                self.palette_form.error = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        for (param, value) in values {
+            let param = BeacnParameter::LED(param);
+            match param.schema().encode(value).and_then(|value| self.device.set(param, value)) {
+                Ok(value) => self.state.set_param(param, value),
+                Err(e) => {
+                    self.palette_form.error = Some(format!("{:#}", e));
+                    break;
+                }
+            }
+        }
+        self.update_colours();
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::{Days, Local};
+use egui::Context;
+use log::warn;
+use beacn_lib::messages::led::LEDParameter;
+use beacn_lib::messages::schema::ParameterValue;
+use beacn_lib::profile::Profile;
+use crate::config::LightingOverlay;
+use crate::services::schedule::{parse_time, Action, WEEK};
+use crate::ui::BeacnApp;
+
+// What's been entered for the next action to add
+pub struct ScheduleForm {
+    time: String,
+    days: [bool; 7],
+    profile: Option<String>,
+
+    // Takes whatever the ring is currently showing, so it can be set up on the Lighting page first
+    lighting: bool,
+    error: Option<String>,
+}
+
+impl Default for ScheduleForm {
+    fn default() -> Self {
+        Self {
+            time: String::from("19:00"),
+            days: [true; 7],
+            profile: None,
+            lighting: false,
+            error: None,
+        }
+    }
+}
+
+impl BeacnApp {
+    pub(crate) fn draw_schedule_page(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Schedule");
+            ui.add_space(4.);
+
+            let Some(schedule) = self.services.schedule.clone() else {
+                ui.label("The schedule is disabled, set 'enabled = true' under [schedule] in config.toml to use it.");
+                return;
+            };
+
+            let now = Local::now();
+            let today = now.date_naive();
+            let upcoming = schedule.upcoming(now);
+            if upcoming.is_empty() {
+                ui.label("Nothing is scheduled");
+            }
+
+            egui::Grid::new("schedule_grid").num_columns(4).striped(true).show(ui, |ui| {
+                for (time, action) in upcoming {
+                    let date = time.date_naive();
+                    let when = if date == today {
+                        time.format("Today %H:%M").to_string()
+                    } else if date == today + Days::new(1) {
+                        time.format("Tomorrow %H:%M").to_string()
+                    } else {
+                        time.format("%a %H:%M").to_string()
+                    };
+
+                    ui.label(when);
+                    ui.label(action.days_label());
+                    ui.label(action.to_string());
+                    if ui.button("Remove").clicked() {
+                        if let Err(e) = schedule.remove(&action) {
+                            warn!("Unable to remove scheduled action: {:#}", e);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.add_space(8.);
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("At");
+                ui.add(egui::TextEdit::singleline(&mut self.schedule_form.time).desired_width(50.));
+                for (day, selected) in WEEK.iter().zip(self.schedule_form.days.iter_mut()) {
+                    ui.toggle_value(selected, day.to_string());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Apply");
+                let selected = self.schedule_form.profile.clone().unwrap_or_else(|| String::from("No profile"));
+                egui::ComboBox::from_id_salt("schedule_profile").selected_text(selected).show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.schedule_form.profile, None, "No profile");
+                    for name in Profile::list_named().unwrap_or_default() {
+                        ui.selectable_value(&mut self.schedule_form.profile, Some(name.clone()), name);
+                    }
+                });
+                ui.checkbox(&mut self.schedule_form.lighting, "and the current lighting");
+            });
+
+            if ui.button("Add").clicked() {
+                self.schedule_form.error = self.schedule_action().and_then(|action| schedule.add(action)).err().map(|e| format!("{:#}", e));
+            }
+            if let Some(error) = &self.schedule_form.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        });
+    }
+
+    fn schedule_action(&self) -> anyhow::Result<Action> {
+        let form = &self.schedule_form;
+        let led = &self.state.led;
+        let format = |param: LEDParameter, value| Some(param.schema().format(value));
+
+        let lighting = match form.lighting {
+            true => LightingOverlay {
+                mode: format(LEDParameter::Mode, ParameterValue::UInt(led.mode)),
+                colour1: format(LEDParameter::Colour1, ParameterValue::Colour(led.colour1)),
+                colour2: format(LEDParameter::Colour2, ParameterValue::Colour(led.colour2)),
+                brightness: Some(led.brightness),
+            },
+            false => LightingOverlay::default(),
+        };
+
+        // Every day is stored as no days at all, which keeps schedule.toml tidy
+        let days = WEEK.iter().zip(form.days).filter(|(_, selected)| *selected).map(|(day, _)| *day).collect::<Vec<_>>();
+        if days.is_empty() {
+            anyhow::bail!("Pick at least one day");
+        }
+        let days = if days.len() == WEEK.len() { vec![] } else { days };
+
+        Ok(Action { time: parse_time(&form.time)?, days, profile: form.profile.clone(), lighting })
+    }
+}